@@ -0,0 +1,32 @@
+//! Accessibility permission.
+//!
+//! Reading the selection through AX, and posting the copy shortcut, need the
+//! process to be trusted under System Settings > Privacy & Security >
+//! Accessibility. Without it AX calls fail as if nothing had focus.
+
+use accessibility_sys_ng::{
+    kAXTrustedCheckOptionPrompt, AXIsProcessTrusted, AXIsProcessTrustedWithOptions,
+};
+use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::string::CFString;
+
+/// Whether the process is trusted for Accessibility.
+pub fn is_trusted() -> bool {
+    unsafe { AXIsProcessTrusted() }
+}
+
+/// `is_trusted`, and when it isn't and `prompt` is set, has macOS ask the
+/// user to grant access. The prompt doesn't wait for an answer: the result
+/// is `false` until the user allows it in System Settings.
+pub fn request_trust(prompt: bool) -> bool {
+    let key = unsafe { CFString::wrap_under_get_rule(kAXTrustedCheckOptionPrompt) };
+    let value = if prompt {
+        CFBoolean::true_value()
+    } else {
+        CFBoolean::false_value()
+    };
+    let options = CFDictionary::from_CFType_pairs(&[(key, value)]);
+    unsafe { AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef()) }
+}
@@ -0,0 +1,141 @@
+// AX reports a selection as a location and a length, which loses which end
+// the user dragged or shift-extended. The insertion point's line still
+// tells: it sits at the active end, so on a selection spanning lines it
+// gives away the direction.
+
+use std::ops::Range;
+
+use accessibility_ng::{AXAttribute, AXUIElement};
+use accessibility_sys_ng::{
+    kAXInsertionPointLineNumberAttribute, kAXLineForIndexParameterizedAttribute,
+};
+use core_foundation::{base::TCFType, number::CFNumber, string::CFString};
+
+use crate::{ax, policy, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum SelectionDirection {
+    /// Made from left to right, the active end is the end of the range.
+    Forward,
+    /// Made from right to left, the active end is its start.
+    Backward,
+}
+
+/// Where a selection is anchored and which end moves when it's extended.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SelectionAnchor {
+    /// UTF-16 range, as `get_selected_range` returns it.
+    pub range: Range<usize>,
+    /// `None` when the selection is empty or on a single line, where AX
+    /// doesn't give it away.
+    pub direction: Option<SelectionDirection>,
+    /// UTF-16 offset of the end that moves, the insertion point for an
+    /// empty selection.
+    pub active_end: Option<usize>,
+}
+
+/// The focused element's selection with its direction, when AX reveals it.
+pub fn get_selection_anchor() -> Result<SelectionAnchor> {
+    policy::ensure_capture_allowed()?;
+    let element = ax::focused_element()?;
+    let range = ax::selected_range(&element)?;
+    let start = range.location.max(0) as usize;
+    let range = start..start + range.length.max(0) as usize;
+    if range.is_empty() {
+        return Ok(SelectionAnchor {
+            active_end: Some(range.start),
+            range,
+            direction: None,
+        });
+    }
+    let direction = insertion_line(&element).and_then(|insertion_line| {
+        let start_line = line_for_index(&element, range.start)?;
+        // the last selected character, the end itself can be on the next line
+        let end_line = line_for_index(&element, range.end - 1)?;
+        let after_end_line = line_for_index(&element, range.end);
+        direction_from_lines(start_line, end_line, after_end_line, insertion_line)
+    });
+    let active_end = direction.map(|direction| match direction {
+        SelectionDirection::Forward => range.end,
+        SelectionDirection::Backward => range.start,
+    });
+    Ok(SelectionAnchor {
+        range,
+        direction,
+        active_end,
+    })
+}
+
+fn direction_from_lines(
+    start_line: i64,
+    end_line: i64,
+    after_end_line: Option<i64>,
+    insertion_line: i64,
+) -> Option<SelectionDirection> {
+    // after a selection ending with a newline the caret is on the next line
+    if after_end_line == Some(insertion_line) && insertion_line != start_line {
+        return Some(SelectionDirection::Forward);
+    }
+    if start_line == end_line {
+        return None;
+    }
+    if insertion_line == start_line {
+        Some(SelectionDirection::Backward)
+    } else if insertion_line == end_line {
+        Some(SelectionDirection::Forward)
+    } else {
+        None
+    }
+}
+
+fn insertion_line(element: &AXUIElement) -> Option<i64> {
+    element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXInsertionPointLineNumberAttribute,
+        )))
+        .ok()?
+        .downcast_into::<CFNumber>()?
+        .to_i64()
+}
+
+fn line_for_index(element: &AXUIElement, index: usize) -> Option<i64> {
+    let index = CFNumber::from(i64::try_from(index).ok()?);
+    element
+        .parameterized_attribute(
+            &AXAttribute::new(&CFString::from_static_string(
+                kAXLineForIndexParameterizedAttribute,
+            )),
+            &index.as_CFType(),
+        )
+        .ok()?
+        .downcast_into::<CFNumber>()?
+        .to_i64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_from_lines() {
+        assert_eq!(
+            direction_from_lines(2, 5, Some(5), 5),
+            Some(SelectionDirection::Forward)
+        );
+        assert_eq!(
+            direction_from_lines(2, 5, Some(6), 2),
+            Some(SelectionDirection::Backward)
+        );
+        assert_eq!(
+            direction_from_lines(2, 5, Some(6), 6),
+            Some(SelectionDirection::Forward)
+        );
+        assert_eq!(
+            direction_from_lines(3, 3, Some(4), 4),
+            Some(SelectionDirection::Forward)
+        );
+        assert_eq!(direction_from_lines(3, 3, None, 3), None);
+        // a stale insertion point elsewhere
+        assert_eq!(direction_from_lines(2, 5, None, 9), None);
+    }
+}
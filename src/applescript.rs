@@ -0,0 +1,156 @@
+// Strings going into AppleScript source, and coming back out of our scripts,
+// are quoted and escaped here in one place. Escaping only quotes corrupted
+// file names containing a backslash.
+
+use crate::SelectedTextError;
+
+/// AppleScript handler our scripts print strings with, paste it after the
+/// script's `end tell`. `unquote` reads its output back.
+pub(crate) const QUOTE_HANDLER: &str = r#"
+on quote_text(this_text)
+	set this_text to my replace_chars(this_text, "\\", "\\\\")
+	set this_text to my replace_chars(this_text, "\"", "\\\"")
+	set this_text to my replace_chars(this_text, linefeed, "\\n")
+	set this_text to my replace_chars(this_text, return, "\\r")
+	set this_text to my replace_chars(this_text, tab, "\\t")
+	return "\"" & this_text & "\""
+end quote_text
+
+on replace_chars(this_text, search_string, replacement_string)
+	set AppleScript's text item delimiters to the search_string
+	set the item_list to every text item of this_text
+	set AppleScript's text item delimiters to the replacement_string
+	set this_text to the item_list as string
+	set AppleScript's text item delimiters to ""
+	return this_text
+end replace_chars
+"#;
+
+/// `s` as an AppleScript string literal.
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads back a string `quote` or `QUOTE_HANDLER` wrote. Unquoted input is
+/// only trimmed.
+pub(crate) fn unquote(s: &str) -> String {
+    // only ASCII escapes are replaced, the result stays UTF-8
+    String::from_utf8(unquote_bytes(s.as_bytes()))
+        .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+/// `unquote` for output that isn't UTF-8.
+pub(crate) fn unquote_bytes(s: &[u8]) -> Vec<u8> {
+    let s = s.trim_ascii();
+    let Some(inner) = s.strip_prefix(b"\"").and_then(|s| s.strip_suffix(b"\"")) else {
+        return s.to_vec();
+    };
+    let mut unquoted = Vec::with_capacity(inner.len());
+    let mut bytes = inner.iter().copied();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            unquoted.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => unquoted.push(b'\n'),
+            Some(b'r') => unquoted.push(b'\r'),
+            Some(b't') => unquoted.push(b'\t'),
+            Some(escaped @ (b'"' | b'\\')) => unquoted.push(escaped),
+            Some(other) => unquoted.extend([b'\\', other]),
+            None => unquoted.push(b'\\'),
+        }
+    }
+    unquoted
+}
+
+/// Runs `script` and returns what it printed. `target` is the app the script
+/// tells, named in `SelectedTextError::AutomationDenied`.
+pub(crate) fn run(script: &str, target: &str) -> anyhow::Result<Vec<u8>> {
+    let output = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()?;
+    if !output.status.success() {
+        return Err(error_from_stderr(&output.stderr, target));
+    }
+    Ok(output.stdout)
+}
+
+pub(crate) fn error_from_stderr(stderr: &[u8], target: &str) -> anyhow::Error {
+    let stderr = stderr.iter().map(|&c| c as char).collect::<String>();
+    SelectedTextError::from_osascript_stderr(&stderr, target)
+        .unwrap_or_else(|| SelectedTextError::AppleScriptFailed {
+            stderr: stderr.trim().to_owned(),
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("c"), "\"c\"");
+        assert_eq!(quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote("C:\\dir\n"), "\"C:\\\\dir\\n\"");
+        assert_eq!(
+            unquote("\"/Users/me/back\\\\slash.txt\""),
+            "/Users/me/back\\slash.txt"
+        );
+        assert_eq!(unquote("  plain  "), "plain");
+    }
+
+    // property: any string survives quoting, including the characters that
+    // need escapes and multi-byte ones
+    #[test]
+    fn test_quote_round_trips() {
+        const ALPHABET: &[&str] = &[
+            "a",
+            "Z",
+            " ",
+            "\"",
+            "\\",
+            "\n",
+            "\r",
+            "\t",
+            "/",
+            "é",
+            "😀",
+            "👩‍👩‍👧",
+            "\\\"",
+            "n",
+        ];
+        // xorshift, deterministic so a failure reproduces
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..2000 {
+            let len = next() % 12;
+            let s: String = (0..len)
+                .map(|_| ALPHABET[next() as usize % ALPHABET.len()])
+                .collect();
+            let quoted = quote(&s);
+            assert!(!quoted.contains('\n'), "{:?}", quoted);
+            assert_eq!(unquote(&quoted), s, "{:?}", quoted);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// A clipboard manager, or any app, can write to the pasteboard while we wait
+// for the copy to land. Before trusting a change we check it plausibly came
+// from the app we sent the copy to.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use objc2_app_kit::{NSPasteboard, NSPasteboardTypeString, NSWorkspace};
+use objc2_foundation::NSString;
+
+use crate::CopyMethod;
+
+// http://nspasteboard.org, written by well-behaved clipboard tools
+const SOURCE_TYPE: &str = "org.nspasteboard.source";
+const AUTO_GENERATED_TYPE: &str = "org.nspasteboard.AutoGeneratedType";
+
+struct CopyAttempt {
+    change_count: isize,
+    method: CopyMethod,
+    pid: Option<i32>,
+    bundle_id: Option<String>,
+    previous_text: Option<u64>,
+}
+
+static LAST_ATTEMPT: Mutex<Option<CopyAttempt>> = Mutex::new(None);
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Attribution {
+    Ours,
+    /// Same text as before the copy, the app may not have written yet, or the
+    /// selection is what was already on the pasteboard.
+    Unchanged,
+    Foreign(&'static str),
+}
+
+/// Remembers the frontmost app and pasteboard before a copy is sent.
+pub(crate) fn record_attempt(pasteboard: &NSPasteboard, change_count: isize, method: CopyMethod) {
+    let (pid, bundle_id) = frontmost_app();
+    *LAST_ATTEMPT.lock().unwrap() = Some(CopyAttempt {
+        change_count,
+        method,
+        pid,
+        bundle_id,
+        previous_text: text_hash(pasteboard),
+    });
+}
+
+/// How the copy sent when the change count was `saved_change_count` was
+/// made, `None` without a record of it.
+pub(crate) fn copy_method(saved_change_count: isize) -> Option<CopyMethod> {
+    let attempt = LAST_ATTEMPT.lock().unwrap();
+    attempt
+        .as_ref()
+        .filter(|attempt| attempt.change_count == saved_change_count)
+        .map(|attempt| attempt.method)
+}
+
+/// Whether the pasteboard's current contents came from the copy that was
+/// sent when its change count was `saved_change_count`. Changes we have no
+/// record for are trusted.
+pub(crate) fn attribute(pasteboard: &NSPasteboard, saved_change_count: isize) -> Attribution {
+    let attempt = LAST_ATTEMPT.lock().unwrap();
+    let Some(attempt) = attempt
+        .as_ref()
+        .filter(|attempt| attempt.change_count == saved_change_count)
+    else {
+        return Attribution::Ours;
+    };
+    let (pid, _) = frontmost_app();
+    if pid != attempt.pid {
+        return Attribution::Foreign("the frontmost app changed");
+    }
+    if has_type(pasteboard, AUTO_GENERATED_TYPE) {
+        return Attribution::Foreign("it was generated by a tool");
+    }
+    let source = unsafe { pasteboard.stringForType(&NSString::from_str(SOURCE_TYPE)) };
+    if let (Some(source), Some(bundle_id)) = (source, &attempt.bundle_id) {
+        if !source.to_string().eq_ignore_ascii_case(bundle_id) {
+            return Attribution::Foreign("another app says it wrote it");
+        }
+    }
+    if attempt.previous_text.is_some() && text_hash(pasteboard) == attempt.previous_text {
+        return Attribution::Unchanged;
+    }
+    Attribution::Ours
+}
+
+fn frontmost_app() -> (Option<i32>, Option<String>) {
+    let workspace = unsafe { NSWorkspace::sharedWorkspace() };
+    let Some(app) = (unsafe { workspace.frontmostApplication() }) else {
+        return (None, None);
+    };
+    let bundle_id = unsafe { app.bundleIdentifier() }.map(|id| id.to_string());
+    (Some(unsafe { app.processIdentifier() }), bundle_id)
+}
+
+fn has_type(pasteboard: &NSPasteboard, name: &str) -> bool {
+    unsafe { pasteboard.types() }.is_some_and(|types| types.iter().any(|t| t.to_string() == name))
+}
+
+// only a hash is kept so a large clipboard isn't held in memory
+fn text_hash(pasteboard: &NSPasteboard) -> Option<u64> {
+    let text = unsafe { pasteboard.stringForType(NSPasteboardTypeString) }?;
+    let mut hasher = DefaultHasher::new();
+    text.to_string().hash(&mut hasher);
+    Some(hasher.finish())
+}
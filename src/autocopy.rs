@@ -0,0 +1,106 @@
+// Apps that put the selection on the pasteboard as soon as it's made
+// (iTerm2's "copy on select", X11 apps through XQuartz) don't need a Cmd+C,
+// and sending one can beep or, in some terminals, clear the selection.
+
+use std::sync::{Mutex, RwLock};
+
+const DEFAULT_AUTO_COPY_APPS: &[&str] = &[
+    "com.googlecode.iterm2",
+    "com.github.wez.wezterm",
+    "org.xquartz.X11",
+    "org.macosforge.xquartz.X11",
+];
+
+/// Copies in a row that left the pasteboard text unchanged before an app is
+/// treated as copying on select.
+const LEARN_AFTER: u32 = 3;
+
+static AUTO_COPY_APPS: RwLock<Vec<(String, bool)>> = RwLock::new(Vec::new());
+static UNCHANGED_STREAKS: Mutex<Vec<(String, u32)>> = Mutex::new(Vec::new());
+static LAST_CHANGE_COUNT: Mutex<Option<isize>> = Mutex::new(None);
+
+/// Marks the app with `bundle_id` as copying its selection on its own (or
+/// not), overriding the built-in list and what was learned.
+pub fn set_auto_copy_app(bundle_id: &str, auto_copies: bool) {
+    let mut apps = AUTO_COPY_APPS.write().unwrap();
+    apps.retain(|(id, _)| !id.eq_ignore_ascii_case(bundle_id));
+    apps.push((bundle_id.to_owned(), auto_copies));
+}
+
+pub(crate) fn auto_copies(bundle_id: &str) -> bool {
+    let configured = AUTO_COPY_APPS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .map(|(_, auto_copies)| *auto_copies);
+    #[cfg(feature = "app-config")]
+    let configured = configured
+        .or_else(|| crate::config::app_setting(bundle_id, |settings| settings.auto_copies));
+    if let Some(auto_copies) = configured {
+        return auto_copies;
+    }
+    DEFAULT_AUTO_COPY_APPS
+        .iter()
+        .any(|id| id.eq_ignore_ascii_case(bundle_id))
+        || UNCHANGED_STREAKS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(id, streak)| id.eq_ignore_ascii_case(bundle_id) && *streak >= LEARN_AFTER)
+}
+
+/// Records whether a copy in the app only rewrote what was already there.
+pub(crate) fn note_copy(bundle_id: &str, unchanged: bool) {
+    let mut streaks = UNCHANGED_STREAKS.lock().unwrap();
+    match streaks
+        .iter_mut()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+    {
+        Some((_, streak)) if unchanged => *streak += 1,
+        Some((_, streak)) => *streak = 0,
+        None if unchanged => streaks.push((bundle_id.to_owned(), 1)),
+        None => {}
+    }
+}
+
+/// Remembers the pasteboard state a capture left behind.
+pub(crate) fn note_change_count(change_count: isize) {
+    *LAST_CHANGE_COUNT.lock().unwrap() = Some(change_count);
+}
+
+/// Whether something wrote to the pasteboard since the last capture, a
+/// stale pasteboard holds an older selection (or anything else).
+pub(crate) fn changed_since_last_capture(change_count: isize) -> bool {
+    LAST_CHANGE_COUNT
+        .lock()
+        .unwrap()
+        .is_some_and(|last| last != change_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_copy_apps() {
+        assert!(auto_copies("com.googlecode.iTerm2"));
+        assert!(!auto_copies("com.example.Editor"));
+        for _ in 0..LEARN_AFTER {
+            note_copy("com.example.Editor", true);
+        }
+        assert!(auto_copies("com.example.Editor"));
+        note_copy("com.example.Editor", false);
+        assert!(!auto_copies("com.example.Editor"));
+        set_auto_copy_app("com.googlecode.iterm2", false);
+        assert!(!auto_copies("com.googlecode.iterm2"));
+    }
+
+    #[test]
+    fn test_changed_since_last_capture() {
+        assert!(!changed_since_last_capture(7));
+        note_change_count(7);
+        assert!(!changed_since_last_capture(7));
+        assert!(changed_since_last_capture(8));
+    }
+}
@@ -0,0 +1,442 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use accessibility_ng::{AXAttribute, AXUIElement, AXUIElementAttributes, AXValue};
+use accessibility_sys_ng::{
+    kAXFocusedApplicationAttribute, kAXFocusedUIElementAttribute, kAXFontNameKey, kAXFontSizeKey,
+    kAXFontTextAttribute, kAXLinkTextAttribute, kAXMenuBarAttribute, kAXPressAction,
+    kAXSelectedTextAttribute, kAXStringForRangeParameterizedAttribute, kAXURLAttribute,
+};
+use anyhow::{anyhow, bail};
+use core_foundation::{
+    attributed_string::{
+        CFAttributedString, CFAttributedStringGetAttributes, CFAttributedStringGetLength,
+        CFAttributedStringGetString,
+    },
+    base::{CFRange, CFType, TCFType},
+    boolean::CFBoolean,
+    dictionary::CFDictionary,
+    number::CFNumber,
+    string::{CFString, CFStringCreateWithSubstring},
+    url::CFURL,
+};
+use core_graphics::geometry::CGRect;
+
+use crate::{accessibility, diagnostics, policy, Result, SelectedTextError, StyledRun};
+
+pub(crate) fn focused_element() -> anyhow::Result<AXUIElement> {
+    let system_element = AXUIElement::system_wide();
+    let Some(focused_element) = system_element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXFocusedUIElementAttribute,
+        )))
+        .map(|element| element.downcast_into::<AXUIElement>())
+        .ok()
+        .flatten()
+    else {
+        // untrusted, AX reports no focus rather than an error
+        if !accessibility::is_trusted() {
+            return Err(SelectedTextError::AccessibilityDenied.into());
+        }
+        return Err(SelectedTextError::NoFocusedElement.into());
+    };
+    Ok(focused_element)
+}
+
+/// Presses the frontmost app's Copy menu item, found by its Cmd+C key
+/// equivalent or, failing that, an untranslated "Copy" title.
+pub(crate) fn press_copy_menu_item() -> anyhow::Result<()> {
+    let system_element = AXUIElement::system_wide();
+    let Some(menu_bar) = system_element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXFocusedApplicationAttribute,
+        )))
+        .ok()
+        .and_then(|app| app.downcast_into::<AXUIElement>())
+        .and_then(|app| {
+            app.attribute(&AXAttribute::new(&CFString::from_static_string(
+                kAXMenuBarAttribute,
+            )))
+            .ok()
+        })
+        .and_then(|menu_bar| menu_bar.downcast_into::<AXUIElement>())
+    else {
+        bail!("Frontmost app has no menu bar");
+    };
+    let Some(item) = find_menu_item(&menu_bar, is_copy_by_shortcut)
+        .or_else(|| find_menu_item(&menu_bar, is_copy_by_title))
+    else {
+        bail!("No Copy menu item");
+    };
+    // disabled means there's nothing to copy, pressing would only beep
+    if item.enabled().is_ok_and(|enabled| !bool::from(enabled)) {
+        bail!("Copy menu item is disabled");
+    }
+    item.perform_action(&CFString::from_static_string(kAXPressAction))
+        .map_err(|e| anyhow!("Failed to press Copy menu item: {:?}", e))
+}
+
+// menu bar > bar items > menus > items, Copy is never in a submenu
+fn find_menu_item(
+    menu_bar: &AXUIElement,
+    matches: fn(&AXUIElement) -> bool,
+) -> Option<AXUIElement> {
+    let bar_items = menu_bar.children().ok()?;
+    bar_items.iter().find_map(|bar_item| {
+        let menus = bar_item.children().ok()?;
+        menus.iter().find_map(|menu| {
+            let items = menu.children().ok()?;
+            items
+                .iter()
+                .find(|item| matches(item))
+                .map(|item| item.clone())
+        })
+    })
+}
+
+fn is_copy_by_shortcut(item: &AXUIElement) -> bool {
+    // a modifier mask of 0 means Command alone
+    item.menu_item_cmd_char()
+        .is_ok_and(|c| c.to_string().eq_ignore_ascii_case("c"))
+        && item
+            .menu_item_cmd_modifier()
+            .ok()
+            .and_then(|modifiers| modifiers.to_i64())
+            == Some(0)
+}
+
+fn is_copy_by_title(item: &AXUIElement) -> bool {
+    item.title().is_ok_and(|title| title == "Copy")
+}
+
+/// The AX element of the app with keyboard focus.
+pub(crate) fn focused_app() -> anyhow::Result<AXUIElement> {
+    AXUIElement::system_wide()
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXFocusedApplicationAttribute,
+        )))
+        .ok()
+        .and_then(|app| app.downcast_into::<AXUIElement>())
+        .ok_or_else(|| SelectedTextError::NoFocusedElement.into())
+}
+
+/// Makes Electron and Chromium apps build their accessibility tree, which
+/// they otherwise only do for screen readers.
+pub(crate) const MANUAL_ACCESSIBILITY: &str = "AXManualAccessibility";
+/// Turns on the accessibility of apps that wait for an assistive app, like
+/// Java's AX bridge in JetBrains IDEs. Also disables some window animations.
+pub(crate) const ENHANCED_USER_INTERFACE: &str = "AXEnhancedUserInterface";
+
+static ENABLED_APP_ATTRIBUTES: Mutex<Vec<(i32, &'static str)>> = Mutex::new(Vec::new());
+
+/// Sets the boolean app attribute `name` once per app. Returns whether it was
+/// just set, the app's tree then takes a moment to appear.
+pub(crate) fn enable_app_attribute(app: &AXUIElement, name: &'static str) -> bool {
+    let Ok(pid) = app.pid() else {
+        return false;
+    };
+    let mut enabled = ENABLED_APP_ATTRIBUTES.lock().unwrap();
+    if enabled.contains(&(pid, name)) {
+        return false;
+    }
+    let attribute = AXAttribute::new(&CFString::from_static_string(name));
+    if let Err(e) = app.set_attribute(&attribute, CFBoolean::true_value().as_CFType()) {
+        diagnostics::debug!("{} not settable in {}: {:?}", name, pid, e);
+        return false;
+    }
+    enabled.push((pid, name));
+    true
+}
+
+/// `element` or its closest ancestor matching `matches`, at most `depth`
+/// levels up.
+pub(crate) fn self_or_ancestor(
+    element: AXUIElement,
+    depth: usize,
+    matches: impl Fn(&AXUIElement) -> bool,
+) -> Option<AXUIElement> {
+    let mut element = element;
+    for _ in 0..=depth {
+        if matches(&element) {
+            return Some(element);
+        }
+        element = element.parent().ok()?;
+    }
+    None
+}
+
+/// First element under `element` matching `matches`, breadth first, looking
+/// at no more than `budget` elements.
+pub(crate) fn find_descendant(
+    element: &AXUIElement,
+    budget: usize,
+    matches: impl Fn(&AXUIElement) -> bool,
+) -> Option<AXUIElement> {
+    let mut queue = std::collections::VecDeque::from([element.clone()]);
+    let mut seen = 0;
+    while let Some(element) = queue.pop_front() {
+        if matches(&element) {
+            return Some(element);
+        }
+        seen += 1;
+        if seen >= budget {
+            return None;
+        }
+        if let Ok(children) = element.children() {
+            queue.extend(children.iter().map(|child| child.clone()));
+        }
+    }
+    None
+}
+
+/// Text of `range` in `element`, through `AXStringForRange`.
+pub(crate) fn string_for_range(element: &AXUIElement, range: CFRange) -> Option<String> {
+    let parameter = AXValue::from_CFRange(range).ok()?;
+    let attribute = AXAttribute::new(&CFString::from_static_string(
+        kAXStringForRangeParameterizedAttribute,
+    ));
+    let string = element
+        .parameterized_attribute(&attribute, &parameter)
+        .ok()?
+        .downcast_into::<CFString>()?;
+    Some(string.to_string())
+}
+
+pub(crate) fn role(element: &AXUIElement) -> Option<String> {
+    element.role().ok().map(|role| role.to_string())
+}
+
+pub(crate) fn focused_pid() -> Option<i32> {
+    focused_element().ok()?.pid().ok()
+}
+
+pub(crate) fn selected_text() -> anyhow::Result<CFString> {
+    let element = focused_element()?;
+    let selected_text = element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXSelectedTextAttribute,
+        )))
+        .map(|text| text.downcast_into::<CFString>())
+        .ok()
+        .flatten();
+    // some apps report the selected range but no (or empty) selected text
+    let range = match selected_text {
+        Some(text) if text.char_len() > 0 => return Ok(text),
+        _ => selected_range(&element)
+            .ok()
+            .filter(|range| range.length > 0),
+    };
+    let Some(range) = range else {
+        return selected_text.ok_or_else(|| SelectedTextError::NoSelection.into());
+    };
+    text_for_range(&element, range)
+        .map(|text| CFString::new(&text))
+        .ok_or_else(|| SelectedTextError::NoSelection.into())
+}
+
+/// `string_for_range`, or the plain text of `AXAttributedStringForRange`
+/// for apps that only answer that one. `None` when both come back empty.
+fn text_for_range(element: &AXUIElement, range: CFRange) -> Option<String> {
+    let text = string_for_range(element, range).filter(|text| !text.is_empty());
+    text.or_else(|| {
+        let parameter = AXValue::from_CFRange(range).ok()?;
+        let attributed = element
+            .parameterized_attribute(&AXAttribute::attributed_string_for_range(), &parameter)
+            .ok()?;
+        let string = unsafe {
+            CFString::wrap_under_get_rule(CFAttributedStringGetString(
+                attributed.as_concrete_TypeRef(),
+            ))
+        };
+        Some(string.to_string()).filter(|text| !text.is_empty())
+    })
+    .inspect(|_| diagnostics::debug!("read the selection from its range"))
+}
+
+pub(crate) fn selected_range(element: &AXUIElement) -> anyhow::Result<CFRange> {
+    let range = element
+        .selected_text_range()
+        .map_err(|e| anyhow!("No selected text range: {:?}", e))?;
+    range
+        .get_value::<CFRange>()
+        .map_err(|e| anyhow!("Selected text range is not a CFRange: {:?}", e))
+}
+
+/// Whether the focused element reports an empty selection, a copy would only
+/// beep and leave the pasteboard alone. `false` when it can't tell.
+pub(crate) fn selection_is_empty() -> bool {
+    focused_element()
+        .and_then(|element| selected_range(&element))
+        .is_ok_and(|range| range.length == 0)
+}
+
+/// UTF-16 range of the focused element's selection, for telling apart equal
+/// text selected in different places.
+pub fn get_selected_range() -> Result<std::ops::Range<usize>> {
+    policy::ensure_capture_allowed()?;
+    let range = selected_range(&focused_element()?)?;
+    let start = range.location.max(0) as usize;
+    Ok(start..start + range.length.max(0) as usize)
+}
+
+/// Screen rectangle of the focused element's selection, in global display
+/// coordinates with the origin at the top left of the main display.
+pub fn get_selection_bounds() -> Result<CGRect> {
+    policy::ensure_capture_allowed()?;
+    Ok(selection_rect()?)
+}
+
+pub(crate) fn selection_rect() -> anyhow::Result<CGRect> {
+    let element = focused_element()?;
+    bounds_for_range(&element, selected_range(&element)?)
+}
+
+pub(crate) fn bounds_for_range(element: &AXUIElement, range: CFRange) -> anyhow::Result<CGRect> {
+    let parameter = AXValue::from_CFRange(range)
+        .map_err(|e| anyhow!("Failed to create range parameter: {:?}", e))?;
+    let bounds = element
+        .parameterized_attribute(&AXAttribute::bounds_for_range(), &parameter)
+        .map_err(|e| anyhow!("No bounds for the range: {:?}", e))?;
+    bounds
+        .get_value::<CGRect>()
+        .map_err(|e| anyhow!("Range bounds are not a CGRect: {:?}", e))
+}
+
+/// Reads the formatting of the focused element's selection through
+/// `AXAttributedStringForRange`, without touching the pasteboard.
+pub fn get_selected_styled_runs_by_ax() -> Result<Vec<StyledRun>> {
+    policy::ensure_capture_allowed()?;
+    let element = focused_element()?;
+    let range = selected_range(&element)?;
+    if range.length == 0 {
+        return Err(SelectedTextError::NoSelection);
+    }
+    let parameter = AXValue::from_CFRange(range)
+        .map_err(|e| anyhow!("Failed to create range parameter: {:?}", e))?;
+    let attributed = element
+        .parameterized_attribute(&AXAttribute::attributed_string_for_range(), &parameter)
+        .map_err(|e| anyhow!("No attributed string for the selected range: {:?}", e))?;
+    Ok(styled_runs(&attributed))
+}
+
+fn styled_runs(attributed: &CFAttributedString) -> Vec<StyledRun> {
+    let attributed_ref = attributed.as_concrete_TypeRef();
+    let string_ref = unsafe { CFAttributedStringGetString(attributed_ref) };
+    let length = unsafe { CFAttributedStringGetLength(attributed_ref) };
+
+    let mut runs = Vec::new();
+    let mut location = 0;
+    while location < length {
+        let mut effective_range = CFRange::init(location, 0);
+        let attributes: CFDictionary<CFString, CFType> = unsafe {
+            CFDictionary::wrap_under_get_rule(CFAttributedStringGetAttributes(
+                attributed_ref,
+                location,
+                &mut effective_range,
+            ))
+        };
+        if effective_range.length <= 0 {
+            break;
+        }
+        let text = unsafe {
+            CFString::wrap_under_create_rule(CFStringCreateWithSubstring(
+                std::ptr::null(),
+                string_ref,
+                effective_range,
+            ))
+        };
+
+        let (font, font_size) = font_attributes(&attributes);
+        let font_name = font.as_deref().unwrap_or_default();
+        runs.push(StyledRun {
+            text: text.to_string(),
+            bold: ["Bold", "Black", "Heavy"]
+                .iter()
+                .any(|weight| font_name.contains(weight)),
+            italic: font_name.contains("Italic") || font_name.contains("Oblique"),
+            link: link_attribute(&attributes),
+            font,
+            font_size,
+        });
+        location = effective_range.location + effective_range.length;
+    }
+    runs
+}
+
+fn font_attributes(attributes: &CFDictionary<CFString, CFType>) -> (Option<String>, Option<f64>) {
+    let key = unsafe { CFString::wrap_under_get_rule(kAXFontTextAttribute) };
+    let Some(font) = attributes
+        .find(&key)
+        .and_then(|font| font.downcast::<CFDictionary>())
+    else {
+        return (None, None);
+    };
+    let font: CFDictionary<CFString, CFType> =
+        unsafe { CFDictionary::wrap_under_get_rule(font.as_concrete_TypeRef()) };
+    let name_key = unsafe { CFString::wrap_under_get_rule(kAXFontNameKey) };
+    let size_key = unsafe { CFString::wrap_under_get_rule(kAXFontSizeKey) };
+    let name = font
+        .find(&name_key)
+        .and_then(|name| name.downcast::<CFString>())
+        .map(|name| name.to_string());
+    let size = font
+        .find(&size_key)
+        .and_then(|size| size.downcast::<CFNumber>())
+        .and_then(|size| size.to_f64());
+    (name, size)
+}
+
+// the link attribute holds the link's AX element, its URL is an attribute of that element
+fn link_attribute(attributes: &CFDictionary<CFString, CFType>) -> Option<String> {
+    let key = unsafe { CFString::wrap_under_get_rule(kAXLinkTextAttribute) };
+    let link = attributes.find(&key)?.downcast::<AXUIElement>()?;
+    url_of(&link)
+}
+
+/// `AXURL` of a link or web area.
+pub(crate) fn url_of(element: &AXUIElement) -> Option<String> {
+    let url = element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXURLAttribute,
+        )))
+        .ok()?
+        .downcast_into::<CFURL>()?;
+    Some(url.get_string().to_string())
+}
+
+/// File path of the `AXDocument` URL of a window, the file it shows.
+pub(crate) fn document_path(window: &AXUIElement) -> Option<PathBuf> {
+    use core_foundation_sys::{base::kCFAllocatorDefault, url::CFURLCreateWithString};
+
+    let document = window.document().ok()?;
+    let url = unsafe {
+        CFURLCreateWithString(
+            kCFAllocatorDefault,
+            document.as_concrete_TypeRef(),
+            std::ptr::null(),
+        )
+    };
+    if url.is_null() {
+        return None;
+    }
+    // decodes percent escapes into the exact bytes of the name
+    unsafe { CFURL::wrap_under_create_rule(url) }.to_path()
+}
+
+/// Selected text of a WebKit or Chromium web area read through text markers,
+/// which also covers selections spanning several elements.
+pub(crate) fn text_marker_selection(web_area: &AXUIElement) -> Option<String> {
+    let range = web_area
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            "AXSelectedTextMarkerRange",
+        )))
+        .ok()?;
+    let string = web_area
+        .parameterized_attribute(
+            &AXAttribute::new(&CFString::from_static_string("AXStringForTextMarkerRange")),
+            &range,
+        )
+        .ok()?
+        .downcast_into::<CFString>()?;
+    Some(string.to_string())
+}
@@ -0,0 +1,147 @@
+// AppleScript and Shortcuts can message a running daemon without a shell:
+// the `«event SlTxgSel»` Apple Event carries the request words as its direct
+// parameter and gets the same output a socket request does.
+
+use std::ffi::c_void;
+use std::ptr;
+
+type OSErr = i16;
+type DescType = u32;
+
+#[repr(C)]
+struct AEDesc {
+    descriptor_type: DescType,
+    data_handle: *mut c_void,
+}
+
+type AEEventHandler = extern "C" fn(*const AEDesc, *mut AEDesc, *mut c_void) -> OSErr;
+
+const fn four_char_code(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+const EVENT_CLASS: u32 = four_char_code(b"SlTx");
+const EVENT_ID: u32 = four_char_code(b"gSel");
+const KEY_DIRECT_OBJECT: u32 = four_char_code(b"----");
+const KEY_ERROR_STRING: u32 = four_char_code(b"errs");
+const TYPE_UTF8_TEXT: DescType = four_char_code(b"utf8");
+const NO_ERR: OSErr = 0;
+const ERR_AE_DESC_NOT_FOUND: OSErr = -1701;
+const ERR_AE_EVENT_FAILED: OSErr = -10000;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn AEInstallEventHandler(
+        event_class: u32,
+        event_id: u32,
+        handler: AEEventHandler,
+        refcon: *mut c_void,
+        is_sys_handler: u8,
+    ) -> OSErr;
+    fn AEGetParamPtr(
+        event: *const AEDesc,
+        keyword: u32,
+        desired_type: DescType,
+        actual_type: *mut DescType,
+        data: *mut c_void,
+        maximum_size: isize,
+        actual_size: *mut isize,
+    ) -> OSErr;
+    fn AEPutParamPtr(
+        event: *mut AEDesc,
+        keyword: u32,
+        type_code: DescType,
+        data: *const c_void,
+        size: isize,
+    ) -> OSErr;
+}
+
+/// Answers `«event SlTxgSel»` with `answer`. The events are only delivered
+/// while the main thread runs the AppKit event loop.
+pub(crate) fn install() -> anyhow::Result<()> {
+    let err = unsafe {
+        AEInstallEventHandler(
+            EVENT_CLASS,
+            EVENT_ID,
+            handle_get_selection,
+            ptr::null_mut(),
+            0,
+        )
+    };
+    if err != NO_ERR {
+        anyhow::bail!("failed to install the Apple Event handler: OSErr {err}");
+    }
+    Ok(())
+}
+
+extern "C" fn handle_get_selection(
+    event: *const AEDesc,
+    reply: *mut AEDesc,
+    _refcon: *mut c_void,
+) -> OSErr {
+    let answer = std::panic::catch_unwind(|| {
+        // no direct parameter asks for the defaults, like an empty line
+        let request = unsafe { direct_parameter(event) }?;
+        crate::macos::answer(&request)
+    });
+    match answer {
+        Ok(Ok(output)) => unsafe { put_text(reply, KEY_DIRECT_OBJECT, &output) },
+        Ok(Err(e)) => {
+            unsafe { put_text(reply, KEY_ERROR_STRING, &e.to_string()) };
+            ERR_AE_EVENT_FAILED
+        }
+        Err(_) => {
+            unsafe { put_text(reply, KEY_ERROR_STRING, "the capture panicked") };
+            ERR_AE_EVENT_FAILED
+        }
+    }
+}
+
+unsafe fn direct_parameter(event: *const AEDesc) -> anyhow::Result<String> {
+    let mut actual_type = 0;
+    let mut size = 0;
+    // a first call with no room only reports the size
+    let err = AEGetParamPtr(
+        event,
+        KEY_DIRECT_OBJECT,
+        TYPE_UTF8_TEXT,
+        &mut actual_type,
+        ptr::null_mut(),
+        0,
+        &mut size,
+    );
+    match err {
+        NO_ERR => {}
+        ERR_AE_DESC_NOT_FOUND => return Ok(String::new()),
+        err => anyhow::bail!("unreadable direct parameter: OSErr {err}"),
+    }
+    let mut data = vec![0u8; size.max(0) as usize];
+    let err = AEGetParamPtr(
+        event,
+        KEY_DIRECT_OBJECT,
+        TYPE_UTF8_TEXT,
+        &mut actual_type,
+        data.as_mut_ptr().cast(),
+        data.len() as isize,
+        &mut size,
+    );
+    if err != NO_ERR {
+        anyhow::bail!("unreadable direct parameter: OSErr {err}");
+    }
+    data.truncate(size.max(0) as usize);
+    Ok(String::from_utf8(data)?)
+}
+
+/// Puts `text` into the reply, a sender that asked for none has a null one.
+unsafe fn put_text(reply: *mut AEDesc, keyword: u32, text: &str) -> OSErr {
+    if reply.is_null() || (*reply).descriptor_type == four_char_code(b"null") {
+        return NO_ERR;
+    }
+    AEPutParamPtr(
+        reply,
+        keyword,
+        TYPE_UTF8_TEXT,
+        text.as_ptr().cast(),
+        text.len() as isize,
+    )
+}
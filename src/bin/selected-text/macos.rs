@@ -0,0 +1,220 @@
+// Everything the CLI and the daemon do goes through AppKit, AX, the Unix
+// socket and Apple Events, see main.rs for the commands.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use get_selected_text_2::{
+    set_default_chord_timing, set_strict, ChordTiming, CopyMethod, MacOsProvider, NormalizeOptions,
+    SelectedText, SelectedTextProvider,
+};
+use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy};
+use objc2_foundation::MainThreadMarker;
+
+const USAGE: &str =
+    "usage: selected-text [text|files|auto] [--json] [--applescript|--menu-item] [--timing <profile>] [--strict] [--normalize] [--fold-typography] [--timeout <ms>]
+       selected-text serve [--socket <path>]";
+const SOCKET_NAME: &str = "selected-text.sock";
+const DEFAULT_TIMEOUT_MS: u64 = 90;
+// how long a client gets to send its request and to read the answer
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+// each request sets the global chord timing and strictness before its
+// capture, so the captures of concurrent requests take turns
+static CAPTURES: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Text,
+    Files,
+    Auto,
+}
+
+struct Args {
+    command: Command,
+    json: bool,
+    copy_method: CopyMethod,
+    timing: ChordTiming,
+    strict: bool,
+    normalize: NormalizeOptions,
+    timeout: u64,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let mut parsed = Args {
+        command: Command::Auto,
+        json: false,
+        copy_method: CopyMethod::default(),
+        timing: ChordTiming::default(),
+        strict: false,
+        normalize: NormalizeOptions::default(),
+        timeout: DEFAULT_TIMEOUT_MS,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "serve" => anyhow::bail!("`serve` must be the first argument"),
+            "text" => parsed.command = Command::Text,
+            "files" => parsed.command = Command::Files,
+            "auto" => parsed.command = Command::Auto,
+            "--json" => parsed.json = true,
+            "--applescript" => parsed.copy_method = CopyMethod::AppleScript,
+            "--menu-item" => parsed.copy_method = CopyMethod::MenuItem,
+            "--strict" => parsed.strict = true,
+            "--normalize" => {
+                parsed.normalize = NormalizeOptions {
+                    fold_typography: parsed.normalize.fold_typography,
+                    ..NormalizeOptions::all()
+                }
+            }
+            "--fold-typography" => parsed.normalize.fold_typography = true,
+            "--timing" => {
+                let Some(value) = args.next() else {
+                    anyhow::bail!("--timing expects a profile name");
+                };
+                parsed.timing = value.parse()?;
+            }
+            "--timeout" => {
+                let Some(value) = args.next() else {
+                    anyhow::bail!("--timeout expects a value in milliseconds");
+                };
+                parsed.timeout = value.parse()?;
+            }
+            other => anyhow::bail!("unknown argument `{other}`"),
+        }
+    }
+    Ok(parsed)
+}
+
+fn capture(args: &Args) -> anyhow::Result<SelectedText> {
+    set_default_chord_timing(args.timing);
+    set_strict(args.strict);
+    let provider = MacOsProvider {
+        copy_method: args.copy_method,
+        pasteboard_wait_timeout: args.timeout,
+    };
+    let selected_text = match args.command {
+        Command::Text => provider.get_selected_text(),
+        Command::Files => provider.get_selected_files(),
+        Command::Auto => provider.get_selection(),
+    }?;
+    Ok(selected_text)
+}
+
+fn render(args: &Args) -> anyhow::Result<String> {
+    let selected_text = capture(args)?.normalized(&args.normalize);
+    if args.json {
+        Ok(serde_json::to_string(&selected_text)?)
+    } else {
+        Ok(selected_text.text.join("\n"))
+    }
+}
+
+fn default_socket_path() -> PathBuf {
+    std::env::temp_dir().join(SOCKET_NAME)
+}
+
+/// The output for a daemon request, in the same words as the command line.
+pub(crate) fn answer(request: &str) -> anyhow::Result<String> {
+    let args = parse_args(request.split_whitespace().map(str::to_owned))?;
+    let _turn = CAPTURES.lock().unwrap_or_else(PoisonError::into_inner);
+    render(&args)
+}
+
+fn handle_connection(mut stream: UnixStream) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let response = answer(&request).unwrap_or_else(|e| format!("error: {e}"));
+    writeln!(stream, "{response}")?;
+    Ok(())
+}
+
+fn serve(socket_path: PathBuf) -> anyhow::Result<()> {
+    // a previous daemon that was killed leaves its socket file behind
+    if socket_path.exists() {
+        std::fs::remove_file(&socket_path)?;
+    }
+    let listener = UnixListener::bind(&socket_path)?;
+    log::info!("listening on {}", socket_path.display());
+    std::thread::Builder::new()
+        .name("accept".to_owned())
+        .spawn(move || accept(listener))?;
+    crate::apple_events::install()?;
+    // Apple Events are dispatched by the main thread's event loop
+    let mtm = MainThreadMarker::new().ok_or_else(|| anyhow::anyhow!("not on the main thread"))?;
+    let app = NSApplication::sharedApplication(mtm);
+    app.setActivationPolicy(NSApplicationActivationPolicy::Prohibited);
+    unsafe { app.run() };
+    Ok(())
+}
+
+// a thread per connection, a client that never sends its line only holds
+// up itself until the timeout
+fn accept(listener: UnixListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let handled = std::thread::Builder::new()
+                    .name("connection".to_owned())
+                    .spawn(move || {
+                        if let Err(e) = handle_connection(stream) {
+                            log::error!("failed to answer request: {e:?}");
+                        }
+                    });
+                if let Err(e) = handled {
+                    log::error!("failed to start a connection thread: {e:?}");
+                }
+            }
+            Err(e) => log::error!("failed to accept connection: {e:?}"),
+        }
+    }
+}
+
+fn parse_serve_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<PathBuf> {
+    let mut socket_path = default_socket_path();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => {
+                let Some(value) = args.next() else {
+                    anyhow::bail!("--socket expects a path");
+                };
+                socket_path = PathBuf::from(value);
+            }
+            other => anyhow::bail!("unknown argument `{other}`"),
+        }
+    }
+    Ok(socket_path)
+}
+
+pub(crate) fn main() {
+    let mut cli_args = std::env::args().skip(1).peekable();
+    if cli_args.peek().map(String::as_str) == Some("serve") {
+        cli_args.next();
+        let result = parse_serve_args(cli_args).and_then(serve);
+        if let Err(e) = result {
+            eprintln!("{e:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let args = match parse_args(cli_args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    match render(&args) {
+        Ok(output) => println!("{output}"),
+        Err(e) => {
+            eprintln!("{e:?}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Command-line bridge for macOS Shortcuts and other automation tools.
+//!
+//! Build with `cargo build --release --features cli` and call the binary from a
+//! Shortcuts "Run Shell Script" action (or `do shell script` in AppleScript):
+//!
+//! ```text
+//! selected-text [text|files|auto] [--json] [--applescript|--menu-item] [--timing <profile>] [--strict] [--normalize] [--fold-typography] [--timeout <ms>]
+//! ```
+//!
+//! - `text`  - "Get Selected Text": the selection of the frontmost app
+//! - `files` - "Get Selected Files": the POSIX paths selected in Finder
+//! - `auto`  - files when Finder (or the desktop) is frontmost, text otherwise (default)
+//!
+//! Plain output prints one entry per line. `--json` prints the `SelectedText`
+//! struct instead. `--applescript` copies through System Events and
+//! `--menu-item` presses the app's Edit ▸ Copy item instead of posting Cmd+C.
+//! `--strict` fails when nothing was copied before the timeout instead of
+//! printing an empty selection. `--timing` picks how fast the chord is typed: `fast`, `default`,
+//! `conservative` or `remote-desktop`.
+//! `--normalize` converts line endings to LF and strips
+//! invisible characters and non-breaking spaces, `--fold-typography` turns
+//! smart quotes, dashes and ellipses into ASCII. Errors go to stderr with a non-zero exit status, so a
+//! Shortcuts workflow stops instead of continuing with empty input.
+//!
+//! ## Daemon mode
+//!
+//! `selected-text serve [--socket <path>]` keeps a single process (and its
+//! Accessibility grant) alive and answers requests on a Unix socket, so any
+//! automation tool can ask for the selection without spawning or linking Rust.
+//! Each connection sends one line using the same words as the command line and
+//! receives the output; failures are answered with a line starting `error:`.
+//!
+//! ```applescript
+//! do shell script "echo 'text --json' | nc -U $TMPDIR/selected-text.sock"
+//! ```
+//!
+//! Connections are answered on threads of their own, a client gets 10 seconds
+//! to send its line and to read the answer.
+//!
+//! The daemon also answers the raw Apple Event `«event SlTxgSel»`. Its direct
+//! parameter holds the same words, a failure raises an AppleScript error.
+//!
+//! ```applescript
+//! tell application "Selected Text" to «event SlTxgSel» "text --json"
+//! ```
+//!
+//! `tell application` finds processes through LaunchServices, so the binary
+//! has to run from inside an app bundle for that, the socket works anywhere.
+
+#[cfg(target_os = "macos")]
+mod apple_events;
+#[cfg(target_os = "macos")]
+mod macos;
+
+#[cfg(target_os = "macos")]
+fn main() {
+    macos::main()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("selected-text only runs on macOS");
+    std::process::exit(1);
+}
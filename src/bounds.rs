@@ -0,0 +1,163 @@
+// AX gives screen rects from the top left of the main display with y going
+// down, AppKit places windows from its bottom left with y going up. A
+// floating toolbar next to the selection needs whichever its UI uses.
+
+#[cfg(target_os = "macos")]
+use core_foundation::base::CFRange;
+#[cfg(target_os = "macos")]
+use core_graphics::{display::CGDisplay, geometry::CGRect};
+
+#[cfg(target_os = "macos")]
+use crate::{ax, policy, Result, SelectedTextError};
+
+/// A rectangle in screen points.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct ScreenRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Where the selection is on screen.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct SelectionBounds {
+    /// Origin at the top left of the main display, as AX and CGWindow use.
+    pub ax: ScreenRect,
+    /// Origin at the bottom left of the main display, as `NSWindow` and
+    /// `NSScreen` use.
+    pub cocoa: ScreenRect,
+}
+
+#[cfg(target_os = "macos")]
+impl SelectionBounds {
+    fn from_ax(rect: CGRect, main_display_height: f64) -> Self {
+        let ax = ScreenRect {
+            x: rect.origin.x,
+            y: rect.origin.y,
+            width: rect.size.width,
+            height: rect.size.height,
+        };
+        SelectionBounds {
+            ax,
+            cocoa: ScreenRect {
+                y: main_display_height - ax.y - ax.height,
+                ..ax
+            },
+        }
+    }
+}
+
+/// Bounds of the focused element's selection, `None` when it has none or
+/// doesn't report them.
+#[cfg(target_os = "macos")]
+pub(crate) fn selection_bounds() -> Option<SelectionBounds> {
+    let rect = ax::selection_rect().ok()?;
+    if rect.size.width <= 0.0 && rect.size.height <= 0.0 {
+        return None;
+    }
+    let main_display_height = CGDisplay::main().bounds().size.height;
+    Some(SelectionBounds::from_ax(rect, main_display_height))
+}
+
+/// Where the insertion point is, see `get_caret_position`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CaretPosition {
+    /// UTF-16 offset of the insertion point in the element's text.
+    pub index: usize,
+    /// A zero-width rect as tall as the line.
+    pub bounds: SelectionBounds,
+    /// AX role of the focused element, e.g. `AXTextArea`.
+    pub role: Option<String>,
+}
+
+/// The insertion point of the focused element on screen, to show UI at the
+/// cursor when nothing is selected. With a selection it's the selection's
+/// end.
+#[cfg(target_os = "macos")]
+pub fn get_caret_position() -> Result<CaretPosition> {
+    policy::ensure_capture_allowed()?;
+    let element = ax::focused_element()?;
+    let range = ax::selected_range(&element)?;
+    let index = (range.location + range.length).max(0);
+    let Some(rect) = caret_rect(|range| ax::bounds_for_range(&element, range).ok(), index) else {
+        return Err(SelectedTextError::other(
+            "The focused element doesn't report where its insertion point is",
+        ));
+    };
+    let main_display_height = CGDisplay::main().bounds().size.height;
+    Ok(CaretPosition {
+        index: index as usize,
+        bounds: SelectionBounds::from_ax(rect, main_display_height),
+        role: ax::role(&element),
+    })
+}
+
+// Many elements give an empty range no bounds, or a zero rect. The edge of
+// the character after the caret, or the one before it at the end of the
+// text, stands in.
+#[cfg(target_os = "macos")]
+fn caret_rect(
+    bounds_for_range: impl Fn(CFRange) -> Option<CGRect>,
+    index: isize,
+) -> Option<CGRect> {
+    let has_size = |rect: &CGRect| rect.size.width > 0.0 || rect.size.height > 0.0;
+    if let Some(rect) = bounds_for_range(CFRange::init(index, 0)).filter(has_size) {
+        return Some(edge(rect, rect.origin.x));
+    }
+    if let Some(rect) = bounds_for_range(CFRange::init(index, 1)).filter(has_size) {
+        return Some(edge(rect, rect.origin.x));
+    }
+    let rect = bounds_for_range(CFRange::init(index.checked_sub(1).filter(|i| *i >= 0)?, 1))
+        .filter(has_size)?;
+    Some(edge(rect, rect.origin.x + rect.size.width))
+}
+
+#[cfg(target_os = "macos")]
+fn edge(rect: CGRect, x: f64) -> CGRect {
+    let mut edge = rect;
+    edge.origin.x = x;
+    edge.size.width = 0.0;
+    edge
+}
+
+#[cfg(all(test, target_os = "macos"))]
+mod tests {
+    use super::*;
+    use core_graphics::geometry::{CGPoint, CGSize};
+
+    #[test]
+    fn test_cocoa_bounds() {
+        let rect = CGRect::new(&CGPoint::new(100.0, 50.0), &CGSize::new(80.0, 20.0));
+        let bounds = SelectionBounds::from_ax(rect, 1000.0);
+        assert_eq!(bounds.ax.y, 50.0);
+        assert_eq!(bounds.cocoa.y, 930.0);
+        assert_eq!(bounds.cocoa.x, 100.0);
+        assert_eq!(bounds.cocoa.height, 20.0);
+    }
+
+    #[test]
+    fn test_caret_rect() {
+        let line = |x: f64| CGRect::new(&CGPoint::new(x, 40.0), &CGSize::new(8.0, 16.0));
+        // a zero rect for the empty range, the next character answers
+        let bounds = |range: CFRange| match range.length {
+            0 => Some(CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(0.0, 0.0))),
+            _ if range.location < 3 => Some(line(8.0 * range.location as f64)),
+            _ => None,
+        };
+        let caret = |rect: Option<CGRect>| {
+            rect.map(|rect| {
+                (
+                    rect.origin.x,
+                    rect.origin.y,
+                    rect.size.width,
+                    rect.size.height,
+                )
+            })
+        };
+        assert_eq!(caret(caret_rect(bounds, 1)), Some((8.0, 40.0, 0.0, 16.0)));
+        // at the end of the text only the character before it has bounds
+        assert_eq!(caret(caret_rect(bounds, 3)), Some((24.0, 40.0, 0.0, 16.0)));
+        assert!(caret_rect(|_| None, 0).is_none());
+    }
+}
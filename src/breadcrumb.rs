@@ -0,0 +1,256 @@
+// When a host app crashes inside a capture (a hung AX call, an AppleScript
+// that never returns, a panic across FFI) its crash report says nothing about
+// what the capture was doing. Every capture leaves a breadcrumb of its
+// strategy, app and stage, optionally mirrored to a file that outlives the
+// process.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "json-log")]
+use crate::json_log;
+use crate::{diagnostics, stats, SelectedTextError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum CaptureStage {
+    Started,
+    ReadingAx,
+    Copying,
+    WaitingForPasteboard,
+    Restoring,
+    Finished,
+}
+
+impl CaptureStage {
+    const ALL: [CaptureStage; 6] = [
+        CaptureStage::Started,
+        CaptureStage::ReadingAx,
+        CaptureStage::Copying,
+        CaptureStage::WaitingForPasteboard,
+        CaptureStage::Restoring,
+        CaptureStage::Finished,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CaptureStage::Started => "started",
+            CaptureStage::ReadingAx => "reading-ax",
+            CaptureStage::Copying => "copying",
+            CaptureStage::WaitingForPasteboard => "waiting-for-pasteboard",
+            CaptureStage::Restoring => "restoring",
+            CaptureStage::Finished => "finished",
+        }
+    }
+}
+
+/// How far the last capture got. A stage other than `Finished` after a crash
+/// means the capture was running when it happened.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CaptureBreadcrumb {
+    /// `ax`, `auto-copy`, `key-chord`, `applescript`, `menu-item` or
+    /// `finder-script`.
+    pub strategy: String,
+    pub app: String,
+    pub stage: CaptureStage,
+    /// Seconds since the Unix epoch when `stage` was reached.
+    pub at: u64,
+    pub pid: u32,
+}
+
+impl CaptureBreadcrumb {
+    // one `key value` per line, readable in a crash report as is
+    fn to_file_contents(&self) -> String {
+        format!(
+            "strategy {}\napp {}\nstage {}\nat {}\npid {}\n",
+            self.strategy,
+            self.app.replace('\n', " "),
+            self.stage.name(),
+            self.at,
+            self.pid
+        )
+    }
+
+    fn from_file_contents(contents: &str) -> Option<Self> {
+        let field = |key: &str| {
+            contents.lines().find_map(|line| {
+                let (name, value) = line.split_once(' ')?;
+                (name == key).then_some(value)
+            })
+        };
+        let stage = field("stage")?;
+        Some(CaptureBreadcrumb {
+            strategy: field("strategy")?.to_owned(),
+            app: field("app")?.to_owned(),
+            stage: *CaptureStage::ALL.iter().find(|s| s.name() == stage)?,
+            at: field("at")?.parse().ok()?,
+            pid: field("pid")?.parse().ok()?,
+        })
+    }
+}
+
+static LAST_BREADCRUMB: Mutex<Option<CaptureBreadcrumb>> = Mutex::new(None);
+static BREADCRUMB_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+// when the running capture began, for how long its stages took
+static STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+// tests leaving breadcrumbs take turns, they all share `LAST_BREADCRUMB`
+#[cfg(test)]
+pub(crate) static TEST_TURN: Mutex<()> = Mutex::new(());
+
+/// Also writes every breadcrumb to `path`, so `last_capture_breadcrumb` finds
+/// it after a restart. `None`, the default, keeps it in memory only.
+pub fn set_breadcrumb_file(path: Option<PathBuf>) {
+    *BREADCRUMB_FILE.lock().unwrap() = path;
+}
+
+/// The breadcrumb of the last capture in this process or, before the first
+/// one, the one left in the breadcrumb file by the previous run.
+pub fn last_capture_breadcrumb() -> Option<CaptureBreadcrumb> {
+    if let Some(breadcrumb) = LAST_BREADCRUMB.lock().unwrap().clone() {
+        return Some(breadcrumb);
+    }
+    let path = BREADCRUMB_FILE.lock().unwrap().clone()?;
+    let contents = std::fs::read_to_string(path).ok()?;
+    CaptureBreadcrumb::from_file_contents(&contents)
+}
+
+pub(crate) fn begin(app: &str, strategy: &str) {
+    *STARTED.lock().unwrap() = Some(Instant::now());
+    leave(|_| CaptureBreadcrumb {
+        strategy: strategy.to_owned(),
+        app: app.to_owned(),
+        stage: CaptureStage::Started,
+        at: 0,
+        pid: std::process::id(),
+    });
+}
+
+/// The capture moved on to `strategy`, e.g. copying after AX failed.
+pub(crate) fn switch_strategy(strategy: &str) {
+    leave(|last| CaptureBreadcrumb {
+        strategy: strategy.to_owned(),
+        ..last.unwrap_or_else(|| unknown_app(strategy))
+    });
+}
+
+pub(crate) fn reached(stage: CaptureStage) {
+    let breadcrumb = leave(|last| CaptureBreadcrumb {
+        stage,
+        ..last.unwrap_or_else(|| unknown_app("unknown"))
+    });
+    if stage == CaptureStage::Finished {
+        end(&breadcrumb, None);
+    }
+}
+
+/// The capture ended without a selection, because of `error` if it was a
+/// `SelectedTextError`. The breadcrumb stays at the stage it failed in.
+pub(crate) fn failed(error: Option<&SelectedTextError>) {
+    let last = LAST_BREADCRUMB.lock().unwrap().clone();
+    if let Some(breadcrumb) = last {
+        end(
+            &breadcrumb,
+            Some(error.map_or("other", SelectedTextError::code)),
+        );
+    }
+}
+
+// only the first end of a capture counts, the public two-step API can report
+// a failure after the copy already did
+fn end(breadcrumb: &CaptureBreadcrumb, error_code: Option<&'static str>) {
+    let Some(started) = STARTED.lock().unwrap().take() else {
+        return;
+    };
+    let duration = started.elapsed();
+    stats::finished(
+        &breadcrumb.app,
+        &breadcrumb.strategy,
+        error_code.is_none(),
+        duration,
+    );
+    #[cfg(feature = "json-log")]
+    if let Some(code) = error_code {
+        json_log::emit(breadcrumb, "failed", duration, Some(code));
+    }
+    #[cfg(not(feature = "json-log"))]
+    let _ = error_code;
+}
+
+// copying can start without `begin` through the public two-step API
+fn unknown_app(strategy: &str) -> CaptureBreadcrumb {
+    *STARTED.lock().unwrap() = Some(Instant::now());
+    CaptureBreadcrumb {
+        strategy: strategy.to_owned(),
+        app: String::new(),
+        stage: CaptureStage::Started,
+        at: 0,
+        pid: std::process::id(),
+    }
+}
+
+fn leave(update: impl FnOnce(Option<CaptureBreadcrumb>) -> CaptureBreadcrumb) -> CaptureBreadcrumb {
+    let mut last = LAST_BREADCRUMB.lock().unwrap();
+    let mut breadcrumb = update(last.take());
+    breadcrumb.at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    if let Some(path) = BREADCRUMB_FILE.lock().unwrap().as_deref() {
+        if let Err(e) = write_atomically(path, &breadcrumb.to_file_contents()) {
+            diagnostics::warn!("Failed to write breadcrumb to {}: {}", path.display(), e);
+        }
+    }
+    #[cfg(feature = "json-log")]
+    json_log::emit(&breadcrumb, "stage", elapsed(), None);
+    *last = Some(breadcrumb.clone());
+    breadcrumb
+}
+
+#[cfg(feature = "json-log")]
+fn elapsed() -> std::time::Duration {
+    STARTED
+        .lock()
+        .unwrap()
+        .map_or(std::time::Duration::ZERO, |started| started.elapsed())
+}
+
+// a crash mid-write mustn't leave half a breadcrumb behind
+pub(crate) fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, contents)?;
+    std::fs::rename(partial, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breadcrumb_file_round_trip() {
+        let breadcrumb = CaptureBreadcrumb {
+            strategy: "key-chord".to_owned(),
+            app: "Visual Studio Code".to_owned(),
+            stage: CaptureStage::WaitingForPasteboard,
+            at: 1_700_000_000,
+            pid: 42,
+        };
+        assert_eq!(
+            CaptureBreadcrumb::from_file_contents(&breadcrumb.to_file_contents()),
+            Some(breadcrumb)
+        );
+        assert_eq!(CaptureBreadcrumb::from_file_contents("stage lost\n"), None);
+    }
+
+    #[test]
+    fn test_breadcrumb_stages() {
+        let _turn = TEST_TURN
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        begin("Notes", "ax");
+        reached(CaptureStage::ReadingAx);
+        switch_strategy("menu-item");
+        let breadcrumb = last_capture_breadcrumb().unwrap();
+        assert_eq!(breadcrumb.app, "Notes");
+        assert_eq!(breadcrumb.strategy, "menu-item");
+        assert_eq!(breadcrumb.stage, CaptureStage::ReadingAx);
+    }
+}
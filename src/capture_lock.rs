@@ -0,0 +1,106 @@
+// A capture snapshots the pasteboard, copies, then restores the snapshot.
+// Two interleaving captures restore each other's snapshots and lose the
+// user's clipboard, so they take turns. The turn spans two public calls
+// (`copy_and_save_pasteboard`, then `get_selected_text_from_pasteboard`),
+// hence a flag and a condvar rather than a held guard. The flag notes the
+// change count the copy started from, which the second call is handed back,
+// so a call with some other count can't end a turn it never took.
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::diagnostics;
+
+/// A turn is given up after this long, in case the caller never reads the
+/// pasteboard after copying.
+const STALE_AFTER: Duration = Duration::from_secs(5);
+
+struct Taken {
+    at: Instant,
+    /// The pasteboard's change count before the copy, once it's known.
+    change_count: Option<isize>,
+}
+
+static TAKEN: Mutex<Option<Taken>> = Mutex::new(None);
+static TURN_ENDED: Condvar = Condvar::new();
+
+/// Waits for the running capture, if any, to finish.
+pub(crate) fn begin() {
+    let mut taken = TAKEN.lock().unwrap();
+    while let Some(since) = taken.as_ref().map(|taken| taken.at) {
+        let Some(left) = STALE_AFTER.checked_sub(since.elapsed()) else {
+            diagnostics::warn!("previous capture never finished, taking its turn");
+            break;
+        };
+        taken = TURN_ENDED.wait_timeout(taken, left).unwrap().0;
+    }
+    *taken = Some(Taken {
+        at: Instant::now(),
+        change_count: None,
+    });
+}
+
+/// Hands the running turn to whoever reads the copy made from
+/// `change_count`, see `Turn::of_copy`.
+pub(crate) fn claim(change_count: isize) {
+    if let Some(taken) = TAKEN.lock().unwrap().as_mut() {
+        taken.change_count = Some(change_count);
+    }
+}
+
+pub(crate) fn end() {
+    *TAKEN.lock().unwrap() = None;
+    TURN_ENDED.notify_one();
+}
+
+/// Ends the turn when dropped, on every return path, if it is the turn of
+/// the copy being read.
+pub(crate) struct Turn {
+    owned: bool,
+}
+
+impl Turn {
+    pub(crate) fn of_copy(change_count: isize) -> Self {
+        let taken = TAKEN.lock().unwrap();
+        Turn {
+            owned: taken
+                .as_ref()
+                .is_some_and(|taken| taken.change_count == Some(change_count)),
+        }
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        if self.owned {
+            end();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captures_take_turns() {
+        begin();
+        let waiter = std::thread::spawn(|| {
+            let started = Instant::now();
+            begin();
+            end();
+            started.elapsed()
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        end();
+        assert!(waiter.join().unwrap() >= Duration::from_millis(40));
+
+        // a read of another copy leaves the turn alone
+        begin();
+        claim(7);
+        drop(Turn::of_copy(3));
+        assert!(TAKEN.lock().unwrap().is_some());
+        drop(Turn::of_copy(7));
+        assert!(TAKEN.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,299 @@
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use core_graphics::event::{CGKeyCode, KeyCode};
+
+use crate::{keycode, Result, SelectedTextError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Command,
+    Shift,
+    Option,
+    Control,
+}
+
+impl Modifier {
+    pub(crate) fn key_code(self) -> CGKeyCode {
+        match self {
+            Modifier::Command => KeyCode::COMMAND,
+            Modifier::Shift => KeyCode::SHIFT,
+            Modifier::Option => KeyCode::OPTION,
+            Modifier::Control => KeyCode::CONTROL,
+        }
+    }
+
+    pub(crate) fn applescript_name(self) -> &'static str {
+        match self {
+            Modifier::Command => "command down",
+            Modifier::Shift => "shift down",
+            Modifier::Option => "option down",
+            Modifier::Control => "control down",
+        }
+    }
+}
+
+/// The shortcut that copies in an app, modifiers are pressed in order and
+/// released in reverse around `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyChord {
+    pub modifiers: Vec<Modifier>,
+    pub key: char,
+}
+
+impl CopyChord {
+    pub fn new(modifiers: &[Modifier], key: char) -> Self {
+        CopyChord {
+            modifiers: modifiers.to_vec(),
+            key: key.to_lowercase().next().unwrap_or(key),
+        }
+    }
+
+    pub fn cmd_c() -> Self {
+        CopyChord::new(&[Modifier::Command], 'c')
+    }
+
+    /// Key code for `key` in the current layout, or its ANSI position.
+    pub(crate) fn key_code(&self) -> anyhow::Result<CGKeyCode> {
+        // honors `set_copy_key_code`
+        if self.key == 'c' {
+            return Ok(keycode::copy_key_code());
+        }
+        keycode::key_code_or_ansi(self.key).ok_or_else(|| {
+            SelectedTextError::KeySimulationFailed(format!(
+                "no key types {:?} in the current layout",
+                self.key
+            ))
+            .into()
+        })
+    }
+}
+
+impl Default for CopyChord {
+    fn default() -> Self {
+        CopyChord::cmd_c()
+    }
+}
+
+/// Parses chords written like `cmd+shift+c` or `⌃c`.
+impl FromStr for CopyChord {
+    type Err = SelectedTextError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = Vec::new();
+        let mut rest = s.trim();
+        while let Some((modifier, tail)) = split_modifier(rest) {
+            if !modifiers.contains(&modifier) {
+                modifiers.push(modifier);
+            }
+            rest = tail;
+        }
+        let mut chars = rest.chars();
+        let (Some(key), None) = (chars.next(), chars.next()) else {
+            return Err(SelectedTextError::other(format_args!(
+                "Expected a single key after the modifiers in {:?}",
+                s
+            )));
+        };
+        Ok(CopyChord::new(&modifiers, key))
+    }
+}
+
+fn split_modifier(s: &str) -> Option<(Modifier, &str)> {
+    for (symbol, modifier) in [
+        ('⌘', Modifier::Command),
+        ('⇧', Modifier::Shift),
+        ('⌥', Modifier::Option),
+        ('⌃', Modifier::Control),
+    ] {
+        if let Some(rest) = s.strip_prefix(symbol) {
+            return Some((modifier, rest.trim_start_matches('+')));
+        }
+    }
+    let (name, rest) = s.split_once('+')?;
+    let modifier = match name.trim().to_ascii_lowercase().as_str() {
+        "cmd" | "command" => Modifier::Command,
+        "shift" => Modifier::Shift,
+        "opt" | "option" | "alt" => Modifier::Option,
+        "ctrl" | "control" => Modifier::Control,
+        _ => return None,
+    };
+    Some((modifier, rest.trim_start()))
+}
+
+static COPY_CHORDS: RwLock<Vec<(String, CopyChord)>> = RwLock::new(Vec::new());
+
+/// Uses `chord` to copy in the app with `bundle_id`, e.g. Cmd+Shift+C for a
+/// terminal. `None` goes back to Cmd+C.
+pub fn set_copy_chord(bundle_id: &str, chord: Option<CopyChord>) {
+    let mut chords = COPY_CHORDS.write().unwrap();
+    chords.retain(|(id, _)| !id.eq_ignore_ascii_case(bundle_id));
+    if let Some(chord) = chord {
+        chords.push((bundle_id.to_owned(), chord));
+    }
+}
+
+/// The chord that copies in the app with `bundle_id`.
+pub fn copy_chord_for(bundle_id: Option<&str>) -> CopyChord {
+    let Some(bundle_id) = bundle_id else {
+        return CopyChord::cmd_c();
+    };
+    let chord = COPY_CHORDS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .map(|(_, chord)| chord.clone());
+    #[cfg(feature = "app-config")]
+    let chord = chord.or_else(|| {
+        crate::config::app_setting(bundle_id, |settings| settings.copy_shortcut.clone())
+    });
+    chord.unwrap_or_default()
+}
+
+/// How long a chord takes to type, apps behind a slow link or a busy event
+/// loop drop keys that are released too quickly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChordTiming {
+    /// Between pressing the key and releasing it.
+    pub hold: Duration,
+    /// Between consecutive modifier presses and releases.
+    pub gap: Duration,
+    /// After the last release, before reading the pasteboard.
+    pub settle: Duration,
+}
+
+impl ChordTiming {
+    pub const FAST: ChordTiming = ChordTiming::from_millis(5, 5, 10);
+    pub const DEFAULT: ChordTiming = ChordTiming::from_millis(20, 20, 20);
+    pub const CONSERVATIVE: ChordTiming = ChordTiming::from_millis(50, 30, 50);
+    /// Screen sharing, VNC and RDP clients forward keys over the network.
+    pub const REMOTE_DESKTOP: ChordTiming = ChordTiming::from_millis(80, 50, 150);
+
+    const fn from_millis(hold: u64, gap: u64, settle: u64) -> Self {
+        ChordTiming {
+            hold: Duration::from_millis(hold),
+            gap: Duration::from_millis(gap),
+            settle: Duration::from_millis(settle),
+        }
+    }
+}
+
+impl Default for ChordTiming {
+    fn default() -> Self {
+        ChordTiming::DEFAULT
+    }
+}
+
+/// Parses a profile name: `fast`, `default`, `conservative` or
+/// `remote-desktop`.
+impl FromStr for ChordTiming {
+    type Err = SelectedTextError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "fast" => Ok(ChordTiming::FAST),
+            "default" => Ok(ChordTiming::DEFAULT),
+            "conservative" => Ok(ChordTiming::CONSERVATIVE),
+            "remote-desktop" | "remote" => Ok(ChordTiming::REMOTE_DESKTOP),
+            _ => Err(SelectedTextError::other(format_args!(
+                "Unknown timing profile {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+static DEFAULT_TIMING: RwLock<ChordTiming> = RwLock::new(ChordTiming::DEFAULT);
+static CHORD_TIMINGS: RwLock<Vec<(String, ChordTiming)>> = RwLock::new(Vec::new());
+
+/// Timing used for apps without their own, see `set_chord_timing`.
+pub fn set_default_chord_timing(timing: ChordTiming) {
+    *DEFAULT_TIMING.write().unwrap() = timing;
+}
+
+/// Types chords slower or faster in the app with `bundle_id`. `None` goes
+/// back to the default timing.
+pub fn set_chord_timing(bundle_id: &str, timing: Option<ChordTiming>) {
+    let mut timings = CHORD_TIMINGS.write().unwrap();
+    timings.retain(|(id, _)| !id.eq_ignore_ascii_case(bundle_id));
+    if let Some(timing) = timing {
+        timings.push((bundle_id.to_owned(), timing));
+    }
+}
+
+pub fn chord_timing_for(bundle_id: Option<&str>) -> ChordTiming {
+    bundle_id
+        .and_then(configured_chord_timing)
+        .unwrap_or_else(|| *DEFAULT_TIMING.read().unwrap())
+}
+
+/// The timing set for `bundle_id` with `set_chord_timing`, or else in the
+/// app config.
+pub(crate) fn configured_chord_timing(bundle_id: &str) -> Option<ChordTiming> {
+    let timing = CHORD_TIMINGS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .map(|(_, timing)| *timing);
+    #[cfg(feature = "app-config")]
+    let timing =
+        timing.or_else(|| crate::config::app_setting(bundle_id, |settings| settings.timing));
+    timing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chord() {
+        assert_eq!("cmd+c".parse::<CopyChord>().unwrap(), CopyChord::cmd_c());
+        assert_eq!(
+            "Cmd + Shift + C".parse::<CopyChord>().unwrap(),
+            CopyChord::new(&[Modifier::Command, Modifier::Shift], 'c')
+        );
+        assert_eq!(
+            "⌃⌥w".parse::<CopyChord>().unwrap(),
+            CopyChord::new(&[Modifier::Control, Modifier::Option], 'w')
+        );
+        assert!("cmd+".parse::<CopyChord>().is_err());
+        assert!("hyper+c".parse::<CopyChord>().is_err());
+    }
+
+    #[test]
+    fn test_copy_chord_table() {
+        let chord = CopyChord::new(&[Modifier::Command, Modifier::Shift], 'c');
+        set_copy_chord("com.example.Term", Some(chord.clone()));
+        assert_eq!(copy_chord_for(Some("com.example.term")), chord);
+        assert_eq!(
+            copy_chord_for(Some("com.example.Other")),
+            CopyChord::cmd_c()
+        );
+        assert_eq!(copy_chord_for(None), CopyChord::cmd_c());
+        set_copy_chord("com.example.Term", None);
+        assert_eq!(copy_chord_for(Some("com.example.Term")), CopyChord::cmd_c());
+    }
+
+    #[test]
+    fn test_chord_timing() {
+        assert_eq!(
+            "Remote_Desktop".parse::<ChordTiming>().unwrap(),
+            ChordTiming::REMOTE_DESKTOP
+        );
+        assert!("slow".parse::<ChordTiming>().is_err());
+        set_chord_timing("com.example.Viewer", Some(ChordTiming::CONSERVATIVE));
+        assert_eq!(
+            chord_timing_for(Some("com.example.viewer")),
+            ChordTiming::CONSERVATIVE
+        );
+        assert_eq!(chord_timing_for(None), ChordTiming::DEFAULT);
+        set_chord_timing("com.example.Viewer", None);
+        assert_eq!(
+            chord_timing_for(Some("com.example.Viewer")),
+            ChordTiming::DEFAULT
+        );
+    }
+}
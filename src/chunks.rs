@@ -0,0 +1,104 @@
+use core_foundation::{
+    base::{CFIndex, CFRange, TCFType},
+    string::{CFString, CFStringGetCharacters},
+};
+
+use crate::{ax, floor_grapheme_boundary, policy, redact, Result};
+
+/// Reads a string in pieces of at most `chunk_len` UTF-16 code units, cut on
+/// grapheme cluster boundaries, so only one chunk is held as a Rust `String`
+/// at a time. Registered redactions are applied per chunk, a match straddling
+/// two chunks is not redacted.
+pub struct TextChunks {
+    string: CFString,
+    position: CFIndex,
+    chunk_len: CFIndex,
+    buffer: Vec<u16>,
+}
+
+impl TextChunks {
+    pub fn new(string: CFString, chunk_len: usize) -> Self {
+        // a chunk must fit a surrogate pair
+        let chunk_len = chunk_len.max(2);
+        Self {
+            string,
+            position: 0,
+            chunk_len: chunk_len as CFIndex,
+            buffer: Vec::with_capacity(chunk_len),
+        }
+    }
+
+    /// Length of the whole string in UTF-16 code units.
+    pub fn total_len(&self) -> usize {
+        self.string.char_len() as usize
+    }
+}
+
+impl Iterator for TextChunks {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let total_len = self.string.char_len();
+        if self.position >= total_len {
+            return None;
+        }
+        let mut len = self.chunk_len.min(total_len - self.position);
+        self.buffer.resize(len as usize, 0);
+        unsafe {
+            CFStringGetCharacters(
+                self.string.as_concrete_TypeRef(),
+                CFRange::init(self.position, len),
+                self.buffer.as_mut_ptr(),
+            );
+        }
+        // leave a trailing high surrogate for the next chunk
+        if len > 1 && (0xD800..=0xDBFF).contains(&self.buffer[len as usize - 1]) {
+            len -= 1;
+            self.buffer.truncate(len as usize);
+        }
+        let mut chunk = String::from_utf16_lossy(&self.buffer);
+        // the last grapheme may continue past the window, read it again with the next chunk
+        if self.position + len < total_len {
+            let last_start = floor_grapheme_boundary(&chunk, chunk.len() - 1);
+            if last_start > 0 {
+                len -= chunk[last_start..].encode_utf16().count() as CFIndex;
+                chunk.truncate(last_start);
+            }
+        }
+        self.position += len;
+        let (chunk, _) = redact(&chunk);
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.string.char_len() - self.position).max(0) as usize;
+        let chunk_len = self.chunk_len as usize;
+        (remaining.div_ceil(chunk_len), Some(remaining))
+    }
+}
+
+/// Like the AX path of `get_selected_text_using_ax_then_copy`, but streams the
+/// selection instead of copying it into one `String`.
+///
+/// Redaction is weaker than in a whole capture: each chunk is redacted on its
+/// own, so a secret cut by a chunk boundary comes back unredacted. A larger
+/// `chunk_len` only makes that rarer, capture the whole selection when
+/// redaction has to hold.
+pub fn get_selected_text_chunks_by_ax(chunk_len: usize) -> Result<TextChunks> {
+    policy::ensure_capture_allowed()?;
+    Ok(TextChunks::new(ax::selected_text()?, chunk_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_keep_surrogate_pairs() {
+        let chunks = TextChunks::new(CFString::new("a😀bc"), 2);
+        assert_eq!(chunks.total_len(), 5);
+        assert_eq!(chunks.collect::<Vec<_>>(), ["a", "😀", "bc"]);
+        let chunks = TextChunks::new(CFString::new("ae\u{301}\u{301}b"), 3);
+        assert_eq!(chunks.collect::<Vec<_>>(), ["a", "e\u{301}\u{301}", "b"]);
+    }
+}
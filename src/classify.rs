@@ -0,0 +1,516 @@
+// Cheap heuristics for tagging a selection so UIs can pick which actions to
+// offer. Everything here runs on every capture, so no regexes or allocations
+// beyond what's needed.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum TextKind {
+    Url,
+    EmailAddress,
+    FilePath,
+    Code,
+    Number,
+    Prose,
+}
+
+/// Language a code selection is probably written in, serialized with the
+/// names highlighters use (`rust`, `python`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+    Swift,
+    C,
+    Cpp,
+    Java,
+    Kotlin,
+    Ruby,
+    Php,
+    Shell,
+    Sql,
+    Html,
+    Css,
+    Json,
+}
+
+/// Classifies `text`, returns `None` for empty or whitespace-only text.
+pub fn classify(text: &str) -> Option<TextKind> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let kind = if is_url(text) {
+        TextKind::Url
+    } else if is_email_address(text) {
+        TextKind::EmailAddress
+    } else if is_file_path(text) {
+        TextKind::FilePath
+    } else if is_number(text) {
+        TextKind::Number
+    } else if looks_like_code(text) {
+        TextKind::Code
+    } else {
+        TextKind::Prose
+    };
+    Some(kind)
+}
+
+fn is_url(text: &str) -> bool {
+    if text.contains(char::is_whitespace) {
+        return false;
+    }
+    if let Some((scheme, rest)) = text.split_once("://") {
+        return !scheme.is_empty()
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            && !rest.is_empty();
+    }
+    if let Some(address) = text.strip_prefix("mailto:") {
+        return is_email_address(address);
+    }
+    text.strip_prefix("www.").is_some_and(is_domain)
+}
+
+fn is_email_address(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c))
+        && is_domain(domain)
+}
+
+fn is_domain(text: &str) -> bool {
+    let host = text.split(['/', '?', '#', ':']).next().unwrap_or_default();
+    let labels = host.split('.').collect::<Vec<_>>();
+    labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.chars().all(char::is_alphabetic))
+}
+
+fn is_file_path(text: &str) -> bool {
+    if text.contains('\n') || text.contains(['<', '>', '|', '*', '{', '}', ';']) {
+        return false;
+    }
+    let rest = ["/", "~/", "./", "../"]
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix));
+    // a lone `/` or a comment marker like `//` is not a path
+    rest.is_some_and(|rest| !rest.is_empty() && !rest.starts_with('/'))
+}
+
+fn is_number(text: &str) -> bool {
+    let text = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit() || c == '_');
+    }
+    let text = text.strip_suffix('%').unwrap_or(text);
+    let mut digits = 0;
+    for c in text.chars() {
+        match c {
+            '0'..='9' => digits += 1,
+            // thousands separators and decimal points in any locale
+            ',' | '.' | '_' | '\'' | ' ' | '\u{a0}' | '\u{202f}' => {}
+            'e' | 'E' if digits > 0 => {}
+            _ => return false,
+        }
+    }
+    digits > 0 && text.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+}
+
+const CODE_KEYWORDS: &[&str] = &[
+    "fn ",
+    "let ",
+    "const ",
+    "var ",
+    "def ",
+    "class ",
+    "struct ",
+    "enum ",
+    "impl ",
+    "import ",
+    "from ",
+    "return ",
+    "func ",
+    "function ",
+    "#include",
+    "#define",
+    "pub ",
+    "use ",
+    "package ",
+    "public ",
+    "private ",
+    "static ",
+    "async ",
+    "await ",
+    "elif ",
+    "else {",
+    "if (",
+    "for (",
+    "while (",
+    "=> ",
+    "->",
+    "::",
+    "!=",
+    "==",
+    "&&",
+    "||",
+    "</",
+    "/>",
+];
+
+fn looks_like_code(text: &str) -> bool {
+    let lines = text.lines().filter(|line| !line.trim().is_empty());
+    let mut line_count = 0;
+    let mut code_lines = 0;
+    for line in lines {
+        line_count += 1;
+        let trimmed = line.trim();
+        let has_keyword = CODE_KEYWORDS
+            .iter()
+            .any(|keyword| trimmed.contains(keyword));
+        let has_code_ending = trimmed.ends_with([';', '{', '}', ')', ']', ',', ':']);
+        let is_comment = ["//", "#", "/*", "*", "--"]
+            .iter()
+            .any(|marker| trimmed.starts_with(marker));
+        if has_keyword || has_code_ending || is_comment {
+            code_lines += 1;
+        }
+    }
+    let symbols = text
+        .chars()
+        .filter(|c| "{}[]()<>;=_$#&|\\`".contains(*c))
+        .count();
+    let non_whitespace = text.chars().filter(|c| !c.is_whitespace()).count().max(1);
+    let symbol_ratio = symbols as f64 / non_whitespace as f64;
+
+    if line_count == 1 {
+        // single lines need both a code-like shape and a fair share of punctuation
+        code_lines == 1 && symbol_ratio > 0.08
+    } else {
+        code_lines * 2 >= line_count && symbol_ratio > 0.03
+    }
+}
+
+// (marker, weight) pairs, a marker is counted once however often it appears
+const LANGUAGE_MARKERS: &[(CodeLanguage, &[(&str, u32)])] = &[
+    (
+        CodeLanguage::Rust,
+        &[
+            ("fn ", 3),
+            ("let mut ", 4),
+            ("impl ", 3),
+            ("pub fn", 4),
+            ("&self", 3),
+            ("::new(", 2),
+            ("println!", 4),
+            ("-> ", 1),
+            ("use std::", 5),
+            ("#[derive", 5),
+            ("Some(", 1),
+            ("Ok(", 1),
+        ],
+    ),
+    (
+        CodeLanguage::Python,
+        &[
+            ("def ", 3),
+            ("elif ", 4),
+            ("self.", 2),
+            ("import ", 1),
+            ("from ", 1),
+            ("print(", 2),
+            ("None", 1),
+            ("__init__", 5),
+            ("):\n", 3),
+            ("lambda ", 2),
+        ],
+    ),
+    (
+        CodeLanguage::JavaScript,
+        &[
+            ("function ", 3),
+            ("const ", 1),
+            ("=> ", 1),
+            ("console.log", 5),
+            ("require(", 4),
+            ("document.", 4),
+            ("===", 3),
+            ("undefined", 2),
+            ("module.exports", 5),
+        ],
+    ),
+    (
+        CodeLanguage::TypeScript,
+        &[
+            ("interface ", 2),
+            (": string", 4),
+            (": number", 4),
+            (": boolean", 4),
+            ("export type ", 5),
+            ("readonly ", 2),
+            ("as const", 3),
+        ],
+    ),
+    (
+        CodeLanguage::Go,
+        &[
+            ("func ", 3),
+            ("package ", 3),
+            (":= ", 3),
+            ("fmt.", 4),
+            ("err != nil", 6),
+            ("go func", 5),
+            ("chan ", 3),
+        ],
+    ),
+    (
+        CodeLanguage::Swift,
+        &[
+            ("func ", 2),
+            ("guard let", 6),
+            ("if let ", 2),
+            ("import UIKit", 6),
+            ("import SwiftUI", 6),
+            ("var body: some View", 6),
+            ("@State", 4),
+            ("print(", 1),
+        ],
+    ),
+    (
+        CodeLanguage::C,
+        &[
+            ("#include <", 3),
+            ("printf(", 3),
+            ("malloc(", 4),
+            ("int main(", 3),
+            ("->", 1),
+            ("NULL", 2),
+            ("sizeof(", 2),
+        ],
+    ),
+    (
+        CodeLanguage::Cpp,
+        &[
+            ("std::", 4),
+            ("#include <", 2),
+            ("cout <<", 5),
+            ("template<", 5),
+            ("template <", 5),
+            ("nullptr", 4),
+            ("namespace ", 3),
+        ],
+    ),
+    (
+        CodeLanguage::Java,
+        &[
+            ("public class ", 5),
+            ("public static void main", 6),
+            ("System.out.println", 6),
+            ("private ", 1),
+            ("@Override", 4),
+            ("import java.", 6),
+        ],
+    ),
+    (
+        CodeLanguage::Kotlin,
+        &[
+            ("fun ", 4),
+            ("val ", 2),
+            ("println(", 2),
+            ("data class ", 5),
+            ("companion object", 6),
+        ],
+    ),
+    (
+        CodeLanguage::Ruby,
+        &[
+            ("def ", 2),
+            ("end\n", 2),
+            ("puts ", 4),
+            ("require '", 4),
+            ("attr_accessor", 6),
+            (".each do", 5),
+        ],
+    ),
+    (
+        CodeLanguage::Php,
+        &[
+            ("<?php", 10),
+            ("$this->", 5),
+            ("echo ", 2),
+            ("function ", 1),
+        ],
+    ),
+    (
+        CodeLanguage::Shell,
+        &[
+            ("#!/bin/", 8),
+            ("echo ", 2),
+            ("fi\n", 3),
+            ("then\n", 3),
+            ("export ", 2),
+            ("$(", 2),
+            ("sudo ", 3),
+            ("| grep", 4),
+        ],
+    ),
+    (
+        CodeLanguage::Sql,
+        &[
+            ("SELECT ", 4),
+            (" FROM ", 3),
+            ("WHERE ", 3),
+            ("INSERT INTO", 6),
+            ("CREATE TABLE", 6),
+            ("JOIN ", 2),
+            ("GROUP BY", 4),
+        ],
+    ),
+    (
+        CodeLanguage::Html,
+        &[
+            ("<!DOCTYPE", 8),
+            ("<div", 4),
+            ("</", 2),
+            ("<html", 6),
+            ("class=\"", 3),
+            ("href=\"", 3),
+        ],
+    ),
+    (
+        CodeLanguage::Css,
+        &[
+            ("px;", 4),
+            ("color:", 3),
+            ("margin:", 3),
+            ("padding:", 3),
+            ("display:", 3),
+            ("@media", 5),
+        ],
+    ),
+];
+
+/// Guesses the language of a code selection from keywords, shebangs and
+/// punctuation. Returns `None` when no language stands out.
+pub fn guess_code_language(text: &str) -> Option<CodeLanguage> {
+    let trimmed = text.trim();
+    if let Some(shebang) = trimmed.lines().next().and_then(|l| l.strip_prefix("#!")) {
+        let language = match shebang {
+            s if s.contains("python") => CodeLanguage::Python,
+            s if s.contains("node") => CodeLanguage::JavaScript,
+            s if s.contains("ruby") => CodeLanguage::Ruby,
+            s if s.contains("php") => CodeLanguage::Php,
+            _ => CodeLanguage::Shell,
+        };
+        return Some(language);
+    }
+    let is_bracketed = (trimmed.starts_with('{') && trimmed.ends_with('}'))
+        || (trimmed.starts_with('[') && trimmed.ends_with(']'));
+    if is_bracketed && (trimmed.contains("\":") || trimmed.starts_with("[\"")) {
+        return Some(CodeLanguage::Json);
+    }
+
+    // markers like `end\n` should also match on the last line
+    let mut text = text.to_owned();
+    text.push('\n');
+    let mut best: Option<(CodeLanguage, u32)> = None;
+    for (language, markers) in LANGUAGE_MARKERS {
+        let score = markers
+            .iter()
+            .filter(|(marker, _)| text.contains(marker))
+            .map(|(_, weight)| weight)
+            .sum::<u32>();
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((*language, score));
+        }
+    }
+    best.filter(|(_, score)| *score >= 4)
+        .map(|(language, _)| language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            ("https://example.com/a?b=c", TextKind::Url),
+            ("www.example.com", TextKind::Url),
+            ("mailto:me@example.com", TextKind::Url),
+            (" someone.else@example.co.uk ", TextKind::EmailAddress),
+            ("/Users/me/Documents/My File.txt", TextKind::FilePath),
+            ("~/.config/app", TextKind::FilePath),
+            ("1,234.56", TextKind::Number),
+            ("-42%", TextKind::Number),
+            ("0xFF_FF", TextKind::Number),
+            ("let x = foo(bar);", TextKind::Code),
+            ("fn main() {\n    println!(\"hi\");\n}", TextKind::Code),
+            ("def f(x):\n    return x * 2\n", TextKind::Code),
+            (
+                "The quick brown fox jumps over the lazy dog.",
+                TextKind::Prose,
+            ),
+            (
+                "Dear team,\nThanks for the update (really).\nSee you soon.",
+                TextKind::Prose,
+            ),
+            ("12 apples", TextKind::Prose),
+            ("a/b", TextKind::Prose),
+        ];
+        for (text, kind) in cases {
+            assert_eq!(classify(text), Some(kind), "{:?}", text);
+        }
+        assert_eq!(classify("  \n"), None);
+    }
+
+    #[test]
+    fn test_guess_code_language() {
+        let cases = [
+            (
+                "use std::fmt;\n\nfn main() {\n    let mut x = 1;\n    println!(\"{x}\");\n}",
+                CodeLanguage::Rust,
+            ),
+            (
+                "class A:\n    def __init__(self):\n        self.x = None\n",
+                CodeLanguage::Python,
+            ),
+            (
+                "const x = require('fs');\nconsole.log(x === undefined);",
+                CodeLanguage::JavaScript,
+            ),
+            (
+                "if err != nil {\n    return fmt.Errorf(\"x\")\n}",
+                CodeLanguage::Go,
+            ),
+            (
+                "SELECT id, name FROM users WHERE id = 1;",
+                CodeLanguage::Sql,
+            ),
+            ("#!/bin/bash\necho hi", CodeLanguage::Shell),
+            ("{\"a\": [1, 2]}", CodeLanguage::Json),
+            (
+                ".a {\n  color: red;\n  margin: 0 4px;\n}",
+                CodeLanguage::Css,
+            ),
+        ];
+        for (text, language) in cases {
+            assert_eq!(guess_code_language(text), Some(language), "{}", text);
+        }
+        assert_eq!(guess_code_language("x = y"), None);
+    }
+}
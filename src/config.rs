@@ -0,0 +1,219 @@
+// App-specific fixes (a slower timing, another copy shortcut, an app to
+// deny) shouldn't need a new build of the host app. They can come from a
+// JSON file instead, which is read again whenever it changes.
+//
+// {
+//   "apps": {
+//     "com.example.Editor": {
+//       "copy_method": "menu-item",
+//       "timing": "conservative",
+//       "copy_shortcut": "cmd+shift+c",
+//       "auto_copies": false,
+//       "denied": false
+//     }
+//   },
+//   "allowed_apps": ["com.example.Editor"]
+// }
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::Context;
+
+use crate::{diagnostics, ChordTiming, CopyChord, CopyMethod, Result};
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AppConfig {
+    #[serde(default)]
+    apps: BTreeMap<String, AppSettings>,
+    allowed_apps: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AppSettings {
+    copy_method: Option<String>,
+    timing: Option<String>,
+    copy_shortcut: Option<String>,
+    auto_copies: Option<bool>,
+    denied: Option<bool>,
+}
+
+/// `AppSettings` with every value parsed, so a bad file changes nothing.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ParsedSettings {
+    pub(crate) copy_method: Option<CopyMethod>,
+    pub(crate) timing: Option<ChordTiming>,
+    pub(crate) copy_shortcut: Option<CopyChord>,
+    pub(crate) auto_copies: Option<bool>,
+    pub(crate) denied: Option<bool>,
+}
+
+struct Loaded {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    apps: Vec<(String, ParsedSettings)>,
+    allowed_apps: Option<Vec<String>>,
+}
+
+static LOADED: Mutex<Option<Loaded>> = Mutex::new(None);
+
+/// Reads per-app settings from the JSON file at `path`, and reads it again
+/// whenever it changes. What was set in code wins over the file, clearing it
+/// in code (`set_copy_method(id, None)`, `set_allowed_apps(None)`) goes back
+/// to the file's value. Loading another file replaces the settings of the
+/// previous one.
+pub fn load_app_config(path: impl Into<PathBuf>) -> Result<()> {
+    let path = path.into();
+    let modified = modified(&path);
+    let (apps, allowed_apps) = read(&path)?;
+    *LOADED.lock().unwrap() = Some(Loaded {
+        path,
+        modified,
+        apps,
+        allowed_apps,
+    });
+    Ok(())
+}
+
+/// Reads the loaded file again if it changed since. A file that no longer
+/// parses is logged and the previous settings stay.
+pub(crate) fn reload_if_changed() {
+    let mut loaded = LOADED.lock().unwrap();
+    let Some(current) = loaded.as_mut() else {
+        return;
+    };
+    let modified = modified(&current.path);
+    if modified == current.modified {
+        return;
+    }
+    current.modified = modified;
+    match read(&current.path) {
+        Ok((apps, allowed_apps)) => {
+            diagnostics::info!("reloaded app config {}", current.path.display());
+            current.apps = apps;
+            current.allowed_apps = allowed_apps;
+        }
+        Err(e) => diagnostics::error!("Failed to reload app config: {:?}", e),
+    }
+}
+
+/// What the loaded file sets for `bundle_id`, for when nothing was set in
+/// code.
+pub(crate) fn app_setting<T>(
+    bundle_id: &str,
+    setting: impl Fn(&ParsedSettings) -> Option<T>,
+) -> Option<T> {
+    let loaded = LOADED.lock().unwrap();
+    loaded
+        .as_ref()?
+        .apps
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .and_then(|(_, settings)| setting(settings))
+}
+
+/// The loaded file's `allowed_apps`, for when none were set in code.
+pub(crate) fn allowed_apps() -> Option<Vec<String>> {
+    let loaded = LOADED.lock().unwrap();
+    loaded.as_ref()?.allowed_apps.clone()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read(path: &Path) -> anyhow::Result<Parsed> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&contents).with_context(|| format!("Invalid app config {}", path.display()))
+}
+
+type Parsed = (Vec<(String, ParsedSettings)>, Option<Vec<String>>);
+
+fn parse(contents: &str) -> anyhow::Result<Parsed> {
+    let config: AppConfig = serde_json::from_str(contents)?;
+    let mut apps = Vec::with_capacity(config.apps.len());
+    for (bundle_id, settings) in config.apps {
+        let parsed = ParsedSettings {
+            copy_method: settings
+                .copy_method
+                .map(|method| method.parse())
+                .transpose()
+                .with_context(|| format!("copy_method of {}", bundle_id))?,
+            timing: settings
+                .timing
+                .map(|timing| timing.parse())
+                .transpose()
+                .with_context(|| format!("timing of {}", bundle_id))?,
+            copy_shortcut: settings
+                .copy_shortcut
+                .map(|chord| chord.parse())
+                .transpose()
+                .with_context(|| format!("copy_shortcut of {}", bundle_id))?,
+            auto_copies: settings.auto_copies,
+            denied: settings.denied,
+        };
+        apps.push((bundle_id, parsed));
+    }
+    Ok((apps, config.allowed_apps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_app_config() {
+        let (apps, allowed_apps) = parse(
+            r#"{
+                "apps": {
+                    "com.example.Term": {
+                        "copy_method": "menu-item",
+                        "timing": "remote-desktop",
+                        "copy_shortcut": "cmd+shift+c"
+                    }
+                },
+                "allowed_apps": ["com.example.Term"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            apps,
+            [(
+                "com.example.Term".to_owned(),
+                ParsedSettings {
+                    copy_method: Some(CopyMethod::MenuItem),
+                    timing: Some(ChordTiming::REMOTE_DESKTOP),
+                    copy_shortcut: Some("cmd+shift+c".parse().unwrap()),
+                    ..Default::default()
+                }
+            )]
+        );
+        assert_eq!(allowed_apps, Some(vec!["com.example.Term".to_owned()]));
+        assert!(parse(r#"{"apps": {"a": {"timing": "glacial"}}}"#).is_err());
+        assert!(parse(r#"{"apps": {"a": {"speed": 1}}}"#).is_err());
+    }
+
+    #[test]
+    fn test_code_wins_over_file() {
+        let path = std::env::temp_dir().join(format!("app-config-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"apps": {"com.example.Layered": {"copy_method": "menu-item"}}}"#,
+        )
+        .unwrap();
+        load_app_config(&path).unwrap();
+        let method = || crate::app_copy_method("com.example.Layered");
+        assert_eq!(method(), Some(CopyMethod::MenuItem));
+        crate::set_copy_method("com.example.Layered", Some(CopyMethod::AppleScript));
+        assert_eq!(method(), Some(CopyMethod::AppleScript));
+        crate::set_copy_method("com.example.Layered", None);
+        assert_eq!(method(), Some(CopyMethod::MenuItem));
+        *LOADED.lock().unwrap() = None;
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,128 @@
+// AppKit objects and AX elements aren't thread-safe, and `Retained` keeps
+// most of them !Send for that reason. Wrapping them in a struct with unsafe
+// Send/Sync impls let them be used and released anywhere, which can crash
+// the host app. `ThreadConfined` lets such a value travel between threads
+// while it's only ever touched on the thread that made it.
+
+use std::ffi::c_void;
+use std::mem::ManuallyDrop;
+use std::thread::{self, ThreadId};
+
+use crate::diagnostics;
+
+#[repr(C)]
+struct DispatchQueue {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    fn pthread_main_np() -> i32;
+    // `dispatch_get_main_queue()` is a macro for its address
+    static _dispatch_main_q: DispatchQueue;
+    fn dispatch_async_f(
+        queue: *const DispatchQueue,
+        context: *mut c_void,
+        work: extern "C" fn(*mut c_void),
+    );
+}
+
+/// A value that can be moved and shared across threads but only reached on
+/// the thread that created it. Dropped elsewhere, a value from the main
+/// thread is sent back there to be dropped, any other value is leaked.
+pub struct ThreadConfined<T: 'static> {
+    value: ManuallyDrop<T>,
+    origin: ThreadId,
+    on_main: bool,
+}
+
+// SAFETY: the value is only reached on `origin` (`get`, `into_inner`, drop)
+// or on the main thread when that's where it came from (`send_to_main`).
+unsafe impl<T: 'static> Send for ThreadConfined<T> {}
+unsafe impl<T: 'static> Sync for ThreadConfined<T> {}
+
+impl<T: 'static> ThreadConfined<T> {
+    pub fn new(value: T) -> Self {
+        ThreadConfined {
+            value: ManuallyDrop::new(value),
+            origin: thread::current().id(),
+            on_main: unsafe { pthread_main_np() } != 0,
+        }
+    }
+
+    pub fn is_origin_thread(&self) -> bool {
+        thread::current().id() == self.origin
+    }
+
+    /// The value, `None` off the thread that created it.
+    pub fn get(&self) -> Option<&T> {
+        self.is_origin_thread().then(|| &*self.value)
+    }
+
+    /// Unwraps the value on the thread that created it, elsewhere the
+    /// wrapper is handed back.
+    pub fn into_inner(self) -> Result<T, Self> {
+        if !self.is_origin_thread() {
+            return Err(self);
+        }
+        let mut this = ManuallyDrop::new(self);
+        Ok(unsafe { ManuallyDrop::take(&mut this.value) })
+    }
+
+    /// Runs `f` with the value on the main queue, without waiting for it.
+    /// Only values created on the main thread can go there, others are
+    /// handed back.
+    pub fn send_to_main<F>(self, f: F) -> Result<(), Self>
+    where
+        F: FnOnce(T) + Send + 'static,
+    {
+        if !self.on_main {
+            return Err(self);
+        }
+        let mut this = ManuallyDrop::new(self);
+        let value = unsafe { ManuallyDrop::take(&mut this.value) };
+        hop_to_main(Box::new(move || f(value)));
+        Ok(())
+    }
+}
+
+impl<T: 'static> Drop for ThreadConfined<T> {
+    fn drop(&mut self) {
+        if self.is_origin_thread() {
+            unsafe { ManuallyDrop::drop(&mut self.value) };
+        } else if self.on_main {
+            let value = unsafe { ManuallyDrop::take(&mut self.value) };
+            hop_to_main(Box::new(move || drop(value)));
+        } else {
+            diagnostics::warn!("leaking a thread-confined value dropped off its thread");
+        }
+    }
+}
+
+// the closure isn't Send, callers make sure what it captures belongs on main
+pub(crate) fn hop_to_main(work: Box<dyn FnOnce()>) {
+    extern "C" fn run(context: *mut c_void) {
+        let work = unsafe { Box::from_raw(context as *mut Box<dyn FnOnce()>) };
+        work();
+    }
+    let context = Box::into_raw(Box::new(work)) as *mut c_void;
+    unsafe { dispatch_async_f(&_dispatch_main_q, context, run) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_thread_confined() {
+        let confined = Arc::new(ThreadConfined::new(String::from("item")));
+        assert_eq!(confined.get().map(String::as_str), Some("item"));
+        let elsewhere = thread::spawn({
+            let confined = confined.clone();
+            move || confined.get().is_none()
+        });
+        assert!(elsewhere.join().unwrap());
+        let confined = Arc::try_unwrap(confined).ok().unwrap();
+        assert_eq!(confined.into_inner().ok().as_deref(), Some("item"));
+    }
+}
@@ -0,0 +1,191 @@
+// Translators and grammar checkers get a fragment wrong without the sentence
+// around it. The focused element's AXValue holds its whole text, and the
+// selected range says where in it the selection sits. Editors that want the
+// whole document get the same two.
+
+use std::ops::Range;
+
+use accessibility_ng::AXUIElementAttributes;
+use core_foundation::string::CFString;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    active_window, ax, delivered, focused_in_this_process, get_selected_text, policy, redact,
+    redact::add_redactions, truncate::utf16_range_to_byte_range, truncate_graphemes,
+    CaptureStrategy, Redacted, Result, SelectedText, SelectedTextError,
+};
+
+/// A selection and the text next to it in the same element.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SelectedTextWithContext {
+    pub selected_text: SelectedText,
+    /// Up to `chars_before` graphemes right before the selection. `None`
+    /// when the selection wasn't read over AX. Redacted like the selection,
+    /// the matches count in `selected_text.redactions`.
+    pub before: Option<String>,
+    /// Up to `chars_after` graphemes right after the selection.
+    pub after: Option<String>,
+}
+
+/// The selection like `get_selected_text`, plus up to `chars_before` and
+/// `chars_after` graphemes around it when the focused element exposes its
+/// text over AX. Other selections come without context.
+pub fn get_selected_text_with_context(
+    chars_before: usize,
+    chars_after: usize,
+) -> Result<SelectedTextWithContext> {
+    policy::ensure_capture_allowed()?;
+    if focused_in_this_process() {
+        return Err(SelectedTextError::SelfFocused);
+    }
+    if let Some((selected, before, after)) = read_with_context(chars_before, chars_after) {
+        let app_name = active_window().app_name().unwrap_or_default().to_owned();
+        let mut selected_text =
+            SelectedText::from_text(app_name, selected).with_strategy(CaptureStrategy::Ax);
+        let (before, found_before) = redact(&before);
+        let (after, found_after) = redact(&after);
+        add_redactions(&mut selected_text.redactions, found_before);
+        add_redactions(&mut selected_text.redactions, found_after);
+        let pasteboard = unsafe { objc2_app_kit::NSPasteboard::generalPasteboard() };
+        return Ok(SelectedTextWithContext {
+            selected_text: delivered(selected_text, &pasteboard),
+            before: Some(before),
+            after: Some(after),
+        });
+    }
+    Ok(SelectedTextWithContext {
+        selected_text: get_selected_text()?,
+        before: None,
+        after: None,
+    })
+}
+
+fn read_with_context(before: usize, after: usize) -> Option<(String, String, String)> {
+    let focused = focused_element_text().ok()?;
+    let range = focused.selection.filter(|range| !range.is_empty())?;
+    let (before, after) = context_around(&focused.text, range.clone(), before, after);
+    Some((
+        focused.text[range].to_owned(),
+        before.to_owned(),
+        after.to_owned(),
+    ))
+}
+
+/// The whole text of the focused element and where the selection is in it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FocusedElementText {
+    /// Redacted, see `add_redaction`.
+    pub text: String,
+    /// Byte range of the selection in `text`, empty at the insertion point.
+    /// `None` when the element reports no range.
+    pub selection: Option<Range<usize>>,
+    /// The same range in UTF-16 code units. It's what AX and
+    /// `get_selected_range` report unless a redaction before the selection
+    /// changed the text's length.
+    pub selection_utf16: Option<Range<usize>>,
+    /// Registered redaction patterns that matched in `text`.
+    pub redactions: Vec<Redacted>,
+}
+
+impl FocusedElementText {
+    // the text before, in and after the selection is redacted apart, so
+    // the offsets still point at the selection afterwards
+    fn redacted(self) -> Self {
+        let mut redactions = Vec::new();
+        let mut redact_piece = |piece: &str| {
+            let (piece, found) = redact(piece);
+            add_redactions(&mut redactions, found);
+            piece
+        };
+        let Some(selection) = self.selection else {
+            let text = redact_piece(&self.text);
+            // offsets that didn't map onto the text can't be mapped onto
+            // the redacted text either
+            let selection_utf16 = self.selection_utf16.filter(|_| text == self.text);
+            return FocusedElementText {
+                text,
+                selection: None,
+                selection_utf16,
+                redactions,
+            };
+        };
+        let head = redact_piece(&self.text[..selection.start]);
+        let selected = redact_piece(&self.text[selection.clone()]);
+        let tail = redact_piece(&self.text[selection.end..]);
+        let start_utf16 = head.encode_utf16().count();
+        FocusedElementText {
+            selection: Some(head.len()..head.len() + selected.len()),
+            selection_utf16: Some(start_utf16..start_utf16 + selected.encode_utf16().count()),
+            text: [head, selected, tail].concat(),
+            redactions,
+        }
+    }
+}
+
+/// The `AXValue` text of the focused element, e.g. a whole document for an
+/// editor, with the selection's offsets in it.
+pub fn get_focused_element_text() -> Result<FocusedElementText> {
+    policy::ensure_capture_allowed()?;
+    Ok(focused_element_text()?.redacted())
+}
+
+fn focused_element_text() -> anyhow::Result<FocusedElementText> {
+    let element = ax::focused_element()?;
+    let Some(text) = element
+        .value()
+        .ok()
+        .and_then(|value| value.downcast_into::<CFString>())
+    else {
+        return Err(SelectedTextError::other("The focused element has no text value").into());
+    };
+    let text = text.to_string();
+    let selection_utf16 = ax::selected_range(&element).ok().map(|range| {
+        let start = range.location.max(0) as usize;
+        start..start + range.length.max(0) as usize
+    });
+    let selection = selection_utf16
+        .as_ref()
+        .and_then(|range| utf16_range_to_byte_range(&text, range.start, range.len()));
+    Ok(FocusedElementText {
+        text,
+        selection,
+        selection_utf16,
+        redactions: Vec::new(),
+    })
+}
+
+/// The last `before` graphemes ahead of `range` in `text` and the first
+/// `after` graphemes following it.
+fn context_around(text: &str, range: Range<usize>, before: usize, after: usize) -> (&str, &str) {
+    let head = &text[..range.start];
+    let start = match before {
+        0 => head.len(),
+        _ => head
+            .grapheme_indices(true)
+            .nth_back(before - 1)
+            .map_or(0, |(start, _)| start),
+    };
+    (
+        &head[start..],
+        truncate_graphemes(&text[range.end..], after),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_around() {
+        let text = "One. Two three four. Five.";
+        let start = text.find("three").unwrap();
+        let range = start..start + "three".len();
+        assert_eq!(context_around(text, range.clone(), 4, 5), ("Two ", " four"));
+        assert_eq!(context_around(text, range.clone(), 0, 0), ("", ""));
+        assert_eq!(
+            context_around(text, range, 100, 100),
+            ("One. Two ", " four. Five.")
+        );
+        assert_eq!(context_around("e\u{301}x", 3..4, 1, 1), ("e\u{301}", ""));
+    }
+}
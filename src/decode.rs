@@ -0,0 +1,83 @@
+// Best-effort decoding for pasteboard data that wasn't posted as a string.
+// Apps that do this are usually old Carbon-era code writing UTF-16 or a
+// Windows/Mac Latin encoding under a legacy or private type.
+
+/// Guesses the encoding of `bytes` and decodes it, replacing anything that
+/// doesn't fit. The guess order is BOM, UTF-16 by null-byte pattern, UTF-8,
+/// and finally Windows-1252, which decodes every byte.
+pub fn decode_lossy(bytes: &[u8]) -> String {
+    let text = if let Some(utf8) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        String::from_utf8_lossy(utf8).into_owned()
+    } else if let Some(utf16) = bytes.strip_prefix(b"\xFF\xFE") {
+        decode_utf16(utf16, u16::from_le_bytes)
+    } else if let Some(utf16) = bytes.strip_prefix(b"\xFE\xFF") {
+        decode_utf16(utf16, u16::from_be_bytes)
+    } else if let Some(little_endian) = looks_like_utf16(bytes) {
+        if little_endian {
+            decode_utf16(bytes, u16::from_le_bytes)
+        } else {
+            decode_utf16(bytes, u16::from_be_bytes)
+        }
+    } else if let Ok(utf8) = std::str::from_utf8(bytes) {
+        utf8.to_owned()
+    } else {
+        bytes.iter().copied().map(cp1252_to_char).collect()
+    };
+    text.trim_end_matches('\0').to_owned()
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+// mostly-ASCII text in UTF-16 has a zero in every other byte, returns whether
+// that's the high (little endian) or low byte
+fn looks_like_utf16(bytes: &[u8]) -> Option<bool> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_zeros * 2 > pairs && even_zeros * 4 <= pairs {
+        Some(true)
+    } else if even_zeros * 2 > pairs && odd_zeros * 4 <= pairs {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// everything AppKit writes in a single-byte encoding is Windows-1252
+pub(crate) fn cp1252_to_char(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_lossy() {
+        assert_eq!(decode_lossy(b"\xEF\xBB\xBFcaf\xC3\xA9"), "café");
+        assert_eq!(decode_lossy(b"\xFF\xFEh\0i\0"), "hi");
+        assert_eq!(decode_lossy(b"\xFE\xFF\0h\0i"), "hi");
+        assert_eq!(decode_lossy(b"h\0e\0y\0!\0\0\0"), "hey!");
+        assert_eq!(decode_lossy(b"\0h\0e\0y"), "hey");
+        assert_eq!(decode_lossy("naïve".as_bytes()), "naïve");
+        assert_eq!(decode_lossy(b"caf\xE9 \x93q\x94"), "café “q”");
+    }
+}
@@ -0,0 +1,81 @@
+// AX can post several notifications for one user action (selection changed,
+// value changed, focus moved), and a caller capturing on each of them would
+// emit the same selection over and over.
+
+use std::ops::Range;
+
+use crate::SelectedText;
+
+#[derive(PartialEq)]
+struct Emission {
+    app_name: String,
+    text: Vec<String>,
+    range: Option<Range<usize>>,
+}
+
+/// Remembers the last selection a watcher emitted, so repeats can be dropped.
+/// Enabled by default, pass one to `watch_selection_with` to change that.
+pub struct SelectionDedup {
+    enabled: bool,
+    last: Option<Emission>,
+}
+
+impl SelectionDedup {
+    pub fn new() -> Self {
+        SelectionDedup {
+            enabled: true,
+            last: None,
+        }
+    }
+
+    /// `false` lets every selection through, e.g. for a consumer that counts
+    /// notifications.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Whether `selected` has the same app, text and `range` (see
+    /// `get_selected_range`) as the previous selection passed in. The
+    /// selection is remembered either way.
+    pub fn is_repeat(&mut self, selected: &SelectedText, range: Option<Range<usize>>) -> bool {
+        let emission = Emission {
+            app_name: selected.app_name.clone(),
+            text: selected.text.clone(),
+            range,
+        };
+        let repeat = self.last.as_ref() == Some(&emission);
+        self.last = Some(emission);
+        self.enabled && repeat
+    }
+
+    /// Forgets the last selection, the next one is never a repeat.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+impl Default for SelectionDedup {
+    fn default() -> Self {
+        SelectionDedup::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_dedup() {
+        let hello = SelectedText::from_text("Notes".to_owned(), "hello".to_owned());
+        let mut dedup = SelectionDedup::new();
+        assert!(!dedup.is_repeat(&hello, Some(0..5)));
+        assert!(dedup.is_repeat(&hello, Some(0..5)));
+        // same text selected elsewhere
+        assert!(!dedup.is_repeat(&hello, Some(10..15)));
+        dedup.set_enabled(false);
+        assert!(!dedup.is_repeat(&hello, Some(10..15)));
+        dedup.set_enabled(true);
+        dedup.reset();
+        assert!(!dedup.is_repeat(&hello, Some(10..15)));
+    }
+}
@@ -0,0 +1,151 @@
+// Watches our own synthetic key events with a listen-only tap, so a chord
+// that never arrived, or whose key-ups got lost, is noticed instead of
+// leaving Cmd logically held down for the rest of the session.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+use core_graphics::event::{
+    CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
+    CGEventType, CGKeyCode, EventField, KeyCode,
+};
+
+/// Stored in `EVENT_SOURCE_USER_DATA` of every event we post, "selt".
+pub(crate) const SYNTHETIC_EVENT_TAG: i64 = 0x7365_6c74;
+
+pub(crate) struct DeliveryWatch {
+    seen: Arc<Mutex<Vec<(CGKeyCode, bool)>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeliveryWatch {
+    /// Starts listening, `None` when the tap can't be created (the process
+    /// lacks Accessibility or Input Monitoring permission).
+    pub(crate) fn start() -> Option<Self> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready, started) = mpsc::channel();
+        let thread = std::thread::spawn({
+            let seen = seen.clone();
+            let stop = stop.clone();
+            move || watch(seen, stop, ready)
+        });
+        if started.recv().unwrap_or(false) {
+            Some(DeliveryWatch {
+                seen,
+                stop,
+                thread: Some(thread),
+            })
+        } else {
+            let _ = thread.join();
+            None
+        }
+    }
+
+    /// Stops listening and returns what made it through.
+    pub(crate) fn finish(mut self) -> Delivery {
+        self.stop_watching();
+        let events = std::mem::take(&mut *self.seen.lock().unwrap());
+        Delivery { events }
+    }
+
+    fn stop_watching(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// an aborted chord drops the watch without finishing it
+impl Drop for DeliveryWatch {
+    fn drop(&mut self) {
+        self.stop_watching();
+    }
+}
+
+fn watch(
+    seen: Arc<Mutex<Vec<(CGKeyCode, bool)>>>,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<bool>,
+) {
+    let tap = CGEventTap::new(
+        CGEventTapLocation::Session,
+        CGEventTapPlacement::TailAppendEventTap,
+        CGEventTapOptions::ListenOnly,
+        vec![
+            CGEventType::KeyDown,
+            CGEventType::KeyUp,
+            CGEventType::FlagsChanged,
+        ],
+        move |_proxy, event_type, event| {
+            if event.get_integer_value_field(EventField::EVENT_SOURCE_USER_DATA)
+                == SYNTHETIC_EVENT_TAG
+            {
+                let key =
+                    event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE) as CGKeyCode;
+                let down = match event_type {
+                    CGEventType::KeyDown => true,
+                    CGEventType::KeyUp => false,
+                    _ => is_modifier_down(key, event.get_flags()),
+                };
+                seen.lock().unwrap().push((key, down));
+            }
+            None
+        },
+    );
+    let Ok(tap) = tap else {
+        let _ = ready.send(false);
+        return;
+    };
+    let Ok(source) = tap.mach_port.create_runloop_source(0) else {
+        let _ = ready.send(false);
+        return;
+    };
+    unsafe { CFRunLoop::get_current().add_source(&source, kCFRunLoopCommonModes) };
+    tap.enable();
+    let _ = ready.send(true);
+    while !stop.load(Ordering::Relaxed) {
+        CFRunLoop::run_in_mode(
+            unsafe { kCFRunLoopDefaultMode },
+            Duration::from_millis(5),
+            false,
+        );
+    }
+}
+
+// modifier keys arrive as FlagsChanged, pressed while their flag is set
+fn is_modifier_down(key: CGKeyCode, flags: CGEventFlags) -> bool {
+    let flag = match key {
+        KeyCode::COMMAND | KeyCode::RIGHT_COMMAND => CGEventFlags::CGEventFlagCommand,
+        KeyCode::SHIFT | KeyCode::RIGHT_SHIFT => CGEventFlags::CGEventFlagShift,
+        KeyCode::OPTION | KeyCode::RIGHT_OPTION => CGEventFlags::CGEventFlagAlternate,
+        KeyCode::CONTROL | KeyCode::RIGHT_CONTROL => CGEventFlags::CGEventFlagControl,
+        _ => return false,
+    };
+    flags.contains(flag)
+}
+
+/// Our events a listen-only tap saw, in order.
+pub(crate) struct Delivery {
+    events: Vec<(CGKeyCode, bool)>,
+}
+
+impl Delivery {
+    pub(crate) fn pressed(&self, key: CGKeyCode) -> bool {
+        self.events.contains(&(key, true))
+    }
+
+    /// Whether the last event seen for `key` was its release.
+    pub(crate) fn released(&self, key: CGKeyCode) -> bool {
+        self.events
+            .iter()
+            .rev()
+            .find(|(seen, _)| *seen == key)
+            .is_some_and(|(_, down)| !down)
+    }
+}
@@ -0,0 +1,96 @@
+// Some hosts mustn't let a library write to the process-wide logger, a
+// selection or file name in a log line can be a policy violation. Everything
+// this crate logs goes through here, to `log` unless a sink is set.
+
+use std::fmt;
+use std::sync::RwLock;
+
+pub use log::Level;
+
+/// Receives what the crate would otherwise log. `target` is the module the
+/// message comes from, as `log` would report it.
+pub trait DiagnosticsSink: Send + Sync {
+    fn record(&self, level: Level, target: &str, message: fmt::Arguments);
+}
+
+/// Drops all diagnostics.
+pub struct Silent;
+
+impl DiagnosticsSink for Silent {
+    fn record(&self, _level: Level, _target: &str, _message: fmt::Arguments) {}
+}
+
+static SINK: RwLock<Option<Box<dyn DiagnosticsSink>>> = RwLock::new(None);
+
+/// Sends the crate's diagnostics to `sink` instead of `log`, `Silent` turns
+/// them off. `None`, the default, goes back to `log`.
+pub fn set_diagnostics_sink(sink: Option<Box<dyn DiagnosticsSink>>) {
+    *SINK.write().unwrap() = sink;
+}
+
+pub(crate) fn emit(level: Level, target: &str, message: fmt::Arguments) {
+    match SINK.read().unwrap().as_deref() {
+        Some(sink) => sink.record(level, target, message),
+        None => log::log!(target: target, level, "{}", message),
+    }
+}
+
+macro_rules! log_error {
+    ($($arg:tt)+) => {
+        $crate::diagnostics::emit($crate::diagnostics::Level::Error, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[cfg(any(test, not(target_os = "linux")))]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        $crate::diagnostics::emit($crate::diagnostics::Level::Warn, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[cfg(target_os = "macos")]
+macro_rules! log_info {
+    ($($arg:tt)+) => {
+        $crate::diagnostics::emit($crate::diagnostics::Level::Info, module_path!(), format_args!($($arg)+))
+    };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        $crate::diagnostics::emit($crate::diagnostics::Level::Debug, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) use log_info as info;
+#[cfg(any(test, not(target_os = "linux")))]
+pub(crate) use log_warn as warn;
+pub(crate) use {log_debug as debug, log_error as error};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl DiagnosticsSink for Collect {
+        fn record(&self, level: Level, _target: &str, message: fmt::Arguments) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", level, message));
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_sink() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        set_diagnostics_sink(Some(Box::new(Collect(lines.clone()))));
+        warn!("copied {} items", 2);
+        set_diagnostics_sink(Some(Box::new(Silent)));
+        error!("dropped");
+        set_diagnostics_sink(None);
+        assert_eq!(*lines.lock().unwrap(), ["WARN copied 2 items"]);
+    }
+}
@@ -0,0 +1,75 @@
+// What's being dragged sits on its own pasteboard, readable while the drag
+// is in progress without copying anything. A drop-target assistant can look
+// at it before the user lets go.
+
+use objc2_app_kit::{
+    NSPasteboard, NSPasteboardNameDrag, NSPasteboardTypeFileURL, NSPasteboardTypeHTML,
+    NSPasteboardTypeString,
+};
+use objc2_foundation::NSURL;
+
+use crate::{
+    active_window, copied_image, policy, recover_pasteboard_text, snapshot, CaptureStrategy,
+    Result, SelectedText,
+};
+
+// kCGEventSourceStateCombinedSessionState, kCGMouseButtonLeft
+const COMBINED_SESSION_STATE: i32 = 0;
+const LEFT_MOUSE_BUTTON: u32 = 0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceButtonState(state: i32, button: u32) -> bool;
+}
+
+/// The files, text or image the user is dragging. `None` when the mouse
+/// button is up: the drag pasteboard keeps the last drag's items after the
+/// drop. `app_name` is the frontmost app's, the drag may have started
+/// elsewhere.
+pub fn current_drag_items() -> Result<Option<SelectedText>> {
+    policy::ensure_capture_allowed()?;
+    if !unsafe { CGEventSourceButtonState(COMBINED_SESSION_STATE, LEFT_MOUSE_BUTTON) } {
+        return Ok(None);
+    }
+    let pasteboard = unsafe { NSPasteboard::pasteboardWithName(NSPasteboardNameDrag) };
+    let app_name = active_window().app_name().unwrap_or_default().to_owned();
+    let paths = file_paths(&pasteboard);
+    if !paths.is_empty() {
+        return Ok(Some(
+            SelectedText::from_file_paths(app_name, paths).with_strategy(CaptureStrategy::Drag),
+        ));
+    }
+    let text =
+        unsafe { pasteboard.stringForType(NSPasteboardTypeString) }.map(|text| text.to_string());
+    let lossy = text.is_none();
+    let Some(text) = text.or_else(|| recover_pasteboard_text(&pasteboard)) else {
+        // images can be huge, the clipboard size limit applies to drags too
+        if snapshot::exceeds_size_limit(&pasteboard) {
+            return Ok(None);
+        }
+        return Ok(copied_image(&pasteboard).map(|image| {
+            SelectedText::from_image(app_name, image).with_strategy(CaptureStrategy::Drag)
+        }));
+    };
+    let html = unsafe { pasteboard.stringForType(NSPasteboardTypeHTML) };
+    let mut selected_text =
+        SelectedText::from_text(app_name, text).with_html(html.map(|html| html.to_string()));
+    selected_text.strategy = Some(CaptureStrategy::Drag);
+    selected_text.lossy = lossy;
+    Ok(Some(selected_text))
+}
+
+// Finder drags file reference URLs (file:///.file/id=...), resolved here
+fn file_paths(pasteboard: &NSPasteboard) -> Vec<String> {
+    let Some(items) = (unsafe { pasteboard.pasteboardItems() }) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| unsafe { item.stringForType(NSPasteboardTypeFileURL) })
+        .filter_map(|url| unsafe { NSURL::URLWithString(&url) })
+        .filter_map(|url| unsafe { url.filePathURL() })
+        .filter_map(|url| unsafe { url.path() })
+        .map(|path| path.to_string())
+        .collect()
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use anyhow::anyhow;
+use objc2::rc::Retained;
+use objc2_foundation::{
+    NSDataDetector, NSDictionary, NSMatchingOptions, NSRange, NSString, NSTextCheckingResult,
+    NSTextCheckingType,
+};
+
+use crate::{truncate::utf16_range_to_byte_range, Result};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub enum Entity {
+    Link {
+        url: String,
+    },
+    /// `timestamp` is in seconds since the Unix epoch, `duration` in seconds.
+    Date {
+        timestamp: f64,
+        duration: f64,
+    },
+    PhoneNumber {
+        number: String,
+    },
+    Address {
+        components: BTreeMap<String, String>,
+    },
+    /// Flight information, e.g. `{"Airline": "United", "Flight": "UA 1234"}`.
+    TransitInformation {
+        components: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DetectedEntity {
+    pub entity: Entity,
+    /// Byte range of the match within the text that was scanned.
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Runs `text` through `NSDataDetector` looking for links, dates, phone
+/// numbers, addresses and flight numbers.
+pub fn detect_entities(text: &str) -> Result<Vec<DetectedEntity>> {
+    let types = NSTextCheckingType::Link
+        | NSTextCheckingType::Date
+        | NSTextCheckingType::PhoneNumber
+        | NSTextCheckingType::Address
+        | NSTextCheckingType::TransitInformation;
+    let detector = unsafe { NSDataDetector::dataDetectorWithTypes_error(types.0) }
+        .map_err(|e| anyhow!("Failed to create NSDataDetector: {:?}", e))?;
+
+    let ns_text = NSString::from_str(text);
+    let full_range = NSRange::new(0, ns_text.length());
+    let matches = unsafe {
+        detector.matchesInString_options_range(&ns_text, NSMatchingOptions::empty(), full_range)
+    };
+
+    let mut entities = Vec::with_capacity(matches.count());
+    for result in matches.iter() {
+        let ns_range = unsafe { result.range() };
+        let Some(range) = utf16_range_to_byte_range(text, ns_range.location, ns_range.length)
+        else {
+            continue;
+        };
+        let Some(entity) = entity_from_result(result) else {
+            continue;
+        };
+        entities.push(DetectedEntity {
+            entity,
+            text: text[range.clone()].to_owned(),
+            range,
+        });
+    }
+    Ok(entities)
+}
+
+fn entity_from_result(result: &NSTextCheckingResult) -> Option<Entity> {
+    let result_type = unsafe { result.resultType() };
+    unsafe {
+        if result_type == NSTextCheckingType::Link {
+            let url = result.URL()?.absoluteString()?;
+            Some(Entity::Link {
+                url: url.to_string(),
+            })
+        } else if result_type == NSTextCheckingType::Date {
+            Some(Entity::Date {
+                timestamp: result.date()?.timeIntervalSince1970(),
+                duration: result.duration(),
+            })
+        } else if result_type == NSTextCheckingType::PhoneNumber {
+            Some(Entity::PhoneNumber {
+                number: result.phoneNumber()?.to_string(),
+            })
+        } else if result_type == NSTextCheckingType::Address {
+            Some(Entity::Address {
+                components: components_to_map(result.addressComponents()),
+            })
+        } else if result_type == NSTextCheckingType::TransitInformation {
+            Some(Entity::TransitInformation {
+                components: components_to_map(result.components()),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+fn components_to_map(
+    components: Option<Retained<NSDictionary<NSString, NSString>>>,
+) -> BTreeMap<String, String> {
+    let Some(components) = components else {
+        return BTreeMap::new();
+    };
+    let (keys, values) = components.to_vecs();
+    keys.into_iter()
+        .zip(values)
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_entities() {
+        let text = "Call 555-123-4567 or visit https://example.com";
+        assert_eq!(
+            detect_entities(text).unwrap(),
+            [
+                DetectedEntity {
+                    entity: Entity::PhoneNumber {
+                        number: "555-123-4567".to_owned(),
+                    },
+                    range: 5..17,
+                    text: "555-123-4567".to_owned(),
+                },
+                DetectedEntity {
+                    entity: Entity::Link {
+                        url: "https://example.com".to_owned(),
+                    },
+                    range: 27..46,
+                    text: "https://example.com".to_owned(),
+                },
+            ]
+        );
+    }
+}
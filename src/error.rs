@@ -0,0 +1,213 @@
+#[cfg(target_os = "macos")]
+use std::any::Any;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// System Settings pane where Automation consent is granted.
+pub const AUTOMATION_SETTINGS_PANE: &str = "System Settings > Privacy & Security > Automation";
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// In strict mode a capture that timed out, lacked permission or couldn't
+/// restore the clipboard fails instead of returning empty text, so automation
+/// doesn't carry on with input that was never captured. Off by default,
+/// and only the macOS capture checks it.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// The `Result` every fallible public function returns.
+pub type Result<T, E = SelectedTextError> = std::result::Result<T, E>;
+
+/// Why a call failed. Causes callers branch on have their own variant,
+/// everything else is `Other` with the message.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SelectedTextError {
+    /// No element or app has keyboard focus.
+    #[error("nothing has keyboard focus")]
+    NoFocusedElement,
+    /// The focused element has no selection.
+    #[error("nothing is selected")]
+    NoSelection,
+    /// The copy chord was posted but never reached the app and nothing was
+    /// copied, another event tap consumed it.
+    #[error(
+        "the copy shortcut was intercepted by another event tap. Utilities like Keyboard \
+         Maestro, BetterTouchTool or Karabiner-Elements can consume or remap Cmd+C: exclude it \
+         there, or copy with CopyMethod::MenuItem instead"
+    )]
+    InterceptedByEventTap,
+    /// The focused element belongs to the calling process, copying would
+    /// send Cmd+C into our own UI.
+    #[error(
+        "the focused element belongs to this process, read the selection from your own views \
+         (e.g. NSTextView's selectedRanges) instead"
+    )]
+    SelfFocused,
+    /// Something panicked mid-capture, the user's pasteboard was restored
+    /// and the panic stopped here instead of unwinding into the host app.
+    #[error("capture panicked: {0}")]
+    Panicked(String),
+    /// Strict mode: the pasteboard didn't change before the timeout.
+    #[error("the app didn't copy anything before the timeout, nothing may be selected")]
+    PasteboardTimeout,
+    /// The process isn't trusted for Accessibility, see
+    /// `accessibility::request_trust`. AX reads fail with it, and in strict
+    /// mode so does a capture.
+    #[error(
+        "this process isn't allowed to use Accessibility, grant it in System Settings > \
+         Privacy & Security > Accessibility"
+    )]
+    AccessibilityDenied,
+    /// Posting the copy chord or another key event failed.
+    #[error("failed to simulate a key press: {0}")]
+    KeySimulationFailed(String),
+    /// Strict mode: the user's clipboard couldn't be put back.
+    #[error("the clipboard couldn't be restored")]
+    RestoreFailed,
+    /// `osascript` failed for a reason other than a refused Apple Event.
+    #[error("AppleScript failed: {stderr}")]
+    AppleScriptFailed { stderr: String },
+    /// Apple Events to `app` were refused (error -1743), the user has to
+    /// allow this process to control it under `settings_pane`.
+    #[error("this process isn't allowed to control {app}, allow it in {settings_pane}")]
+    AutomationDenied {
+        app: String,
+        settings_pane: &'static str,
+    },
+    /// The frontmost app is on the deny list, see `set_app_denied`. Nothing
+    /// was read from it.
+    #[error("capturing from {bundle_id} is denied")]
+    AppDenied { bundle_id: String },
+    /// An allow list is set with `set_allowed_apps` and the frontmost app,
+    /// `None` when it has no bundle id, isn't on it.
+    #[error(
+        "capturing from {} isn't allowed, it's not on the allow list",
+        bundle_id.as_deref().unwrap_or("an app without a bundle id")
+    )]
+    AppNotAllowed { bundle_id: Option<String> },
+    /// The screen is locked or the screensaver is running, a copy would go
+    /// to the login window.
+    #[error("the screen is locked, there's nothing to capture")]
+    ScreenLocked,
+    /// Anything else, with its message.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl SelectedTextError {
+    /// A stable name for the variant, for logs and telemetry.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SelectedTextError::NoFocusedElement => "no-focused-element",
+            SelectedTextError::NoSelection => "no-selection",
+            SelectedTextError::InterceptedByEventTap => "intercepted-by-event-tap",
+            SelectedTextError::SelfFocused => "self-focused",
+            SelectedTextError::Panicked(_) => "panicked",
+            SelectedTextError::PasteboardTimeout => "timeout",
+            SelectedTextError::AccessibilityDenied => "not-trusted",
+            SelectedTextError::KeySimulationFailed(_) => "key-simulation-failed",
+            SelectedTextError::RestoreFailed => "restore-failed",
+            SelectedTextError::AppleScriptFailed { .. } => "applescript-failed",
+            SelectedTextError::AutomationDenied { .. } => "automation-denied",
+            SelectedTextError::AppDenied { .. } => "app-denied",
+            SelectedTextError::AppNotAllowed { .. } => "app-not-allowed",
+            SelectedTextError::ScreenLocked => "screen-locked",
+            SelectedTextError::Other(_) => "other",
+        }
+    }
+
+    pub(crate) fn other(message: impl fmt::Display) -> Self {
+        SelectedTextError::Other(message.to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn panicked(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload.downcast_ref::<&str>().map_or_else(
+                || "unknown panic".to_owned(),
+                |message| (*message).to_owned(),
+            ),
+        };
+        SelectedTextError::Panicked(message)
+    }
+
+    /// Recognizes a refused Apple Event in the stderr of `osascript`. `target`
+    /// is the app the script talks to, used when the message doesn't name it.
+    #[cfg(target_os = "macos")]
+    pub(crate) fn from_osascript_stderr(stderr: &str, target: &str) -> Option<Self> {
+        if !stderr.contains("-1743") {
+            return None;
+        }
+        // "Not authorized to send Apple events to System Events. (-1743)"
+        let app = stderr
+            .split_once("Apple events to ")
+            .and_then(|(_, rest)| rest.split_once(". ("))
+            .map_or(target, |(app, _)| app.trim());
+        Some(SelectedTextError::AutomationDenied {
+            app: app.to_owned(),
+            settings_pane: AUTOMATION_SETTINGS_PANE,
+        })
+    }
+}
+
+// internals still use anyhow, the typed error they carry comes back out
+impl From<anyhow::Error> for SelectedTextError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<SelectedTextError>() {
+            Ok(error) => error,
+            Err(error) => SelectedTextError::Other(format!("{:#}", error)),
+        }
+    }
+}
+
+impl From<std::io::Error> for SelectedTextError {
+    fn from(error: std::io::Error) -> Self {
+        SelectedTextError::other(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_automation_denied() {
+        let stderr =
+            "0:95: execution error: Not authorized to send Apple events to Finder. (-1743)\n";
+        assert_eq!(
+            SelectedTextError::from_osascript_stderr(stderr, "System Events"),
+            Some(SelectedTextError::AutomationDenied {
+                app: "Finder".to_owned(),
+                settings_pane: AUTOMATION_SETTINGS_PANE,
+            })
+        );
+        assert!(matches!(
+            SelectedTextError::from_osascript_stderr("execution error: (-1743)", "System Events"),
+            Some(SelectedTextError::AutomationDenied { app, .. }) if app == "System Events"
+        ));
+        assert_eq!(
+            SelectedTextError::from_osascript_stderr("0:12: syntax error (-2741)", "Finder"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_anyhow() {
+        let typed: anyhow::Error = SelectedTextError::NoSelection.into();
+        assert_eq!(
+            SelectedTextError::from(typed.context("reading the selection")),
+            SelectedTextError::NoSelection
+        );
+        let other = SelectedTextError::from(anyhow::anyhow!("boom"));
+        assert_eq!(other, SelectedTextError::Other("boom".to_owned()));
+        assert_eq!(other.code(), "other");
+    }
+}
@@ -0,0 +1,196 @@
+#[cfg(target_os = "macos")]
+use std::ffi::OsString;
+#[cfg(target_os = "macos")]
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(target_os = "macos")]
+use objc2::{rc::Retained, runtime::AnyObject, ClassType};
+#[cfg(target_os = "macos")]
+use objc2_foundation::{
+    NSArray, NSCopying, NSString, NSURLLocalizedTypeDescriptionKey, NSURLTypeIdentifierKey, NSURL,
+};
+
+#[cfg(target_os = "macos")]
+use crate::applescript;
+use crate::Result;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FileMetadata {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    /// Size in bytes; for directories this is the size of the directory entry, not its contents.
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_directory: bool,
+    /// Uniform Type Identifier, e.g. `public.png`. Only read on macOS.
+    pub uti: Option<String>,
+    /// Localized kind as shown by Finder, e.g. `PNG image`. Only read on
+    /// macOS.
+    pub kind: Option<String>,
+}
+
+pub fn file_metadata(path: &Path) -> Result<FileMetadata> {
+    let metadata = std::fs::metadata(path)?;
+    let (uti, kind) = type_identifiers(path);
+    Ok(FileMetadata {
+        path: path.to_owned(),
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        is_directory: metadata.is_dir(),
+        uti,
+        kind,
+    })
+}
+
+/// Turns the newline-delimited output of the Finder scripts, which print
+/// every path with `applescript::QUOTE_HANDLER`, into plain paths, dropping
+/// blank lines and duplicates. Lines that aren't UTF-8 are decoded with
+/// replacement characters, and also returned as the exact paths so one odd
+/// file name doesn't lose the rest of the selection.
+#[cfg(target_os = "macos")]
+pub(crate) fn decode_paths(output: &[u8]) -> (Vec<String>, Vec<PathBuf>) {
+    let mut paths: Vec<String> = Vec::new();
+    let mut lossy_paths = Vec::new();
+    for line in output.split(|&b| b == b'\n') {
+        let path = match std::str::from_utf8(line) {
+            Ok(line) => applescript::unquote(line),
+            Err(_) => {
+                let exact = applescript::unquote_bytes(line);
+                let path = String::from_utf8_lossy(&exact).into_owned();
+                let exact = PathBuf::from(OsString::from_vec(exact));
+                if !lossy_paths.contains(&exact) {
+                    lossy_paths.push(exact);
+                }
+                path
+            }
+        };
+        if !path.is_empty() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    (paths, lossy_paths)
+}
+
+// JSON has no room for paths that aren't UTF-8
+pub(crate) fn serialize_path_lossy<S: serde::Serializer>(
+    path: &Path,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+pub(crate) fn serialize_optional_path_lossy<S: serde::Serializer>(
+    path: &Option<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => serializer.serialize_some(&path.to_string_lossy()),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) fn serialize_paths_lossy<S: serde::Serializer>(
+    paths: &[PathBuf],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(paths.iter().map(|path| path.to_string_lossy()))
+}
+
+#[cfg(target_os = "macos")]
+fn type_identifiers(path: &Path) -> (Option<String>, Option<String>) {
+    let Some(path) = path.to_str() else {
+        return (None, None);
+    };
+    let url = unsafe { NSURL::fileURLWithPath(&NSString::from_str(path)) };
+    let keys = unsafe {
+        NSArray::from_vec(vec![
+            NSURLTypeIdentifierKey.copy(),
+            NSURLLocalizedTypeDescriptionKey.copy(),
+        ])
+    };
+    let Ok(values) = (unsafe { url.resourceValuesForKeys_error(&keys) }) else {
+        return (None, None);
+    };
+    let string_value = |key: &NSString| values.get(key).and_then(as_string);
+    unsafe {
+        (
+            string_value(NSURLTypeIdentifierKey),
+            string_value(NSURLLocalizedTypeDescriptionKey),
+        )
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn as_string(value: &AnyObject) -> Option<String> {
+    let is_string: bool = unsafe { objc2::msg_send![value, isKindOfClass: NSString::class()] };
+    if !is_string {
+        return None;
+    }
+    let string = unsafe { Retained::retain(value as *const AnyObject as *mut NSString) }?;
+    Some(string.to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn type_identifiers(_path: &Path) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+#[cfg(all(test, target_os = "macos"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unquote_path() {
+        assert_eq!(
+            applescript::unquote("\"/Users/me/a b.txt\""),
+            "/Users/me/a b.txt"
+        );
+        assert_eq!(
+            applescript::unquote("\"/Users/me/say \\\"hi\\\".txt\""),
+            "/Users/me/say \"hi\".txt"
+        );
+        assert_eq!(applescript::unquote("/already/plain"), "/already/plain");
+    }
+
+    #[test]
+    fn test_decode_paths() {
+        let output =
+            b"\"/Users/me/a.txt\"\n\n\"/Users/me/b \\\"c\\\".txt\"\r\n\"/Users/me/a.txt\"\n";
+        assert_eq!(
+            decode_paths(output),
+            (
+                vec![
+                    "/Users/me/a.txt".to_owned(),
+                    "/Users/me/b \"c\".txt".to_owned()
+                ],
+                vec![]
+            )
+        );
+        assert_eq!(decode_paths(b""), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_decode_paths_keeps_odd_names() {
+        let output = b"\"/Users/me/a.txt\"\n\"/Users/me/caf\xE9 \\\"x\\\".txt\"\n";
+        let (paths, lossy_paths) = decode_paths(output);
+        assert_eq!(
+            paths,
+            ["/Users/me/a.txt", "/Users/me/caf\u{fffd} \"x\".txt"]
+        );
+        assert_eq!(
+            lossy_paths,
+            [PathBuf::from(OsString::from_vec(
+                b"/Users/me/caf\xE9 \"x\".txt".to_vec()
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_file_metadata() {
+        let metadata = file_metadata(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        assert!(metadata.is_directory);
+        assert_eq!(metadata.uti.as_deref(), Some("public.folder"));
+    }
+}
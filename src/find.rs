@@ -0,0 +1,48 @@
+// Cmd+E puts the selection on the find pasteboard and Cmd+G in any app
+// searches for what's there. A "search for selection" feature built on a
+// capture can take part in that instead of keeping its own search string.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use objc2_app_kit::{NSPasteboard, NSPasteboardNameFind, NSPasteboardTypeString};
+use objc2_foundation::NSString;
+
+use crate::{diagnostics, Result, SelectedText, SelectedTextError};
+
+static PUBLISH: AtomicBool = AtomicBool::new(false);
+
+/// Puts the text of every successful text capture on the find pasteboard,
+/// like Cmd+E does. Off by default.
+pub fn set_publish_to_find_pasteboard(publish: bool) {
+    PUBLISH.store(publish, Ordering::Relaxed);
+}
+
+/// The string apps search for with Cmd+G.
+pub fn find_pasteboard_text() -> Option<String> {
+    let pasteboard = unsafe { NSPasteboard::pasteboardWithName(NSPasteboardNameFind) };
+    unsafe { pasteboard.stringForType(NSPasteboardTypeString) }.map(|text| text.to_string())
+}
+
+pub fn set_find_pasteboard_text(text: &str) -> Result<()> {
+    let pasteboard = unsafe { NSPasteboard::pasteboardWithName(NSPasteboardNameFind) };
+    unsafe { pasteboard.clearContents() };
+    if !unsafe { pasteboard.setString_forType(&NSString::from_str(text), NSPasteboardTypeString) } {
+        return Err(SelectedTextError::other(
+            "Failed to write to the find pasteboard",
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn publish(selected_text: &SelectedText) {
+    if !PUBLISH.load(Ordering::Relaxed) || selected_text.is_file_paths {
+        return;
+    }
+    let text = selected_text.text.join("\n");
+    if text.is_empty() {
+        return;
+    }
+    if let Err(e) = set_find_pasteboard_text(&text) {
+        diagnostics::warn!("{}", e);
+    }
+}
@@ -0,0 +1,108 @@
+// Strategies are picked per app (handlers, copy methods, chord timings, the
+// strategy cache), and the first capture in an app pays for finding out.
+// Hearing when an app comes to the front lets a client do that early.
+
+use std::ptr::NonNull;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use block2::RcBlock;
+use objc2::rc::Retained;
+use objc2::ClassType;
+use objc2_app_kit::{
+    NSRunningApplication, NSWorkspace, NSWorkspaceApplicationKey,
+    NSWorkspaceDidActivateApplicationNotification,
+};
+use objc2_foundation::{NSNotification, NSObject};
+
+/// An app that came to the front.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FrontmostApp {
+    pub name: String,
+    pub bundle_id: Option<String>,
+    pub pid: i32,
+}
+
+impl FrontmostApp {
+    fn of(app: &NSRunningApplication) -> Self {
+        FrontmostApp {
+            name: unsafe { app.localizedName() }.map_or_else(String::new, |name| name.to_string()),
+            bundle_id: unsafe { app.bundleIdentifier() }.map(|id| id.to_string()),
+            pid: unsafe { app.processIdentifier() },
+        }
+    }
+}
+
+/// A running `watch_frontmost_app`. The watch stops when this is dropped.
+pub struct FrontmostAppWatch {
+    receiver: Receiver<FrontmostApp>,
+    observer: Retained<NSObject>,
+}
+
+impl FrontmostAppWatch {
+    /// Waits for the next app to come to the front.
+    pub fn recv(&self) -> Option<FrontmostApp> {
+        self.receiver.recv().ok()
+    }
+
+    /// Like `recv`, giving up after `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<FrontmostApp> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// The next app if one is waiting.
+    pub fn try_recv(&self) -> Option<FrontmostApp> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// The channel the apps arrive on.
+    pub fn receiver(&self) -> &Receiver<FrontmostApp> {
+        &self.receiver
+    }
+}
+
+impl Drop for FrontmostAppWatch {
+    fn drop(&mut self) {
+        unsafe {
+            NSWorkspace::sharedWorkspace()
+                .notificationCenter()
+                .removeObserver(&self.observer)
+        };
+    }
+}
+
+/// Sends the app that comes to the front each time the frontmost app
+/// changes. NSWorkspace posts its notifications on the main thread, so they
+/// only arrive while the main run loop runs, as it does in any app; a
+/// command-line tool has to run it.
+pub fn watch_frontmost_app() -> FrontmostAppWatch {
+    let (sender, receiver) = mpsc::channel();
+    let block = RcBlock::new(move |notification: NonNull<NSNotification>| {
+        let notification = unsafe { notification.as_ref() };
+        if let Some(app) = activated_app(notification) {
+            let _ = sender.send(FrontmostApp::of(&app));
+        }
+    });
+    let observer = unsafe {
+        NSWorkspace::sharedWorkspace()
+            .notificationCenter()
+            .addObserverForName_object_queue_usingBlock(
+                Some(NSWorkspaceDidActivateApplicationNotification),
+                None,
+                None,
+                &block,
+            )
+    };
+    FrontmostAppWatch { receiver, observer }
+}
+
+fn activated_app(notification: &NSNotification) -> Option<Retained<NSRunningApplication>> {
+    let user_info = unsafe { notification.userInfo() }?;
+    let app = unsafe { user_info.objectForKey(NSWorkspaceApplicationKey) }?;
+    let is_app: bool =
+        unsafe { objc2::msg_send![&app, isKindOfClass: NSRunningApplication::class()] };
+    if !is_app {
+        return None;
+    }
+    Some(unsafe { Retained::cast(app) })
+}
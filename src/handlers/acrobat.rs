@@ -0,0 +1,42 @@
+// Acrobat and Acrobat Reader keep selected text out of AX and take their time
+// answering Cmd+C. Their scripting dictionaries have no text selection
+// either, so the handler only tunes the copy and tidies what it brings back.
+
+use std::time::Duration;
+
+use crate::{normalize, ChordTiming, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &["com.adobe.Acrobat.Pro", "com.adobe.Reader"];
+
+const PASTEBOARD_WAIT: Duration = Duration::from_millis(2000);
+
+pub(crate) struct Acrobat;
+
+impl AppHandler for Acrobat {
+    fn name(&self) -> &'static str {
+        "acrobat"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, _app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        Ok(None)
+    }
+
+    fn chord_timing(&self) -> Option<ChordTiming> {
+        Some(ChordTiming::CONSERVATIVE)
+    }
+
+    fn min_pasteboard_wait(&self) -> Option<Duration> {
+        Some(PASTEBOARD_WAIT)
+    }
+
+    // PDF text keeps the hyphens of words broken across lines
+    fn tidy_copied_text(&self, text: &str) -> Option<String> {
+        Some(normalize::join_hyphenated_lines(text))
+    }
+}
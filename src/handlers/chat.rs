@@ -0,0 +1,96 @@
+// Slack and Discord are Electron apps whose composer keeps focus while the
+// user selects in the message history, so reading the focused element often
+// returns the draft, or nothing, instead of the messages. The composer is
+// told apart by its editable role and the history is read off the web area.
+
+use std::time::Duration;
+
+use accessibility_ng::{AXUIElement, AXUIElementAttributes};
+
+use crate::{ax, ChatPane, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &[
+    "com.tinyspeck.slackmacgap",
+    "com.hnc.Discord",
+    "com.hnc.DiscordPTB",
+    "com.hnc.DiscordCanary",
+];
+
+const TREE_RETRIES: u32 = 5;
+const TREE_RETRY_DELAY: Duration = Duration::from_millis(60);
+
+/// Levels above the focused element an editable ancestor is looked for at,
+/// the composer nests its text in a few groups.
+const COMPOSER_DEPTH: usize = 4;
+const WEB_AREA_DEPTH: usize = 16;
+
+pub(crate) struct ChatApp;
+
+impl AppHandler for ChatApp {
+    fn name(&self) -> &'static str {
+        "chat"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let app = ax::focused_app()?;
+        let retries = if ax::enable_app_attribute(&app, ax::MANUAL_ACCESSIBILITY) {
+            TREE_RETRIES
+        } else {
+            1
+        };
+        for attempt in 0..retries {
+            if attempt > 0 {
+                std::thread::sleep(TREE_RETRY_DELAY);
+            }
+            if let Some((text, pane)) = selection(&app) {
+                let mut selected_text = SelectedText::from_text(app_name.to_owned(), text);
+                selected_text.chat_pane = Some(pane);
+                return Ok(Some(selected_text));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn is_editable_role(role: &str) -> bool {
+    matches!(role, "AXTextArea" | "AXTextField" | "AXComboBox")
+}
+
+fn selection(app: &AXUIElement) -> Option<(String, ChatPane)> {
+    let focused = app.focused_uielement().ok()?;
+    let composer = ax::self_or_ancestor(focused.clone(), COMPOSER_DEPTH, |element| {
+        ax::role(element).is_some_and(|role| is_editable_role(&role))
+    });
+    // an empty draft selection means the user selected somewhere else
+    if let Some(composer) = composer {
+        let text = composer.selected_text().ok().map(|text| text.to_string());
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            return Some((text, ChatPane::Composer));
+        }
+    }
+    let web_area = ax::self_or_ancestor(focused, WEB_AREA_DEPTH, |element| {
+        ax::role(element).as_deref() == Some("AXWebArea")
+    })?;
+    let text = ax::text_marker_selection(&web_area).filter(|text| !text.is_empty())?;
+    Some((text, ChatPane::MessageHistory))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_chat_apps() {
+        assert!(ChatApp.handles("com.tinyspeck.slackmacgap"));
+        assert!(ChatApp.handles("com.hnc.discordcanary"));
+        assert!(!ChatApp.handles("com.microsoft.teams2"));
+        assert!(is_editable_role("AXTextArea"));
+        assert!(!is_editable_role("AXStaticText"));
+    }
+}
@@ -0,0 +1,93 @@
+// Chromium browsers build their accessibility tree on demand, so right after
+// AXManualAccessibility is set the window has no web area yet. Once it's
+// there, the selection and the page address are read off the AXWebArea.
+
+use std::time::Duration;
+
+use accessibility_ng::{AXUIElement, AXUIElementAttributes};
+
+use crate::{ax, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &[
+    "com.google.Chrome",
+    "com.google.Chrome.beta",
+    "com.google.Chrome.dev",
+    "com.google.Chrome.canary",
+    "org.chromium.Chromium",
+    "com.microsoft.edgemac",
+    "com.microsoft.edgemac.Beta",
+    "com.microsoft.edgemac.Dev",
+    "com.brave.Browser",
+    "company.thebrowser.Browser",
+    "com.vivaldi.Vivaldi",
+    "com.operasoftware.Opera",
+];
+
+const TREE_RETRIES: u32 = 5;
+const TREE_RETRY_DELAY: Duration = Duration::from_millis(60);
+
+const WEB_AREA_DEPTH: usize = 12;
+/// Elements looked at when searching the window for the web area.
+const WEB_AREA_SEARCH_BUDGET: usize = 400;
+
+pub(crate) struct Chromium;
+
+impl AppHandler for Chromium {
+    fn name(&self) -> &'static str {
+        "chromium"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let app = ax::focused_app()?;
+        let retries = if ax::enable_app_attribute(&app, ax::MANUAL_ACCESSIBILITY) {
+            TREE_RETRIES
+        } else {
+            1
+        };
+        for attempt in 0..retries {
+            if attempt > 0 {
+                std::thread::sleep(TREE_RETRY_DELAY);
+            }
+            let Some(web_area) = web_area(&app) else {
+                continue;
+            };
+            let Some(text) = selection(&app, &web_area) else {
+                continue;
+            };
+            let mut selected_text = SelectedText::from_text(app_name.to_owned(), text);
+            selected_text.url = ax::url_of(&web_area);
+            return Ok(Some(selected_text));
+        }
+        Ok(None)
+    }
+}
+
+fn is_web_area(element: &AXUIElement) -> bool {
+    ax::role(element).as_deref() == Some("AXWebArea")
+}
+
+// a fresh tree has focus on the window rather than inside the page
+fn web_area(app: &AXUIElement) -> Option<AXUIElement> {
+    if let Ok(focused) = app.focused_uielement() {
+        if let Some(web_area) = ax::self_or_ancestor(focused, WEB_AREA_DEPTH, is_web_area) {
+            return Some(web_area);
+        }
+    }
+    let window = app.focused_window().ok()?;
+    ax::find_descendant(&window, WEB_AREA_SEARCH_BUDGET, is_web_area)
+}
+
+fn selection(app: &AXUIElement, web_area: &AXUIElement) -> Option<String> {
+    if let Some(text) = ax::text_marker_selection(web_area).filter(|text| !text.is_empty()) {
+        return Some(text);
+    }
+    // text fields keep their selection to themselves
+    let focused = app.focused_uielement().ok()?;
+    Some(focused.selected_text().ok()?.to_string()).filter(|text| !text.is_empty())
+}
@@ -0,0 +1,61 @@
+// VS Code and its forks are Electron apps: no accessibility tree until asked
+// for one, then the editor's selection is on the focused AXTextArea.
+
+use std::time::Duration;
+
+use crate::{ax, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &[
+    "com.microsoft.VSCode",
+    "com.microsoft.VSCodeInsiders",
+    "com.vscodium",
+    // Cursor
+    "com.todesktop.230313mzl4w4u92",
+    "com.exafunction.windsurf",
+];
+
+/// Tries while an app that was just asked for its tree builds it.
+const TREE_RETRIES: u32 = 5;
+const TREE_RETRY_DELAY: Duration = Duration::from_millis(40);
+
+pub(crate) struct ElectronEditor;
+
+impl AppHandler for ElectronEditor {
+    fn name(&self) -> &'static str {
+        "electron-editor"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let app = ax::focused_app()?;
+        let retries = if ax::enable_app_attribute(&app, ax::MANUAL_ACCESSIBILITY) {
+            TREE_RETRIES
+        } else {
+            1
+        };
+        for attempt in 0..retries {
+            if attempt > 0 {
+                std::thread::sleep(TREE_RETRY_DELAY);
+            }
+            if let Some(text) = text_area_selection(&app) {
+                return Ok(Some(SelectedText::from_text(app_name.to_owned(), text)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn text_area_selection(app: &accessibility_ng::AXUIElement) -> Option<String> {
+    use accessibility_ng::AXUIElementAttributes;
+
+    let focused = app.focused_uielement().ok()?;
+    if ax::role(&focused)? != "AXTextArea" {
+        return None;
+    }
+    Some(focused.selected_text().ok()?.to_string())
+}
@@ -0,0 +1,79 @@
+// Firefox starts its accessibility engine only when it thinks an assistive
+// app is running, and until then every AX read comes back empty. Once it's
+// up the selection is on the focused document, as text markers like WebKit.
+
+use std::time::Duration;
+
+use accessibility_ng::{AXUIElement, AXUIElementAttributes};
+
+use crate::{ax, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &[
+    "org.mozilla.firefox",
+    "org.mozilla.firefoxdeveloperedition",
+    "org.mozilla.nightly",
+    "org.torproject.torbrowser",
+    "net.waterfox.waterfox",
+    "app.zen-browser.zen",
+];
+
+/// Spinning up the engine takes a while on large pages.
+const ENGINE_RETRIES: u32 = 6;
+const ENGINE_RETRY_DELAY: Duration = Duration::from_millis(80);
+
+const DOCUMENT_DEPTH: usize = 12;
+
+pub(crate) struct Firefox;
+
+impl AppHandler for Firefox {
+    fn name(&self) -> &'static str {
+        "firefox"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let app = ax::focused_app()?;
+        // newer versions listen to the first, older ones to the second
+        let manual = ax::enable_app_attribute(&app, ax::MANUAL_ACCESSIBILITY);
+        let enhanced = ax::enable_app_attribute(&app, ax::ENHANCED_USER_INTERFACE);
+        let retries = if manual || enhanced {
+            ENGINE_RETRIES
+        } else {
+            1
+        };
+        for attempt in 0..retries {
+            if attempt > 0 {
+                std::thread::sleep(ENGINE_RETRY_DELAY);
+            }
+            if let Some((text, url)) = document_selection(&app) {
+                let mut selected_text = SelectedText::from_text(app_name.to_owned(), text);
+                selected_text.url = url;
+                return Ok(Some(selected_text));
+            }
+        }
+        // the copy fallback works whether or not the engine came up
+        Ok(None)
+    }
+}
+
+fn document_selection(app: &AXUIElement) -> Option<(String, Option<String>)> {
+    let focused = app.focused_uielement().ok()?;
+    let document = ax::self_or_ancestor(focused.clone(), DOCUMENT_DEPTH, |element| {
+        ax::role(element).as_deref() == Some("AXWebArea")
+    });
+    let url = document.as_ref().and_then(ax::url_of);
+    let text = document
+        .as_ref()
+        .and_then(ax::text_marker_selection)
+        .filter(|text| !text.is_empty())
+        .or_else(|| {
+            let text = focused.selected_text().ok()?.to_string();
+            (!text.is_empty()).then_some(text)
+        })?;
+    Some((text, url))
+}
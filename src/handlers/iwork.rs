@@ -0,0 +1,131 @@
+// Pages, Numbers and Keynote put proxy elements in their AX trees that
+// report no selected text, and copying from Numbers gives a table whose cell
+// boundaries don't survive as plain text. Numbers and Keynote hand out their
+// selection over AppleScript, Pages' body text answers a ranged AX read.
+
+use accessibility_ng::AXUIElementAttributes;
+
+use crate::{ax, Result, SelectedText};
+
+use super::{is_any_of, scripted_selection, AppHandler};
+
+const BODY_DEPTH: usize = 3;
+
+enum Source {
+    /// The selected range of the focused text area, read with
+    /// AXStringForRange.
+    TextRange,
+    /// Prints the selection with `quote_text`.
+    Script(&'static str),
+}
+
+pub(crate) struct IWorkApp {
+    name: &'static str,
+    bundle_id: &'static str,
+    source: Source,
+}
+
+pub(crate) static SUITE: &[&dyn AppHandler] = &[&PAGES, &NUMBERS, &KEYNOTE];
+
+// Pages' dictionary has no text selection
+static PAGES: IWorkApp = IWorkApp {
+    name: "pages",
+    bundle_id: "com.apple.iWork.Pages",
+    source: Source::TextRange,
+};
+
+// cells are joined like a spreadsheet paste expects, tabs between and lines
+// after, so the table structure survives
+static NUMBERS: IWorkApp = IWorkApp {
+    name: "numbers",
+    bundle_id: "com.apple.iWork.Numbers",
+    source: Source::Script(
+        r#"
+tell application id "com.apple.iWork.Numbers"
+	tell active sheet of front document
+		set theTable to first table whose class of selection range is range
+		set rowTexts to {}
+		repeat with theRow in rows of selection range of theTable
+			set cellTexts to {}
+			repeat with theCell in cells of theRow
+				set cellValue to formatted value of theCell
+				if cellValue is missing value then set cellValue to ""
+				set end of cellTexts to cellValue
+			end repeat
+			set AppleScript's text item delimiters to tab
+			set end of rowTexts to cellTexts as text
+		end repeat
+	end tell
+end tell
+set AppleScript's text item delimiters to linefeed
+set tableText to rowTexts as text
+set AppleScript's text item delimiters to ""
+return my quote_text(tableText)
+"#,
+    ),
+};
+
+// the text of the selected slides, or of the selected items on a slide
+static KEYNOTE: IWorkApp = IWorkApp {
+    name: "keynote",
+    bundle_id: "com.apple.iWork.Keynote",
+    source: Source::Script(
+        r#"
+tell application id "com.apple.iWork.Keynote"
+	set itemTexts to {}
+	repeat with theItem in (selection of front document)
+		if class of theItem is slide then
+			tell theItem
+				if title showing then set end of itemTexts to object text of default title item
+				if body showing then set end of itemTexts to object text of default body item
+				repeat with theText in text items
+					set end of itemTexts to object text of theText
+				end repeat
+			end tell
+		else
+			try
+				set end of itemTexts to object text of theItem
+			end try
+		end if
+	end repeat
+end tell
+set AppleScript's text item delimiters to linefeed
+set slideText to itemTexts as text
+set AppleScript's text item delimiters to ""
+return my quote_text(slideText)
+"#,
+    ),
+};
+
+impl AppHandler for IWorkApp {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, &[self.bundle_id])
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let text = match self.source {
+            Source::TextRange => body_selection(),
+            Source::Script(script) => scripted_selection(script, app_name),
+        };
+        Ok(text
+            .filter(|text| !text.is_empty())
+            .map(|text| SelectedText::from_text(app_name.to_owned(), text)))
+    }
+}
+
+fn body_selection() -> Option<String> {
+    let focused = ax::focused_element().ok()?;
+    let body = ax::self_or_ancestor(focused, BODY_DEPTH, |element| {
+        ax::role(element).as_deref() == Some("AXTextArea")
+    })?;
+    let selected_text = body.selected_text().ok().map(|text| text.to_string());
+    if let Some(text) = selected_text.filter(|text| !text.is_empty()) {
+        return Some(text);
+    }
+    let range = ax::selected_range(&body).ok()?;
+    ax::string_for_range(&body, range)
+}
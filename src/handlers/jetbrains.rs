@@ -0,0 +1,84 @@
+// IntelliJ-family IDEs only expose their Swing tree once Java's AX bridge
+// sees an assistive app, and the editor component often reports an empty
+// AXSelectedText while AXSelectedTextRange is right.
+
+use std::time::Duration;
+
+use accessibility_ng::AXUIElement;
+
+use crate::{ax, Result, SelectedText};
+
+use super::AppHandler;
+
+const BUNDLE_ID_PREFIXES: &[&str] = &["com.jetbrains.", "com.google.android.studio"];
+
+/// The bridge builds the tree on its own thread after the flag is set.
+const BRIDGE_RETRIES: u32 = 5;
+const BRIDGE_RETRY_DELAY: Duration = Duration::from_millis(60);
+
+/// The focused element can be a caret child of the editor component.
+const EDITOR_DEPTH: usize = 3;
+
+pub(crate) struct JetBrainsIde;
+
+impl AppHandler for JetBrainsIde {
+    fn name(&self) -> &'static str {
+        "jetbrains"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        let bundle_id = bundle_id.to_ascii_lowercase();
+        BUNDLE_ID_PREFIXES
+            .iter()
+            .any(|prefix| bundle_id.starts_with(prefix))
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let app = ax::focused_app()?;
+        let retries = if ax::enable_app_attribute(&app, ax::ENHANCED_USER_INTERFACE) {
+            BRIDGE_RETRIES
+        } else {
+            1
+        };
+        for attempt in 0..retries {
+            if attempt > 0 {
+                std::thread::sleep(BRIDGE_RETRY_DELAY);
+            }
+            if let Some(text) = editor_selection(&app) {
+                return Ok(Some(SelectedText::from_text(app_name.to_owned(), text)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn editor_selection(app: &AXUIElement) -> Option<String> {
+    use accessibility_ng::AXUIElementAttributes;
+
+    let focused = app.focused_uielement().ok()?;
+    let editor = ax::self_or_ancestor(focused, EDITOR_DEPTH, |element| {
+        ax::role(element).as_deref() == Some("AXTextArea")
+    })?;
+    let selected_text = editor.selected_text().ok().map(|text| text.to_string());
+    if let Some(text) = selected_text.filter(|text| !text.is_empty()) {
+        return Some(text);
+    }
+    let range = ax::selected_range(&editor).ok()?;
+    if range.length == 0 {
+        return Some(String::new());
+    }
+    ax::string_for_range(&editor, range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_jetbrains_ides() {
+        assert!(JetBrainsIde.handles("com.jetbrains.intellij"));
+        assert!(JetBrainsIde.handles("com.jetbrains.PyCharm-EAP"));
+        assert!(JetBrainsIde.handles("com.google.android.studio"));
+        assert!(!JetBrainsIde.handles("com.jetbrainsfan.app"));
+    }
+}
@@ -0,0 +1,280 @@
+// Apps whose selection the generic AX-then-copy path misses, or only gets
+// through a slow keystroke and clipboard round trip, get a handler of their
+// own that's tried first.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::{
+    applescript, breadcrumb, diagnostics, CaptureStrategy, ChordTiming, CopyMethod, Result,
+    SelectedText, SelectedTextError,
+};
+
+mod acrobat;
+mod chat;
+mod chromium;
+mod electron;
+mod firefox;
+mod iwork;
+mod jetbrains;
+mod office;
+mod safari;
+mod terminals;
+mod xcode;
+
+/// Captures the selection in the apps it handles. Implement this to add an
+/// app, see `register_handler`.
+///
+/// Only `name` and `capture` are required. Methods added later come with a
+/// default that keeps the generic behavior, so handlers in other crates keep
+/// compiling.
+pub trait AppHandler: Send + Sync {
+    /// Shows up in breadcrumbs, see `last_capture_breadcrumb`.
+    fn name(&self) -> &'static str;
+
+    /// Narrows down the bundle ids a handler was registered for, e.g. to
+    /// skip an app's helper processes. All of them by default.
+    fn handles(&self, _bundle_id: &str) -> bool {
+        true
+    }
+
+    /// The selection, or `None` to fall through to the generic path.
+    fn capture(&self, app_name: &str, bundle_id: &str) -> Result<Option<SelectedText>>;
+
+    /// How to copy after falling through, instead of what the caller asked
+    /// for.
+    fn copy_method(&self) -> Option<CopyMethod> {
+        None
+    }
+
+    /// Timing of the copy chord after falling through, unless one was set
+    /// with `set_chord_timing`.
+    fn chord_timing(&self) -> Option<ChordTiming> {
+        None
+    }
+
+    /// Shortest time to wait for the pasteboard after a copy, for apps that
+    /// are slow to write it.
+    fn min_pasteboard_wait(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Cleans up text copied after falling through, e.g. PDF line breaks.
+    /// `None` keeps it as is.
+    fn tidy_copied_text(&self, _text: &str) -> Option<String> {
+        None
+    }
+}
+
+static BUILT_IN_HANDLERS: &[&dyn AppHandler] = &[
+    &electron::ElectronEditor,
+    &jetbrains::JetBrainsIde,
+    &safari::Safari,
+    &chromium::Chromium,
+    &firefox::Firefox,
+    &chat::ChatApp,
+    &acrobat::Acrobat,
+    &xcode::Xcode,
+];
+
+/// Handlers for apps that ship together, e.g. one per Office app.
+static BUILT_IN_SUITES: &[&[&dyn AppHandler]] = &[office::SUITE, iwork::SUITE, terminals::SUITE];
+
+static HANDLERS: RwLock<Vec<(String, Arc<dyn AppHandler>)>> = RwLock::new(Vec::new());
+
+/// Uses `handler` for the apps whose bundle id matches `bundle_id_pattern`,
+/// either a bundle id or a prefix ending in `*` like `com.figma.*`, compared
+/// ignoring case.
+///
+/// Only one handler is tried per capture: the most recently registered one
+/// that matches (and whose `handles` agrees), then the built-in ones. So a
+/// registered handler overrides a built-in one for the same app, and if it
+/// returns `None` the capture goes on with the generic path, not with the
+/// handler it replaced.
+pub fn register_handler(bundle_id_pattern: &str, handler: impl AppHandler + 'static) {
+    HANDLERS
+        .write()
+        .unwrap()
+        .push((bundle_id_pattern.to_owned(), Arc::new(handler)));
+}
+
+/// Captures with the first handler for `bundle_id`, `None` when there's no
+/// handler or it fell through.
+pub(crate) fn capture_with_handler(
+    app_name: &str,
+    bundle_id: &str,
+) -> anyhow::Result<Option<SelectedText>> {
+    with_handler(bundle_id, |handler| {
+        breadcrumb::switch_strategy(handler.name());
+        let Some(mut selected_text) = handler.capture(app_name, bundle_id)? else {
+            diagnostics::debug!("{} handler fell through", handler.name());
+            return Ok(None);
+        };
+        selected_text
+            .strategy
+            .get_or_insert(CaptureStrategy::Handler(handler.name()));
+        Ok(Some(selected_text))
+    })
+    .unwrap_or(Ok(None))
+}
+
+pub(crate) fn handler_name(bundle_id: &str) -> Option<&'static str> {
+    with_handler(bundle_id, |handler| handler.name())
+}
+
+pub(crate) fn copy_method(bundle_id: &str) -> Option<CopyMethod> {
+    with_handler(bundle_id, |handler| handler.copy_method()).flatten()
+}
+
+pub(crate) fn chord_timing(bundle_id: &str) -> Option<ChordTiming> {
+    with_handler(bundle_id, |handler| handler.chord_timing()).flatten()
+}
+
+pub(crate) fn min_pasteboard_wait(bundle_id: &str) -> Option<Duration> {
+    with_handler(bundle_id, |handler| handler.min_pasteboard_wait()).flatten()
+}
+
+pub(crate) fn tidy_copied_text(bundle_id: &str, text: &str) -> Option<String> {
+    with_handler(bundle_id, |handler| handler.tidy_copied_text(text)).flatten()
+}
+
+fn with_handler<R>(bundle_id: &str, f: impl FnOnce(&dyn AppHandler) -> R) -> Option<R> {
+    // cloned out of the lock, a capture can take seconds and
+    // `register_handler` would wait on it
+    let registered: Vec<Arc<dyn AppHandler>> = HANDLERS
+        .read()
+        .unwrap()
+        .iter()
+        .rev()
+        .filter(|(pattern, _)| pattern_matches(pattern, bundle_id))
+        .map(|(_, handler)| Arc::clone(handler))
+        .collect();
+    let built_in = BUILT_IN_HANDLERS
+        .iter()
+        .chain(BUILT_IN_SUITES.iter().copied().flatten())
+        .copied();
+    let handler = registered
+        .iter()
+        .map(|handler| handler.as_ref())
+        .chain(built_in)
+        .find(|handler| handler.handles(bundle_id))?;
+    Some(f(handler))
+}
+
+/// Runs `script`, which prints one string with `quote_text`, and reads the
+/// string back. Failures are logged, the caller falls through.
+pub(crate) fn scripted_selection(script: &str, target: &str) -> Option<String> {
+    let script = format!("{}{}", script, applescript::QUOTE_HANDLER);
+    match applescript::run(&script, target) {
+        Ok(output) => Some(applescript::unquote(&String::from_utf8_lossy(&output))),
+        Err(e) => {
+            log_script_error(&e, target);
+            None
+        }
+    }
+}
+
+/// Logs a failed selection script, the caller falls through.
+pub(crate) fn log_script_error(e: &anyhow::Error, target: &str) {
+    // the copy fallback still works without consent
+    if let Some(SelectedTextError::AutomationDenied { .. }) = e.downcast_ref() {
+        diagnostics::warn!("{}", e);
+    } else {
+        diagnostics::debug!("selection script for {} failed: {:?}", target, e);
+    }
+}
+
+fn pattern_matches(pattern: &str, bundle_id: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => bundle_id
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+        None => pattern.eq_ignore_ascii_case(bundle_id),
+    }
+}
+
+pub(crate) fn is_any_of(bundle_id: &str, bundle_ids: &[&str]) -> bool {
+    bundle_ids
+        .iter()
+        .any(|id| id.eq_ignore_ascii_case(bundle_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fake;
+
+    impl AppHandler for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn capture(&self, app_name: &str, _: &str) -> Result<Option<SelectedText>> {
+            Ok(Some(SelectedText::from_text(
+                app_name.to_owned(),
+                "fake".to_owned(),
+            )))
+        }
+    }
+
+    #[test]
+    fn test_registered_handler_goes_first() {
+        let _turn = breadcrumb::TEST_TURN
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        register_handler("com.microsoft.VSCode", Fake);
+        let selected_text = capture_with_handler("Code", "com.microsoft.vscode")
+            .unwrap()
+            .unwrap();
+        assert_eq!(selected_text.text, ["fake"]);
+        assert_eq!(
+            selected_text.strategy,
+            Some(CaptureStrategy::Handler("fake"))
+        );
+        assert!(capture_with_handler("Notes", "com.apple.Notes")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_suite_handlers() {
+        let handler_for = |bundle_id| with_handler(bundle_id, |handler| handler.name());
+        assert_eq!(handler_for("com.microsoft.Word"), Some("word"));
+        assert_eq!(handler_for("com.microsoft.excel"), Some("excel"));
+        assert_eq!(handler_for("com.microsoft.Outlook"), Some("outlook"));
+        assert_eq!(handler_for("com.apple.iWork.Pages"), Some("pages"));
+        assert_eq!(handler_for("com.apple.iwork.numbers"), Some("numbers"));
+        assert_eq!(handler_for("com.apple.iWork.Keynote"), Some("keynote"));
+        assert_eq!(handler_for("net.kovidgoyal.kitty"), Some("kitty"));
+        assert_eq!(handler_for("org.alacritty"), Some("alacritty"));
+        assert_eq!(handler_for("com.apple.Terminal"), None);
+        assert_eq!(handler_for("com.apple.Notes"), None);
+    }
+
+    #[test]
+    fn test_outlook_falls_through_to_a_patient_copy() {
+        let _turn = breadcrumb::TEST_TURN
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let bundle_id = "com.microsoft.Outlook";
+        assert!(capture_with_handler("Outlook", bundle_id)
+            .unwrap()
+            .is_none());
+        assert_eq!(copy_method(bundle_id), None);
+        assert_eq!(chord_timing(bundle_id), Some(ChordTiming::CONSERVATIVE));
+        assert_eq!(
+            min_pasteboard_wait(bundle_id),
+            Some(Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("com.figma.*", "com.figma.Desktop"));
+        assert!(pattern_matches("md.obsidian", "md.Obsidian"));
+        assert!(!pattern_matches("md.obsidian", "md.obsidian.helper"));
+        assert!(!pattern_matches("com.figma.*", "com.fig"));
+        assert!(pattern_matches("*", "com.apple.Notes"));
+    }
+}
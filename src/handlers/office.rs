@@ -0,0 +1,111 @@
+// Office apps answer Cmd+C slowly, the pasteboard can take a second to
+// change, and Excel copies a bitmap of the cells alongside the text. Their
+// AppleScript dictionaries hand out the selection directly, so that's tried
+// first, and a copy after falling through is typed and waited for patiently.
+
+use std::time::Duration;
+
+use crate::{ChordTiming, Result, SelectedText};
+
+use super::{is_any_of, scripted_selection, AppHandler};
+
+/// The pasteboard wait Office apps get at the least after a copy.
+const PASTEBOARD_WAIT: Duration = Duration::from_millis(1500);
+
+pub(crate) struct OfficeApp {
+    name: &'static str,
+    bundle_id: &'static str,
+    /// Prints the selection with `quote_text`, `None` when the dictionary
+    /// has no text selection.
+    script: Option<&'static str>,
+}
+
+pub(crate) static SUITE: &[&dyn AppHandler] = &[&WORD, &EXCEL, &POWERPOINT, &OUTLOOK];
+
+static WORD: OfficeApp = OfficeApp {
+    name: "word",
+    bundle_id: "com.microsoft.Word",
+    script: Some(
+        r#"
+tell application id "com.microsoft.Word"
+	set selectedText to content of text object of selection
+end tell
+return my quote_text(selectedText)
+"#,
+    ),
+};
+
+// cells are joined like a copy would, tabs between and lines after
+static EXCEL: OfficeApp = OfficeApp {
+    name: "excel",
+    bundle_id: "com.microsoft.Excel",
+    script: Some(
+        r#"
+tell application id "com.microsoft.Excel"
+	set selectedValues to string value of selection
+end tell
+if class of selectedValues is not list then set selectedValues to {{selectedValues}}
+set rowTexts to {}
+repeat with selectedRow in selectedValues
+	set rowValues to contents of selectedRow
+	if class of rowValues is not list then set rowValues to {rowValues}
+	set AppleScript's text item delimiters to tab
+	set end of rowTexts to rowValues as text
+end repeat
+set AppleScript's text item delimiters to linefeed
+set tableText to rowTexts as text
+set AppleScript's text item delimiters to ""
+return my quote_text(tableText)
+"#,
+    ),
+};
+
+static POWERPOINT: OfficeApp = OfficeApp {
+    name: "powerpoint",
+    bundle_id: "com.microsoft.Powerpoint",
+    script: Some(
+        r#"
+tell application id "com.microsoft.Powerpoint"
+	set selectedText to content of text range of selection of active window
+end tell
+return my quote_text(selectedText)
+"#,
+    ),
+};
+
+// Outlook's `selection` is the selected messages, not text within one, so
+// only the patient copy applies
+static OUTLOOK: OfficeApp = OfficeApp {
+    name: "outlook",
+    bundle_id: "com.microsoft.Outlook",
+    script: None,
+};
+
+impl AppHandler for OfficeApp {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, &[self.bundle_id])
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let Some(script) = self.script else {
+            return Ok(None);
+        };
+        // an insertion point reads as empty, and so does a selected picture
+        // a copy would get
+        Ok(scripted_selection(script, app_name)
+            .filter(|text| !text.is_empty())
+            .map(|text| SelectedText::from_text(app_name.to_owned(), text)))
+    }
+
+    fn chord_timing(&self) -> Option<ChordTiming> {
+        Some(ChordTiming::CONSERVATIVE)
+    }
+
+    fn min_pasteboard_wait(&self) -> Option<Duration> {
+        Some(PASTEBOARD_WAIT)
+    }
+}
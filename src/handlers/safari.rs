@@ -0,0 +1,80 @@
+// Safari is where users most often get blank results: AXSelectedText is
+// empty for selections spanning several elements, and the copy fallback
+// loses the page. Text markers cover most pages, JavaScript the rest.
+
+use accessibility_ng::AXUIElement;
+
+use crate::{applescript, ax, Result, SelectedText};
+
+use super::{is_any_of, log_script_error, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &["com.apple.Safari", "com.apple.SafariTechnologyPreview"];
+
+/// The focused element is usually somewhere inside the page's web area.
+const WEB_AREA_DEPTH: usize = 12;
+
+pub(crate) struct Safari;
+
+impl AppHandler for Safari {
+    fn name(&self) -> &'static str {
+        "safari"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, app_name: &str, bundle_id: &str) -> Result<Option<SelectedText>> {
+        if let Some(web_area) = focused_web_area() {
+            let url = ax::url_of(&web_area);
+            if let Some(text) = ax::text_marker_selection(&web_area).filter(|t| !t.is_empty()) {
+                let mut selected_text = SelectedText::from_text(app_name.to_owned(), text);
+                selected_text.url = url;
+                return Ok(Some(selected_text));
+            }
+        }
+        match javascript_selection(bundle_id) {
+            Ok(Some((text, url))) => {
+                let mut selected_text = SelectedText::from_text(app_name.to_owned(), text);
+                selected_text.url = url;
+                Ok(Some(selected_text))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                log_script_error(&e, "Safari");
+                Ok(None)
+            }
+        }
+    }
+}
+
+pub(crate) fn focused_web_area() -> Option<AXUIElement> {
+    let focused = ax::focused_element().ok()?;
+    ax::self_or_ancestor(focused, WEB_AREA_DEPTH, |element| {
+        ax::role(element).as_deref() == Some("AXWebArea")
+    })
+}
+
+// needs Develop ▸ Allow JavaScript from Apple Events
+fn javascript_selection(bundle_id: &str) -> anyhow::Result<Option<(String, Option<String>)>> {
+    let script = format!(
+        r#"
+tell application id {}
+	set theTab to current tab of front window
+	set selectedText to do JavaScript "window.getSelection().toString()" in theTab
+	return my quote_text(selectedText) & linefeed & my quote_text(URL of theTab)
+end tell
+{}"#,
+        applescript::quote(bundle_id),
+        applescript::QUOTE_HANDLER
+    );
+    let output = applescript::run(&script, "Safari")?;
+    let output = String::from_utf8_lossy(&output);
+    let mut lines = output.trim().lines().map(applescript::unquote);
+    let text = lines.next().unwrap_or_default();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let url = lines.next().filter(|url| !url.is_empty());
+    Ok(Some((text, url)))
+}
@@ -0,0 +1,149 @@
+// GPU terminals draw their own text and expose little of it over AX. Kitty
+// hands out its selection over remote control and WezTerm copies on select,
+// so a copy chord is the last resort, and not a welcome one: a modifier
+// dropped on the way types a stray `c` into whatever the shell is running.
+// Copies go through the Edit menu instead, except in Alacritty, which has
+// none: it gets the chord, typed slowly.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use objc2_app_kit::NSWorkspace;
+
+use crate::{diagnostics, ChordTiming, CopyMethod, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+pub(crate) struct Terminal {
+    name: &'static str,
+    bundle_id: &'static str,
+    /// Reads the selection without copying, given the terminal's executable
+    /// and pid.
+    read_selection: Option<fn(&Path, i32) -> Option<String>>,
+    /// Whether the app has an Edit ▸ Copy menu item to copy with.
+    has_copy_menu: bool,
+}
+
+pub(crate) static SUITE: &[&dyn AppHandler] = &[&KITTY, &WEZTERM, &ALACRITTY, &WARP];
+
+static KITTY: Terminal = Terminal {
+    name: "kitty",
+    bundle_id: "net.kovidgoyal.kitty",
+    read_selection: Some(kitty_selection),
+    has_copy_menu: true,
+};
+
+// copies on select out of the box, see `set_auto_copy_app`, and `wezterm cli`
+// has no way to ask for the selection
+static WEZTERM: Terminal = Terminal {
+    name: "wezterm",
+    bundle_id: "com.github.wez.wezterm",
+    read_selection: None,
+    has_copy_menu: true,
+};
+
+static ALACRITTY: Terminal = Terminal {
+    name: "alacritty",
+    bundle_id: "org.alacritty",
+    read_selection: None,
+    has_copy_menu: false,
+};
+
+static WARP: Terminal = Terminal {
+    name: "warp",
+    bundle_id: "dev.warp.Warp-Stable",
+    read_selection: None,
+    has_copy_menu: true,
+};
+
+impl AppHandler for Terminal {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, &[self.bundle_id])
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let Some(read_selection) = self.read_selection else {
+            return Ok(None);
+        };
+        let Some((executable, pid)) = frontmost_executable() else {
+            return Ok(None);
+        };
+        Ok(read_selection(&executable, pid)
+            .map(|text| SelectedText::from_text(app_name.to_owned(), text)))
+    }
+
+    fn copy_method(&self) -> Option<CopyMethod> {
+        self.has_copy_menu.then_some(CopyMethod::MenuItem)
+    }
+
+    fn chord_timing(&self) -> Option<ChordTiming> {
+        (!self.has_copy_menu).then_some(ChordTiming::CONSERVATIVE)
+    }
+}
+
+fn frontmost_executable() -> Option<(PathBuf, i32)> {
+    let workspace = unsafe { NSWorkspace::sharedWorkspace() };
+    let app = unsafe { workspace.frontmostApplication() }?;
+    let path = unsafe { app.executableURL()?.path()? };
+    Some((PathBuf::from(path.to_string()), unsafe {
+        app.processIdentifier()
+    }))
+}
+
+// needs `allow_remote_control` and `listen_on` in kitty.conf, kitty appends
+// its pid to the socket name
+fn kitty_selection(executable: &Path, pid: i32) -> Option<String> {
+    let socket = std::env::var("KITTY_LISTEN_ON")
+        .ok()
+        .or_else(|| kitty_socket(pid))?;
+    let output = Command::new(executable)
+        .args(["@", "--to", &socket, "get-text", "--extent", "selection"])
+        .output()
+        .map_err(|e| diagnostics::debug!("kitty @ get-text failed to start: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        diagnostics::debug!(
+            "kitty @ get-text failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+    // kitty prints nothing when nothing is selected
+    let text = String::from_utf8_lossy(&output.stdout).into_owned();
+    (!text.is_empty()).then_some(text)
+}
+
+fn kitty_socket(pid: i32) -> Option<String> {
+    let suffix = format!("-{}", pid);
+    [PathBuf::from("/tmp"), std::env::temp_dir()]
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| is_kitty_socket_name(name, &suffix))
+        })
+        .map(|path| format!("unix:{}", path.display()))
+}
+
+fn is_kitty_socket_name(name: &str, pid_suffix: &str) -> bool {
+    name.starts_with("kitty") && name.ends_with(pid_suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_kitty_socket_name() {
+        assert!(is_kitty_socket_name("kitty-remote-4242", "-4242"));
+        assert!(!is_kitty_socket_name("kitty-remote-14242", "-4242"));
+    }
+}
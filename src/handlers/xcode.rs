@@ -0,0 +1,70 @@
+// Xcode's source editor is an AXTextArea deep inside the workspace window,
+// and the focused element is often a scroll area or gutter around it. Its
+// AXSelectedText can also lag behind edits, the selected range read back
+// through AXStringForRange is current.
+
+use accessibility_ng::{AXUIElement, AXUIElementAttributes};
+
+use crate::{ax, Result, SelectedText};
+
+use super::{is_any_of, AppHandler};
+
+const BUNDLE_IDS: &[&str] = &["com.apple.dt.Xcode"];
+
+const EDITOR_DEPTH: usize = 4;
+/// Elements looked at when searching the window for the editor.
+const EDITOR_SEARCH_BUDGET: usize = 600;
+
+pub(crate) struct Xcode;
+
+impl AppHandler for Xcode {
+    fn name(&self) -> &'static str {
+        "xcode"
+    }
+
+    fn handles(&self, bundle_id: &str) -> bool {
+        is_any_of(bundle_id, BUNDLE_IDS)
+    }
+
+    fn capture(&self, app_name: &str, _bundle_id: &str) -> Result<Option<SelectedText>> {
+        let app = ax::focused_app()?;
+        let Some(editor) = source_editor(&app) else {
+            return Ok(None);
+        };
+        let Some(text) = editor_selection(&editor) else {
+            return Ok(None);
+        };
+        let mut selected_text = SelectedText::from_text(app_name.to_owned(), text);
+        selected_text.document_path = app
+            .focused_window()
+            .ok()
+            .and_then(|window| ax::document_path(&window));
+        Ok(Some(selected_text))
+    }
+}
+
+fn is_source_editor(element: &AXUIElement) -> bool {
+    ax::role(element).as_deref() == Some("AXTextArea")
+        && element
+            .description()
+            .is_ok_and(|description| description == "Source Editor")
+}
+
+// the window search only runs when focus sits outside the editor, e.g. on
+// the navigator after opening a file
+fn source_editor(app: &AXUIElement) -> Option<AXUIElement> {
+    let focused = app.focused_uielement().ok()?;
+    if let Some(editor) = ax::self_or_ancestor(focused, EDITOR_DEPTH, is_source_editor) {
+        return Some(editor);
+    }
+    let window = app.focused_window().ok()?;
+    ax::find_descendant(&window, EDITOR_SEARCH_BUDGET, is_source_editor)
+}
+
+fn editor_selection(editor: &AXUIElement) -> Option<String> {
+    let range = ax::selected_range(editor).ok()?;
+    if range.length == 0 {
+        return Some(String::new());
+    }
+    ax::string_for_range(editor, range).or_else(|| Some(editor.selected_text().ok()?.to_string()))
+}
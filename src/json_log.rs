@@ -0,0 +1,98 @@
+// Log pipelines ingest structured records, not the free-form lines `log`
+// gets. Every breadcrumb a capture leaves, and how it failed, can be
+// written as one JSON object per line to a sink the caller provides.
+
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::breadcrumb::CaptureBreadcrumb;
+use crate::diagnostics;
+
+/// One line of the JSON log.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CaptureEvent<'a> {
+    /// `stage` when the capture reached `stage`, `failed` when it ended
+    /// without a selection.
+    pub event: &'static str,
+    pub strategy: &'a str,
+    pub app: &'a str,
+    /// Named as in the breadcrumb file, e.g. `waiting-for-pasteboard`.
+    pub stage: &'static str,
+    /// Milliseconds since the capture started.
+    pub duration_ms: u64,
+    /// `SelectedTextError::code` of the failure, `other` for other errors.
+    pub error: Option<&'static str>,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    pub pid: u32,
+}
+
+static SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+/// Writes capture events to `sink` as JSON lines. `None`, the default, stops.
+/// A sink that fails to write is dropped, with a warning.
+pub fn set_json_log(sink: Option<Box<dyn Write + Send>>) {
+    *SINK.lock().unwrap() = sink;
+}
+
+pub(crate) fn emit(
+    breadcrumb: &CaptureBreadcrumb,
+    event: &'static str,
+    duration: Duration,
+    error: Option<&'static str>,
+) {
+    let mut sink = SINK.lock().unwrap();
+    let Some(writer) = sink.as_mut() else {
+        return;
+    };
+    let event = CaptureEvent {
+        event,
+        strategy: &breadcrumb.strategy,
+        app: &breadcrumb.app,
+        stage: breadcrumb.stage.name(),
+        duration_ms: duration.as_millis().try_into().unwrap_or(u64::MAX),
+        error,
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        pid: breadcrumb.pid,
+    };
+    if let Err(e) = write_line(writer, &event) {
+        *sink = None;
+        diagnostics::warn!("JSON log sink failed, dropping it: {}", e);
+    }
+}
+
+fn write_line(writer: &mut dyn Write, event: &CaptureEvent) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, event)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_line() {
+        let event = CaptureEvent {
+            event: "failed",
+            strategy: "key-chord",
+            app: "Slack",
+            stage: "waiting-for-pasteboard",
+            duration_ms: 812,
+            error: Some("timeout"),
+            at: 1_700_000_000,
+            pid: 42,
+        };
+        let mut line = Vec::new();
+        write_line(&mut line, &event).unwrap();
+        assert_eq!(
+            String::from_utf8(line).unwrap(),
+            "{\"event\":\"failed\",\"strategy\":\"key-chord\",\"app\":\"Slack\",\
+             \"stage\":\"waiting-for-pasteboard\",\"duration_ms\":812,\"error\":\"timeout\",\
+             \"at\":1700000000,\"pid\":42}\n"
+        );
+    }
+}
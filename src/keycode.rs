@@ -0,0 +1,300 @@
+//! Virtual key codes for synthesizing shortcuts.
+//!
+//! Key codes name physical keys (ANSI positions), so on Dvorak, AZERTY and
+//! friends the key labeled "C" has a different code. [`key_code_for_char`]
+//! resolves a character through the current keyboard layout instead of
+//! assuming QWERTY, [`ANSI_KEYS`] is the QWERTY table for when it can't.
+
+use std::ffi::c_void;
+use std::sync::{mpsc, Mutex, Once};
+use std::time::Duration;
+
+use core_foundation::{
+    base::{CFRelease, CFTypeRef, TCFType},
+    data::{CFData, CFDataRef},
+    dictionary::CFDictionaryRef,
+    string::CFStringRef,
+};
+use core_foundation_sys::notification_center::{
+    CFNotificationCenterAddObserver, CFNotificationCenterGetDistributedCenter,
+    CFNotificationCenterRef, CFNotificationName,
+    CFNotificationSuspensionBehaviorDeliverImmediately,
+};
+pub use core_graphics::event::{CGKeyCode, KeyCode};
+
+use crate::{confined, diagnostics};
+
+type TISInputSourceRef = *const c_void;
+
+extern "C" {
+    fn pthread_main_np() -> i32;
+}
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    static kTISPropertyUnicodeKeyLayoutData: CFStringRef;
+    static kTISNotifySelectedKeyboardInputSourceChanged: CFStringRef;
+    fn TISCopyCurrentKeyboardLayoutInputSource() -> TISInputSourceRef;
+    fn TISCopyCurrentASCIICapableKeyboardLayoutInputSource() -> TISInputSourceRef;
+    fn TISGetInputSourceProperty(source: TISInputSourceRef, key: CFStringRef) -> CFTypeRef;
+    fn LMGetKbdType() -> u8;
+    fn UCKeyTranslate(
+        key_layout: *const c_void,
+        virtual_key_code: u16,
+        key_action: u16,
+        modifier_key_state: u32,
+        keyboard_type: u32,
+        key_translate_options: u32,
+        dead_key_state: *mut u32,
+        max_string_length: usize,
+        actual_string_length: *mut usize,
+        unicode_string: *mut u16,
+    ) -> i32;
+}
+
+const KUC_KEY_ACTION_DISPLAY: u16 = 3;
+const KUC_KEY_TRANSLATE_NO_DEAD_KEYS_MASK: u32 = 1;
+// `cmdKey >> 8`, so layouts like "Dvorak - QWERTY ⌘" report their shortcut key
+const COMMAND_MODIFIER_STATE: u32 = 1;
+// the highest virtual key code on Apple keyboards is 0x7E
+const MAX_KEY_CODE: CGKeyCode = 0x7F;
+
+/// `C` on ANSI keyboards, used when the layout can't be read.
+const ANSI_C: CGKeyCode = 8;
+
+// how long a lookup off the main thread waits for the main queue to read the
+// layout
+const MAIN_QUEUE_WAIT: Duration = Duration::from_millis(100);
+
+/// The characters each key types on an ANSI keyboard with a US layout. Named
+/// keys (return, arrows, modifiers, ...) are constants on [`KeyCode`].
+pub const ANSI_KEYS: &[(char, CGKeyCode)] = &[
+    ('a', 0x00),
+    ('s', 0x01),
+    ('d', 0x02),
+    ('f', 0x03),
+    ('h', 0x04),
+    ('g', 0x05),
+    ('z', 0x06),
+    ('x', 0x07),
+    ('c', 0x08),
+    ('v', 0x09),
+    ('b', 0x0B),
+    ('q', 0x0C),
+    ('w', 0x0D),
+    ('e', 0x0E),
+    ('r', 0x0F),
+    ('y', 0x10),
+    ('t', 0x11),
+    ('1', 0x12),
+    ('2', 0x13),
+    ('3', 0x14),
+    ('4', 0x15),
+    ('6', 0x16),
+    ('5', 0x17),
+    ('=', 0x18),
+    ('9', 0x19),
+    ('7', 0x1A),
+    ('-', 0x1B),
+    ('8', 0x1C),
+    ('0', 0x1D),
+    (']', 0x1E),
+    ('o', 0x1F),
+    ('u', 0x20),
+    ('[', 0x21),
+    ('i', 0x22),
+    ('p', 0x23),
+    ('l', 0x25),
+    ('j', 0x26),
+    ('\'', 0x27),
+    ('k', 0x28),
+    (';', 0x29),
+    ('\\', 0x2A),
+    (',', 0x2B),
+    ('/', 0x2C),
+    ('n', 0x2D),
+    ('m', 0x2E),
+    ('.', 0x2F),
+    (' ', 0x31),
+    ('`', 0x32),
+];
+
+/// Key code of `c` on an ANSI keyboard with a US layout.
+pub fn ansi_key_code(c: char) -> Option<CGKeyCode> {
+    let c = c.to_lowercase().next()?;
+    ANSI_KEYS
+        .iter()
+        .find(|(key, _)| *key == c)
+        .map(|&(_, key_code)| key_code)
+}
+
+/// Key code of `c` in the current layout, falling back to its ANSI position
+/// when the layout can't be read.
+pub fn key_code_or_ansi(c: char) -> Option<CGKeyCode> {
+    key_code_for_char(c).or_else(|| ansi_key_code(c))
+}
+
+static RESOLVED: Mutex<Vec<(char, CGKeyCode)>> = Mutex::new(Vec::new());
+static COPY_KEY_OVERRIDE: Mutex<Option<CGKeyCode>> = Mutex::new(None);
+static WATCH_INPUT_SOURCE: Once = Once::new();
+
+/// Forces the key code sent for Cmd+C, for layouts where the lookup picks the
+/// wrong key. `None` goes back to resolving it from the current layout.
+pub fn set_copy_key_code(key_code: Option<CGKeyCode>) {
+    *COPY_KEY_OVERRIDE.lock().unwrap() = key_code;
+}
+
+/// Forgets the resolved key codes so the next copy reads the layout again.
+/// Happens automatically when the user switches input sources, as long as
+/// the main thread runs its run loop: that's where the change is announced.
+pub fn refresh_keyboard_layout() {
+    RESOLVED.lock().unwrap().clear();
+}
+
+pub(crate) fn copy_key_code() -> CGKeyCode {
+    if let Some(key_code) = *COPY_KEY_OVERRIDE.lock().unwrap() {
+        return key_code;
+    }
+    key_code_for_char('c').unwrap_or(ANSI_C)
+}
+
+/// Key code that types `c` with Command held in the current layout, so
+/// `key_code_for_char('v')` is the key for Cmd+V. The layout can only be read
+/// on the main thread, other threads have the main queue read it. That needs
+/// the main thread to run its run loop, when it doesn't answer within 100 ms
+/// this returns `None`.
+pub fn key_code_for_char(c: char) -> Option<CGKeyCode> {
+    let c = c.to_lowercase().next()?;
+    let cached = |resolved: &[(char, CGKeyCode)]| {
+        resolved
+            .iter()
+            .find(|(resolved, _)| *resolved == c)
+            .map(|&(_, key_code)| key_code)
+    };
+    if let Some(key_code) = cached(&RESOLVED.lock().unwrap()) {
+        return Some(key_code);
+    }
+    // not under the lock, the main queue may be resolving the same key
+    let key_code = resolve(c)?;
+    let mut resolved = RESOLVED.lock().unwrap();
+    if cached(&resolved).is_none() {
+        resolved.push((c, key_code));
+    }
+    Some(key_code)
+}
+
+fn resolve(c: char) -> Option<CGKeyCode> {
+    // Text Input Sources asserts it's called on the main thread since macOS 14
+    if unsafe { pthread_main_np() } == 0 {
+        return resolve_on_main(c);
+    }
+    WATCH_INPUT_SOURCE.call_once(watch_input_source);
+    // input methods (Japanese, Chinese, ...) have no layout data of their own
+    unsafe {
+        resolve_in(TISCopyCurrentKeyboardLayoutInputSource(), c)
+            .or_else(|| resolve_in(TISCopyCurrentASCIICapableKeyboardLayoutInputSource(), c))
+    }
+}
+
+// a main thread that's blocked, e.g. joining the capturing thread, never
+// gets to the work, the answer it gives later is still cached
+fn resolve_on_main(c: char) -> Option<CGKeyCode> {
+    let (sender, receiver) = mpsc::channel();
+    confined::hop_to_main(Box::new(move || {
+        let _ = sender.send(key_code_for_char(c));
+    }));
+    let key_code = receiver.recv_timeout(MAIN_QUEUE_WAIT).ok().flatten();
+    if key_code.is_none() {
+        diagnostics::debug!(
+            "the main queue didn't look up {:?} in the keyboard layout",
+            c
+        );
+    }
+    key_code
+}
+
+// the notification is delivered on the main run loop, where it's safe to read
+// the new layout right away
+extern "C" fn input_source_changed(
+    _center: CFNotificationCenterRef,
+    _observer: *mut c_void,
+    _name: CFNotificationName,
+    _object: *const c_void,
+    _user_info: CFDictionaryRef,
+) {
+    refresh_keyboard_layout();
+    let key_code = key_code_for_char('c');
+    diagnostics::debug!("input source changed, Cmd+C is key code {:?}", key_code);
+}
+
+fn watch_input_source() {
+    unsafe {
+        CFNotificationCenterAddObserver(
+            CFNotificationCenterGetDistributedCenter(),
+            std::ptr::null(),
+            input_source_changed,
+            kTISNotifySelectedKeyboardInputSourceChanged,
+            std::ptr::null(),
+            CFNotificationSuspensionBehaviorDeliverImmediately,
+        );
+    }
+}
+
+unsafe fn resolve_in(source: TISInputSourceRef, c: char) -> Option<CGKeyCode> {
+    if source.is_null() {
+        return None;
+    }
+    let layout_data = TISGetInputSourceProperty(source, kTISPropertyUnicodeKeyLayoutData);
+    let key_code = if layout_data.is_null() {
+        None
+    } else {
+        let layout_data = CFData::wrap_under_get_rule(layout_data as CFDataRef);
+        let layout = layout_data.bytes().as_ptr() as *const c_void;
+        (0..MAX_KEY_CODE).find(|&key_code| translate(layout, key_code) == Some(c))
+    };
+    CFRelease(source as CFTypeRef);
+    key_code
+}
+
+unsafe fn translate(layout: *const c_void, key_code: CGKeyCode) -> Option<char> {
+    let mut dead_key_state = 0;
+    let mut length = 0;
+    let mut chars = [0u16; 4];
+    let status = UCKeyTranslate(
+        layout,
+        key_code,
+        KUC_KEY_ACTION_DISPLAY,
+        COMMAND_MODIFIER_STATE,
+        u32::from(LMGetKbdType()),
+        KUC_KEY_TRANSLATE_NO_DEAD_KEYS_MASK,
+        &mut dead_key_state,
+        chars.len(),
+        &mut length,
+        chars.as_mut_ptr(),
+    );
+    if status != 0 || length != 1 {
+        return None;
+    }
+    char::from_u32(u32::from(chars[0])).and_then(|c| c.to_lowercase().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi_keys() {
+        assert_eq!(ansi_key_code('C'), Some(ANSI_C));
+        assert_eq!(ansi_key_code('v'), Some(0x09));
+        assert_eq!(ansi_key_code('é'), None);
+        for (i, (c, key_code)) in ANSI_KEYS.iter().enumerate() {
+            assert!(
+                ANSI_KEYS[i + 1..]
+                    .iter()
+                    .all(|(other, other_code)| other != c && other_code != key_code),
+                "{:?} is listed twice",
+                c
+            );
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use objc2_foundation::NSString;
+use objc2_natural_language::NLLanguageRecognizer;
+
+// NLLanguageUndetermined
+const UNDETERMINED: &str = "und";
+
+/// Returns the BCP-47 tag (e.g. `en`, `zh-Hans`) of the dominant language of
+/// `text` according to `NLLanguageRecognizer`.
+pub fn detect_language(text: &str) -> Option<String> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    let language =
+        unsafe { NLLanguageRecognizer::dominantLanguageForString(&NSString::from_str(text)) }?
+            .to_string();
+    (language != UNDETERMINED).then_some(language)
+}
@@ -1,387 +1,683 @@
-use std::sync::Arc;
-
-use accessibility_ng::{AXAttribute, AXUIElement};
-use accessibility_sys_ng::{kAXFocusedUIElementAttribute, kAXSelectedTextAttribute};
-use active_win_pos_rs::get_active_window;
-use core_foundation::string::CFString;
-use core_graphics::{
-    event::{CGEvent, CGEventTapLocation, CGKeyCode},
-    event_source::{CGEventSource, CGEventSourceStateID},
+use std::path::{Path, PathBuf};
+
+use diagnostics::error;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+pub use macos::{
+    active_app, copy_and_save_pasteboard, ctrl_c_and_save_pasteboard, get_selected_files_of,
+    get_selected_text_from_pasteboard, get_selected_text_using_ax_then_copy,
+    get_selected_text_using_ax_then_copy_with, selected_finder_item_count, set_copy_method,
+    sim_chord, sim_ctrl_c, simulate, GetSelectedTextResult, PasteBoardContainer,
+    PasteboardSavedState,
+};
+#[cfg(target_os = "macos")]
+pub(crate) use macos::{
+    app_copy_method, capture_with_options, copied_image, copy_chord_timing, delivered,
+    focused_in_this_process, frontmost_bundle_id, recover_pasteboard_text, restore_pasteboard,
+    strategies_for, SETTLE_TIME,
+};
+#[cfg(target_os = "macos")]
+#[allow(deprecated)]
+pub use macos::{get_selected_files, get_window_meta, in_finder_or_empty_window};
+
+#[cfg(target_os = "macos")]
+mod anchor;
+#[cfg(target_os = "macos")]
+pub use anchor::{get_selection_anchor, SelectionAnchor, SelectionDirection};
+
+#[cfg(target_os = "macos")]
+mod applescript;
+
+#[cfg(target_os = "macos")]
+mod attribution;
+#[cfg(target_os = "macos")]
+use attribution::Attribution;
+
+#[cfg(target_os = "macos")]
+mod autocopy;
+#[cfg(target_os = "macos")]
+pub use autocopy::set_auto_copy_app;
+
+#[cfg(target_os = "macos")]
+mod ax;
+#[cfg(target_os = "macos")]
+pub use ax::{get_selected_range, get_selected_styled_runs_by_ax, get_selection_bounds};
+
+mod bounds;
+#[cfg(target_os = "macos")]
+pub use bounds::get_caret_position;
+pub use bounds::{CaretPosition, ScreenRect, SelectionBounds};
+
+#[cfg(target_os = "macos")]
+mod breadcrumb;
+#[cfg(target_os = "macos")]
+use breadcrumb::CaptureStage;
+#[cfg(target_os = "macos")]
+pub use breadcrumb::{last_capture_breadcrumb, set_breadcrumb_file, CaptureBreadcrumb};
+
+#[cfg(target_os = "macos")]
+mod capture_lock;
+
+#[cfg(target_os = "macos")]
+mod chord;
+#[cfg(target_os = "macos")]
+pub use chord::{
+    chord_timing_for, copy_chord_for, set_chord_timing, set_copy_chord, set_default_chord_timing,
+    ChordTiming, CopyChord, Modifier,
+};
+
+#[cfg(target_os = "macos")]
+mod chunks;
+#[cfg(target_os = "macos")]
+pub use chunks::{get_selected_text_chunks_by_ax, TextChunks};
+
+mod classify;
+pub use classify::{classify, guess_code_language, CodeLanguage, TextKind};
+
+#[cfg(target_os = "macos")]
+mod confined;
+#[cfg(target_os = "macos")]
+pub use confined::ThreadConfined;
+
+#[cfg(target_os = "macos")]
+mod context;
+#[cfg(target_os = "macos")]
+pub use context::{
+    get_focused_element_text, get_selected_text_with_context, FocusedElementText,
+    SelectedTextWithContext,
 };
-use log::error;
-use objc2::rc::Retained;
-use objc2_app_kit::{NSPasteboard, NSPasteboardItem, NSPasteboardTypeString};
 
-use anyhow::{anyhow, bail};
-use objc2_foundation::NSArray;
+mod decode;
+pub use decode::decode_lossy;
+
+mod dedup;
+pub use dedup::SelectionDedup;
+
+mod diagnostics;
+pub use diagnostics::{set_diagnostics_sink, DiagnosticsSink, Level, Silent};
+
+#[cfg(target_os = "macos")]
+mod delivery;
+
+#[cfg(target_os = "macos")]
+mod drag;
+#[cfg(target_os = "macos")]
+pub use drag::current_drag_items;
+
+mod error;
+pub use error::{set_strict, Result, SelectedTextError, AUTOMATION_SETTINGS_PANE};
+
+mod files;
+pub use files::{file_metadata, FileMetadata};
+
+#[cfg(target_os = "macos")]
+mod find;
+#[cfg(target_os = "macos")]
+pub use find::{find_pasteboard_text, set_find_pasteboard_text, set_publish_to_find_pasteboard};
+
+#[cfg(target_os = "macos")]
+mod handlers;
+#[cfg(target_os = "macos")]
+pub use handlers::{register_handler, AppHandler};
+
+#[cfg(all(target_os = "macos", feature = "json-log"))]
+mod json_log;
+#[cfg(all(target_os = "macos", feature = "json-log"))]
+pub use json_log::{set_json_log, CaptureEvent};
+
+#[cfg(target_os = "macos")]
+mod options;
+#[cfg(target_os = "macos")]
+pub use options::{get_selected_text_with, SelectedTextOptions, Strategy};
+
+mod patterns;
+#[cfg(all(target_os = "macos", feature = "pasteboard-patterns"))]
+pub use patterns::detect_pasteboard_patterns;
+pub use patterns::PasteboardPattern;
+
+#[cfg(target_os = "macos")]
+mod peek;
+#[cfg(target_os = "macos")]
+pub use peek::{peek, CapturePeek};
+
+#[cfg(target_os = "macos")]
+mod policy;
+#[cfg(target_os = "macos")]
+pub use policy::{set_allowed_apps, set_app_denied};
+
+#[cfg(target_os = "macos")]
+mod posting;
+#[cfg(target_os = "macos")]
+use posting::post_key;
+#[cfg(target_os = "macos")]
+pub use posting::{event_posting, set_event_posting, EventPosting, PostTap, SourceState};
+
+mod provider;
+#[cfg(target_os = "macos")]
+pub use provider::MacOsProvider;
+pub use provider::{default_provider, get_selected_text, SelectedTextProvider};
+
+mod redact;
+pub use redact::{add_default_redactions, add_redaction, clear_redactions, redact, Redacted};
+
+#[cfg(target_os = "macos")]
+mod screenshot;
+#[cfg(target_os = "macos")]
+pub use screenshot::capture_selection_image;
+
+mod script;
+pub use script::{dominant_script, script_of, text_direction, Script, TextDirection};
+
+#[cfg(target_os = "macos")]
+mod snapshot;
+#[cfg(target_os = "macos")]
+pub use snapshot::{set_clipboard_size_limit, PasteboardGuard, PasteboardSnapshot};
+
+#[cfg(target_os = "macos")]
+mod stats;
+#[cfg(target_os = "macos")]
+pub use stats::{reset_stats, set_stats_enabled, stats, CaptureStats};
+
+#[cfg(target_os = "macos")]
+mod strategy_cache;
+#[cfg(target_os = "macos")]
+pub use strategy_cache::set_strategy_cache_file;
+#[cfg(target_os = "macos")]
+use strategy_cache::Learned;
+
+#[cfg(target_os = "macos")]
+mod strategy_config;
+#[cfg(target_os = "macos")]
+pub use strategy_config::{set_strategy_config, StrategyConfig};
+
+mod truncate;
+pub use truncate::{floor_grapheme_boundary, truncate_bytes, truncate_graphemes, truncate_utf16};
+
+#[cfg(target_os = "macos")]
+mod target;
+#[cfg(target_os = "macos")]
+pub use target::PasteboardTarget;
+
+mod token;
+pub use token::ClipboardToken;
+#[cfg(target_os = "macos")]
+pub use token::{restore_if_unchanged, write_if_unchanged};
+
+mod tokens;
+pub use tokens::{approx_tokens, set_tokenizer, HeuristicTokenizer, Tokenizer};
+
+#[cfg(target_os = "macos")]
+mod visible;
+#[cfg(target_os = "macos")]
+pub use visible::{get_selection_visibility, SelectionVisibility};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::LinuxProvider;
+#[cfg(target_os = "linux")]
+mod wayland;
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+pub use self::windows::WindowsProvider;
+
+#[cfg(target_os = "macos")]
+mod watch;
+#[cfg(target_os = "macos")]
+pub use watch::{watch_selection, watch_selection_with, SelectionWatch};
+
+#[cfg(target_os = "macos")]
+mod window;
+#[cfg(target_os = "macos")]
+pub use window::{active_window, get_selections_in_app_windows, WindowSelection};
+
+#[cfg(target_os = "macos")]
+mod markers;
+#[cfg(target_os = "macos")]
+pub use markers::set_pasteboard_markers;
+
+mod normalize;
+pub use normalize::{normalize, set_default_normalize, NormalizeOptions};
+
+#[cfg(target_os = "macos")]
+pub mod accessibility;
+
+#[cfg(target_os = "macos")]
+pub mod keycode;
+#[cfg(target_os = "macos")]
+pub use keycode::{refresh_keyboard_layout, set_copy_key_code};
+
+mod markdown;
+pub use markdown::html_to_markdown;
+
+#[cfg(all(target_os = "macos", feature = "language"))]
+mod language;
+#[cfg(all(target_os = "macos", feature = "language"))]
+pub use language::detect_language;
+
+#[cfg(all(target_os = "macos", feature = "ocr"))]
+mod ocr;
+#[cfg(all(target_os = "macos", feature = "ocr"))]
+pub use ocr::recognize_text;
+
+#[cfg(all(target_os = "macos", feature = "frontmost-watch"))]
+mod frontmost;
+#[cfg(all(target_os = "macos", feature = "frontmost-watch"))]
+pub use frontmost::{watch_frontmost_app, FrontmostApp, FrontmostAppWatch};
+
+#[cfg(all(target_os = "macos", feature = "file-promises"))]
+mod promises;
+#[cfg(all(target_os = "macos", feature = "file-promises"))]
+pub use promises::set_file_promise_limit;
+
+#[cfg(feature = "rtf")]
+mod rtf;
+#[cfg(feature = "rtf")]
+pub use rtf::parse_rtf;
+
+#[cfg(all(target_os = "macos", feature = "app-config"))]
+mod config;
+#[cfg(all(target_os = "macos", feature = "app-config"))]
+pub use config::load_app_config;
+
+#[cfg(all(target_os = "macos", feature = "data-detectors"))]
+mod entities;
+#[cfg(all(target_os = "macos", feature = "data-detectors"))]
+pub use entities::{detect_entities, DetectedEntity, Entity};
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SelectedText {
     pub is_file_paths: bool,
     pub app_name: String,
     pub text: Vec<String>,
-}
-pub struct PasteboardSavedState {
-    pub saved_change_count: isize,
-    pub saved_contents: Option<objc2::rc::Retained<NSArray<NSPasteboardItem>>>,
+    /// What the text looks like, `None` for file selections and empty text.
+    pub kind: Option<TextKind>,
+    /// Likely language when `kind` is `Code`, for picking syntax highlighting.
+    pub code_language: Option<CodeLanguage>,
+    /// Estimated token count of the text, see `set_tokenizer`.
+    pub approx_tokens: Option<usize>,
+    /// BCP-47 tag of the dominant language, only detected with the `language` feature.
+    pub language: Option<String>,
+    /// Dominant writing direction, `None` when the text has no letters.
+    pub direction: Option<TextDirection>,
+    /// Unicode script most of the letters are written in.
+    pub script: Option<Script>,
+    /// The HTML flavor of a clipboard capture, when the app provided one.
+    pub html: Option<String>,
+    /// `html` converted to Markdown.
+    pub markdown: Option<String>,
+    /// Formatting of the selection, read from the RTF flavor with the `rtf` feature.
+    pub styled_runs: Option<Vec<StyledRun>>,
+    /// Metadata of the selected files, filled in by `with_file_metadata`.
+    pub files: Option<Vec<FileMetadata>>,
+    /// Registered redaction patterns that matched, see `add_redaction`.
+    pub redactions: Vec<Redacted>,
+    /// The text was recovered from raw pasteboard data with a guessed
+    /// encoding, or some selected paths aren't UTF-8, see `lossy_paths`.
+    pub lossy: bool,
+    /// Selected paths whose names aren't valid UTF-8, exactly as the file
+    /// system has them. `text` holds them with replacement characters.
+    #[serde(serialize_with = "files::serialize_paths_lossy")]
+    pub lossy_paths: Vec<PathBuf>,
+    /// Image data of an image selection, PNG or TIFF. Not serialized.
+    #[serde(skip)]
+    pub image: Option<Vec<u8>>,
+    /// How the selection was read, `None` for a value built by hand or on
+    /// other platforms.
+    pub strategy: Option<CaptureStrategy>,
+    /// Where the selection is on screen, when the focused element reports
+    /// it, for placing a popup next to it.
+    pub bounds: Option<SelectionBounds>,
+    /// The capture copied to the pasteboard, only the fallback after AX
+    /// fails does.
+    pub clipboard_touched: bool,
+    /// Whether the user's pasteboard was put back afterwards, `None` when it
+    /// wasn't touched.
+    pub clipboard_restored: Option<bool>,
+    /// The user's pasteboard held more than `set_clipboard_size_limit`
+    /// allows, or restoring was turned off in `SelectedTextOptions`, so it
+    /// wasn't snapshotted and now holds the copy.
+    pub clipboard_restore_skipped: bool,
+    /// Address of the page the selection is on, read by browser handlers.
+    pub url: Option<String>,
+    /// Whether a selection in Slack or Discord is in the message being
+    /// written or in the history.
+    pub chat_pane: Option<ChatPane>,
+    /// File open in the editor the selection is in, read by editor handlers.
+    #[serde(serialize_with = "files::serialize_optional_path_lossy")]
+    pub document_path: Option<PathBuf>,
+    /// What the system detected in a clipboard capture, with the
+    /// `pasteboard-patterns` feature on macOS 13 and later.
+    pub pasteboard_patterns: Option<Vec<PasteboardPattern>>,
+    /// The general pasteboard's state when the capture finished, for
+    /// `write_if_unchanged` and `restore_if_unchanged`.
+    pub clipboard_token: Option<ClipboardToken>,
+    /// Increases with every result in this process, a consumer handling
+    /// results out of order can drop one older than what it already has.
+    pub sequence: u64,
+    /// Milliseconds since the Unix epoch when the result was read.
+    pub captured_at: u64,
 }
 
-pub enum GetSelectedTextResult {
-    Text(SelectedText),
-    PasteboardState(PasteboardSavedState),
-}
+static RESULT_SEQUENCE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
 
-#[derive(Clone)]
-pub struct PasteBoardContainer {
-    pub inner: Arc<objc2::rc::Retained<NSPasteboard>>,
-    pub pasteboard: Option<Retained<NSArray<NSPasteboardItem>>>,
-}
-unsafe impl Send for PasteBoardContainer {}
-unsafe impl Sync for PasteBoardContainer {}
-
-const CMD_KEY: CGKeyCode = core_graphics::event::KeyCode::COMMAND;
-const KEY_C: CGKeyCode = 8;
-
-pub fn simulate(key: CGKeyCode, key_down: bool) -> anyhow::Result<()> {
-    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
-        .map_err(|_| anyhow!("Failed to create CGEventSource"))?;
-    if let Some(cg_event) = CGEvent::new_keyboard_event(source, key, key_down).ok() {
-        cg_event.post(CGEventTapLocation::HID);
-        // Let ths MacOS catchup
-        std::thread::sleep(std::time::Duration::from_millis(20));
-        Ok(())
-    } else {
-        bail!("Failed to simulate key press event for spotlight selected text copy")
-    }
+// the wall clock can step back, the sequence can't
+fn next_sequence_and_time() -> (u64, u64) {
+    let sequence = RESULT_SEQUENCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+    let captured_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64);
+    (sequence, captured_at)
 }
 
-// KeyPress(Key),
-// KeyRelease(Key),
-// reference - https://github.com/Narsil/rdev/blob/main/src/macos/keycodes.rs
-pub fn sim_ctrl_c() -> anyhow::Result<()> {
-    // keydown
-    println!("keydown cmd");
-    simulate(CMD_KEY, true)?;
-    // keydown
-    println!("keydown c");
-    simulate(KEY_C, true)?;
-    // keyup
-    println!("key up c");
-    simulate(KEY_C, false)?;
-    // keyup
-    println!("key up cmd");
-    simulate(CMD_KEY, false)?;
-    Ok(())
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StyledRun {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub link: Option<String>,
+    /// PostScript name of the font, e.g. `Helvetica-Bold`.
+    pub font: Option<String>,
+    /// Font size in points.
+    pub font_size: Option<f64>,
 }
 
-const QUIET_CMD_C: &str = r#"
-tell application "System Events"
-    set savedAlertVolume to alert volume of (get volume settings)
-    set volume alert volume 0
-    keystroke "c" using {command down}
-    set volume alert volume savedAlertVolume
-end tell
-"#;
-
-fn quiet_cmd_c() -> anyhow::Result<()> {
-    // debug_println!("get_selected_text_by_clipboard_using_applescript");
-    let output = std::process::Command::new("osascript")
-        .arg("-e")
-        .arg(QUIET_CMD_C)
-        .output()?;
-    // .spawn()?;
-
-    if !output.status.success() {
-        bail!(output
-            .stderr
-            .into_iter()
-            .map(|c| c as char)
-            .collect::<String>());
-    }
-    Ok(())
+/// Which part of a chat app the selection came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ChatPane {
+    /// The box a message is written in.
+    Composer,
+    /// Messages already sent.
+    MessageHistory,
 }
 
-pub fn ctrl_c_and_save_pasteboard(
-    pasteboard: &objc2::rc::Retained<NSPasteboard>,
-    use_applescript: bool,
-) -> anyhow::Result<PasteboardSavedState> {
-    let saved_change_count = unsafe { pasteboard.changeCount() };
-    let saved_contents = unsafe { pasteboard.pasteboardItems() };
-
-    if use_applescript {
-        quiet_cmd_c()?;
-    } else {
-        sim_ctrl_c()?;
+impl SelectedText {
+    pub fn from_text(app_name: String, text: String) -> Self {
+        #[cfg(all(target_os = "macos", feature = "language"))]
+        let language = detect_language(&text);
+        #[cfg(not(all(target_os = "macos", feature = "language")))]
+        let language = None;
+        let kind = classify(&text);
+        let code_language = match kind {
+            Some(TextKind::Code) => guess_code_language(&text),
+            _ => None,
+        };
+        let direction = text_direction(&text);
+        let script = dominant_script(&text);
+        let (text, redactions) = redact(&text);
+        let (sequence, captured_at) = next_sequence_and_time();
+        Self {
+            is_file_paths: false,
+            app_name,
+            approx_tokens: Some(approx_tokens(&text)),
+            text: vec![text],
+            kind,
+            code_language,
+            language,
+            direction,
+            script,
+            html: None,
+            markdown: None,
+            styled_runs: None,
+            files: None,
+            redactions,
+            lossy: false,
+            lossy_paths: Vec::new(),
+            image: None,
+            clipboard_touched: false,
+            clipboard_restored: None,
+            clipboard_restore_skipped: false,
+            url: None,
+            chat_pane: None,
+            document_path: None,
+            pasteboard_patterns: None,
+            clipboard_token: None,
+            strategy: None,
+            bounds: None,
+            sequence,
+            captured_at,
+        }
     }
 
-    Ok(PasteboardSavedState {
-        saved_change_count,
-        saved_contents,
-    })
-}
+    /// An image selection, e.g. a copied image or `capture_selection_image`.
+    /// With the `ocr` feature `text` holds the recognized text.
+    pub fn from_image(app_name: String, image: Vec<u8>) -> Self {
+        #[cfg(all(target_os = "macos", feature = "ocr"))]
+        let text = recognize_text(&image)
+            .map_err(|e| error!("recognize_text failed: {:?}", e))
+            .unwrap_or_default();
+        #[cfg(not(all(target_os = "macos", feature = "ocr")))]
+        let text = String::new();
+        let mut selected_text = Self::from_text(app_name, text);
+        selected_text.image = Some(image);
+        selected_text
+    }
 
-#[cfg(target_os = "macos")]
-pub fn get_selected_text_from_pasteboard(
-    app_name: String,
-    pasteboard: &objc2::rc::Retained<NSPasteboard>,
-    saved_change_count: isize,
-    saved_contents: Option<objc2::rc::Retained<NSArray<NSPasteboardItem>>>,
-    pasteboard_wait_timeout: u64,
-) -> anyhow::Result<SelectedText> {
-    use log::info;
-    use objc2::runtime::ProtocolObject;
-
-    let start_time = std::time::Instant::now();
-    let timeout = std::time::Duration::from_millis(pasteboard_wait_timeout);
-    let mut new_change_count = saved_change_count;
-    while new_change_count == saved_change_count {
-        if start_time.elapsed() > timeout {
-            break;
+    pub fn from_file_paths(app_name: String, paths: Vec<String>) -> Self {
+        let (sequence, captured_at) = next_sequence_and_time();
+        Self {
+            is_file_paths: true,
+            app_name,
+            text: paths,
+            kind: None,
+            code_language: None,
+            approx_tokens: None,
+            language: None,
+            direction: None,
+            script: None,
+            html: None,
+            markdown: None,
+            styled_runs: None,
+            files: None,
+            redactions: Vec::new(),
+            lossy: false,
+            lossy_paths: Vec::new(),
+            image: None,
+            clipboard_touched: false,
+            clipboard_restored: None,
+            clipboard_restore_skipped: false,
+            url: None,
+            chat_pane: None,
+            document_path: None,
+            pasteboard_patterns: None,
+            clipboard_token: None,
+            strategy: None,
+            bounds: None,
+            sequence,
+            captured_at,
         }
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        new_change_count = unsafe { pasteboard.changeCount() };
     }
-    if new_change_count == saved_change_count {
-        println!("User didn't select any text or pasteboard took too long to update");
-        info!("User didn't select any text or pasteboard took too long to update");
-        return Ok(SelectedText {
-            is_file_paths: false,
-            app_name: app_name.clone(),
-            text: vec![String::new()],
-        });
+
+    /// Whether this result was read after `other`.
+    pub fn is_newer_than(&self, other: &SelectedText) -> bool {
+        self.sequence > other.sequence
     }
-    let copied_text = unsafe { pasteboard.stringForType(NSPasteboardTypeString) };
-    println!("copied_text: {:?}", copied_text);
-    println!("new_change_count: {:?}", new_change_count);
-    println!("saved_change_count: {:?}", saved_change_count);
-    unsafe {
-        if let Some(prev_contents) = saved_contents {
-            pasteboard.clearContents();
-            let max = prev_contents.count();
-            println!("max: {:?}", max);
-            println!("prev_contents: {:?}", prev_contents.lastObject());
-            if max > 1 {
-                let mut objs = Vec::with_capacity(max + 10);
-                for i in 0..max - 1 {
-                    objs.push(ProtocolObject::from_retained(
-                        prev_contents.objectAtIndex(i),
-                    ));
-                }
-
-                let res = pasteboard.writeObjects(&NSArray::from_vec(objs));
-                if !res {
-                    bail!("Failed to write objects to pasteboard");
-                }
-            }
+
+    /// Stats every selected path. Paths that can no longer be read are left out.
+    pub fn with_file_metadata(mut self) -> Self {
+        if !self.is_file_paths {
+            return self;
         }
+        // lossy names only stat under their exact path
+        let paths = self
+            .text
+            .iter()
+            .filter(|path| self.lossy_paths.is_empty() || !path.contains('\u{fffd}'))
+            .map(Path::new)
+            .chain(self.lossy_paths.iter().map(PathBuf::as_path));
+        let files = paths
+            .filter_map(|path| {
+                file_metadata(path)
+                    .map_err(|e| error!("Failed to stat {}: {}", path.display(), e))
+                    .ok()
+            })
+            .collect();
+        self.files = Some(files);
+        self
     }
-    Ok(SelectedText {
-        is_file_paths: false,
-        app_name: app_name.clone(),
-        text: vec![copied_text.map(|t| t.to_string()).unwrap_or_default()],
-    })
-}
 
-pub fn get_window_meta() -> (String, String) {
-    match get_active_window() {
-        Ok(window) => (window.app_name, window.title),
-        Err(_) => {
-            // user might be in the desktop / home view
-            ("Empty Window".into(), "Empty Window".into())
+    /// Applies `options` to a text selection, file paths are left untouched.
+    /// The result is redacted again, stripping invisible characters can join
+    /// a secret the first pass didn't recognize.
+    pub fn normalized(mut self, options: &NormalizeOptions) -> Self {
+        if self.is_file_paths || *options == NormalizeOptions::default() {
+            return self;
+        }
+        for text in &mut self.text {
+            let (redacted, found) = redact(&normalize(text, options));
+            *text = redacted;
+            redact::add_redactions(&mut self.redactions, found);
+        }
+        if let Some(text) = self.text.first() {
+            self.kind = classify(text);
+            self.code_language = match self.kind {
+                Some(TextKind::Code) => guess_code_language(text),
+                _ => None,
+            };
+            self.approx_tokens = Some(approx_tokens(text));
         }
+        self
     }
 }
 
-pub fn in_finder_or_empty_window() -> (bool, String) {
-    let (app_name, _) = get_window_meta();
-    (app_name == "Finder" || app_name == "Empty Window", app_name)
+/// How the selection is put on the pasteboard when AX can't read it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyMethod {
+    /// Post the app's copy chord (Cmd+C unless `set_copy_chord` says
+    /// otherwise) as CGEvents.
+    #[default]
+    KeyChord,
+    /// Send the app's copy chord through System Events with the alert sound
+    /// muted.
+    AppleScript,
+    /// Press the frontmost app's Edit ▸ Copy menu item through AX. No key
+    /// events are sent, so the keyboard layout and apps that rebind Cmd+C
+    /// don't matter.
+    MenuItem,
 }
 
-pub fn get_selected_files(window_name: &str) -> anyhow::Result<SelectedText> {
-    let no_active_app = window_name == "Empty Window";
-    match get_selected_file_paths_by_clipboard_using_applescript(no_active_app) {
-        Ok(text) => {
-            println!("file paths: {:?}", text.split("\n"));
-            return Ok(SelectedText {
-                is_file_paths: true,
-                app_name: window_name.to_owned(),
-                text: text
-                    .split("\n")
-                    .map(|t| t.to_owned())
-                    .collect::<Vec<String>>(),
-            });
-        }
-        Err(e) => {
-            bail!(
-                "get_selected_file_paths_by_clipboard_using_applescript failed: {:?}",
-                e
-            );
+impl CopyMethod {
+    fn name(self) -> &'static str {
+        match self {
+            CopyMethod::KeyChord => "key-chord",
+            CopyMethod::AppleScript => "applescript",
+            CopyMethod::MenuItem => "menu-item",
         }
     }
 }
-pub fn get_selected_text_using_ax_then_copy(
-    app_name: String,
-    pasteboard: &objc2::rc::Retained<NSPasteboard>,
-    use_apple_script: bool,
-) -> anyhow::Result<GetSelectedTextResult> {
-    let mut selected_text = SelectedText {
-        is_file_paths: false,
-        app_name: app_name.clone(),
-        text: vec![],
-    };
-
-    match get_selected_text_by_ax() {
-        Ok(txt) => {
-            selected_text.text = vec![txt];
-            Ok(GetSelectedTextResult::Text(selected_text))
+
+impl serde::Serialize for CopyMethod {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// Parses a method name: `key-chord`, `applescript` or `menu-item`.
+impl std::str::FromStr for CopyMethod {
+    type Err = SelectedTextError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_ascii_lowercase().replace('_', "-");
+        [
+            CopyMethod::KeyChord,
+            CopyMethod::AppleScript,
+            CopyMethod::MenuItem,
+        ]
+        .into_iter()
+        .find(|method| method.name() == name)
+        .ok_or_else(|| SelectedTextError::other(format_args!("Unknown copy method {:?}", s)))
+    }
+}
+
+/// How a capture gets the selection. `peek` only predicts the first four,
+/// for what comes after an app handler that may fall through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaptureStrategy {
+    /// Read over AX, nothing else happens.
+    Ax,
+    /// The app already copied the selection on its own.
+    AutoCopy,
+    /// The app has to copy, the user's pasteboard is saved and restored.
+    Copy(CopyMethod),
+    /// The focused element reports an empty selection.
+    NothingSelected,
+    /// The app handler with this name, see `register_handler`.
+    Handler(&'static str),
+    /// Finder's selected files, copied through a Finder script.
+    FinderFilePaths,
+    /// What's being dragged, see `current_drag_items`.
+    Drag,
+}
+
+/// What the user is looking at when a capture starts.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum ActiveWindow {
+    /// An app is frontmost. `title` is `None` when it has no window or the
+    /// title can't be read without Screen Recording permission.
+    App {
+        name: String,
+        bundle_id: Option<String>,
+        pid: i32,
+        title: Option<String>,
+    },
+    /// Finder is frontmost without a window, the selection is on the desktop.
+    Desktop,
+    /// The screen is locked or the screensaver is running, there's nothing
+    /// to capture.
+    ScreenLocked,
+    /// No app is frontmost.
+    Unknown,
+}
+
+impl ActiveWindow {
+    /// Name of the frontmost app, Finder's for `Desktop`.
+    pub fn app_name(&self) -> Option<&str> {
+        match self {
+            ActiveWindow::App { name, .. } => Some(name),
+            ActiveWindow::Desktop => Some("Finder"),
+            ActiveWindow::ScreenLocked | ActiveWindow::Unknown => None,
         }
-        Err(e) => {
-            error!("get_selected_text_by_ax failed: {:?}", e);
-            Ok(GetSelectedTextResult::PasteboardState(
-                ctrl_c_and_save_pasteboard(pasteboard, use_apple_script)?,
-            ))
+    }
+
+    /// Where a capture should look, `None` when there's nothing to capture.
+    pub fn app(&self) -> Option<ActiveApp> {
+        match self {
+            ActiveWindow::App { name, .. } => Some(ActiveApp::App(name.clone())),
+            ActiveWindow::Desktop => Some(ActiveApp::Desktop),
+            ActiveWindow::ScreenLocked | ActiveWindow::Unknown => None,
         }
     }
 }
 
-fn get_selected_text_by_ax() -> anyhow::Result<String> {
-    log::info!("get_selected_text_by_ax");
-    let system_element = AXUIElement::system_wide();
-    let Some(selected_element) = system_element
-        .attribute(&AXAttribute::new(&CFString::from_static_string(
-            kAXFocusedUIElementAttribute,
-        )))
-        .map(|element| element.downcast_into::<AXUIElement>())
-        .ok()
-        .flatten()
-    else {
-        bail!("No selected element");
-    };
-    let Some(selected_text) = selected_element
-        .attribute(&AXAttribute::new(&CFString::from_static_string(
-            kAXSelectedTextAttribute,
-        )))
-        .map(|text| text.downcast_into::<CFString>())
-        .ok()
-        .flatten()
-    else {
-        bail!("No selected text");
-    };
-    Ok(selected_text.to_string())
+/// Where the selection lives when a capture starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActiveApp {
+    /// No window is active, the user is on the desktop and the selection is
+    /// Finder's desktop selection.
+    Desktop,
+    App(String),
 }
 
-const FILE_PATH_COPY_APPLE_SCRIPT: &str = r#"
-tell application "Finder"
-	set selectedItems to selection
-	
-	if selectedItems is {} then
-		return "" -- Return an empty string if no items are selected
-	end if
-	
-	set itemPaths to {}
-	repeat with anItem in selectedItems
-		set filePath to POSIX path of (anItem as alias)
-		-- Escape any existing double quotes in the file path
-		set escapedPath to my replace_chars(filePath, "\"", "\\\"")
-		-- Add the escaped and quoted path to the list
-		set end of itemPaths to "\"" & escapedPath & "\""
-	end repeat
-	
-	set AppleScript's text item delimiters to linefeed
-	set pathText to itemPaths as text
-	
-	return pathText -- Return the pathText content
-end tell
-
-on replace_chars(this_text, search_string, replacement_string)
-	set AppleScript's text item delimiters to the search_string
-	set the item_list to every text item of this_text
-	set AppleScript's text item delimiters to the replacement_string
-	set this_text to the item_list as string
-	set AppleScript's text item delimiters to ""
-	return this_text
-end replace_chars
-"#;
-
-const EMPTY_WINDOW_PATH_COPY_APPLE_SCRIPT: &str = r#"
-tell application "Finder"
-	set desktopPath to (path to desktop folder as text)
-	set selectedItems to (get selection)
-	
-	if selectedItems is {} then
-		return "" -- Return an empty string if no items are selected
-	end if
-	
-	set itemPaths to {}
-	repeat with anItem in selectedItems
-		set filePath to POSIX path of (anItem as alias)
-		-- Escape any existing double quotes in the file path
-		set escapedPath to my replace_chars(filePath, "\"", "\\\"")
-		-- Add the escaped and quoted path to the list
-		set end of itemPaths to "\"" & escapedPath & "\""
-	end repeat
-	
-	set AppleScript's text item delimiters to linefeed
-	set pathText to itemPaths as text
-	
-	return pathText -- Return the pathText content
-end tell
-
-on replace_chars(this_text, search_string, replacement_string)
-	set AppleScript's text item delimiters to the search_string
-	set the item_list to every text item of this_text
-	set AppleScript's text item delimiters to the replacement_string
-	set this_text to the item_list as string
-	set AppleScript's text item delimiters to ""
-	return this_text
-end replace_chars
-"#;
-
-fn get_selected_file_paths_by_clipboard_using_applescript(
-    for_empty_window: bool,
-) -> Result<String, Box<dyn std::error::Error>> {
-    log::info!("get_selected_text_by_clipboard_using_applescript");
-    let mut binding = std::process::Command::new("osascript");
-    let cmd = binding.arg("-e");
-
-    if for_empty_window {
-        cmd.arg(EMPTY_WINDOW_PATH_COPY_APPLE_SCRIPT);
-    } else {
-        cmd.arg(FILE_PATH_COPY_APPLE_SCRIPT);
-    };
-
-    let output = cmd.output()?;
-
-    if output.status.success() {
-        let content = String::from_utf8(output.stdout)?;
-        let content = content.trim();
-        Ok(content.to_string())
-    } else {
-        let err = output
-            .stderr
-            .into_iter()
-            .map(|c| c as char)
-            .collect::<String>()
-            .into();
-        Err(err)
+impl ActiveApp {
+    /// Name reported as `SelectedText::app_name`, the desktop belongs to
+    /// Finder.
+    pub fn app_name(&self) -> &str {
+        match self {
+            ActiveApp::Desktop => "Finder",
+            ActiveApp::App(name) => name,
+        }
     }
-}
 
-fn _selected_text(
-    app_name: String,
-    pasteboard: &objc2::rc::Retained<NSPasteboard>,
-    use_apple_script: bool,
-) -> anyhow::Result<SelectedText> {
-    match get_selected_text_using_ax_then_copy(app_name.clone(), &pasteboard, use_apple_script)? {
-        GetSelectedTextResult::Text(selected_text) => Ok(selected_text),
-        GetSelectedTextResult::PasteboardState(mut pasteboard_saved_state) => {
-            get_selected_text_from_pasteboard(
-                app_name.clone(),
-                &pasteboard,
-                pasteboard_saved_state.saved_change_count,
-                pasteboard_saved_state.saved_contents.take(),
-                90,
-            )
+    /// Whether the selection is files rather than text.
+    pub fn selects_files(&self) -> bool {
+        match self {
+            ActiveApp::Desktop => true,
+            ActiveApp::App(name) => name == "Finder",
         }
     }
 }
@@ -391,29 +687,21 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_get_selected_text() {
-        const USE_APPLE_SCRIPT: bool = false;
-        let dummy_app_name = "Dummy App".to_owned();
-        let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
-        println!("--- get_selected_text ---");
-        let mut start = std::time::Instant::now();
-        let text = _selected_text(dummy_app_name.clone(), &pasteboard, USE_APPLE_SCRIPT).unwrap();
-        let elapsed = start.elapsed();
-        println!("Time elapsed: {} ms", elapsed.as_millis());
-        println!("selected text: {:#?}", text);
-        println!("--- get_selected_text ---");
-        std::thread::sleep(std::time::Duration::from_millis(1000));
-        start = std::time::Instant::now();
-        let text = _selected_text(dummy_app_name.clone(), &pasteboard, USE_APPLE_SCRIPT).unwrap();
-        let elapsed = start.elapsed();
-        println!("Time elapsed: {} ms", elapsed.as_millis());
-        println!("selected text: {:#?}", text);
-        println!("--- get_selected_text ---");
-        std::thread::sleep(std::time::Duration::from_millis(1000));
-        start = std::time::Instant::now();
-        let text = _selected_text(dummy_app_name.clone(), &pasteboard, USE_APPLE_SCRIPT).unwrap();
-        let elapsed = start.elapsed();
-        println!("Time elapsed: {} ms", elapsed.as_millis());
-        println!("selected text: {:#?}", text);
+    fn test_results_are_sequenced() {
+        let first = SelectedText::from_text("Notes".to_owned(), "a".to_owned());
+        let second = SelectedText::from_file_paths("Finder".to_owned(), vec!["/tmp".to_owned()]);
+        assert!(second.is_newer_than(&first));
+        assert!(!first.is_newer_than(&second));
+    }
+
+    #[test]
+    fn test_normalized_is_redacted_again() {
+        add_redaction("normalized-test", r"hunter2-[0-9]{4}").unwrap();
+        let selected_text =
+            SelectedText::from_text("Notes".to_owned(), "pw hunter2-\u{200b}1234".to_owned())
+                .normalized(&NormalizeOptions::all());
+        assert_eq!(selected_text.text, ["pw [REDACTED:normalized-test]"]);
+        assert_eq!(selected_text.redactions.len(), 1);
+        assert_eq!(selected_text.redactions[0].count, 1);
     }
 }
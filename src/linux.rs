@@ -0,0 +1,245 @@
+// X11 apps publish whatever is highlighted as the PRIMARY selection, so the
+// selection can be read without posting a copy or touching the clipboard.
+// Wayland has a primary selection too, readable by clients outside the focus
+// only through wlr-data-control. Apps that publish neither are asked over
+// AT-SPI2 for the selection of the focused accessible instead.
+
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use active_win_pos_rs::get_active_window;
+use atspi_proxies::{
+    accessible::AccessibleProxyBlocking,
+    bus::BusProxyBlocking,
+    common::{Interface, ObjectRefOwned, State},
+    text::TextProxyBlocking,
+};
+use zbus::{blocking::connection, proxy::CacheProperties};
+
+use crate::{
+    decode_lossy, diagnostics, wayland, ActiveWindow, Result, SelectedText, SelectedTextError,
+    SelectedTextProvider,
+};
+
+/// The primary selection through wlr-data-control on Wayland, through
+/// `xclip` or `xsel` on X11, then AT-SPI2 over the accessibility bus. Each
+/// read gets `command_timeout` milliseconds, a selection owner or accessible
+/// that never answers would block it forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinuxProvider {
+    pub command_timeout: u64,
+}
+
+impl Default for LinuxProvider {
+    fn default() -> Self {
+        LinuxProvider {
+            command_timeout: 1000,
+        }
+    }
+}
+
+impl SelectedTextProvider for LinuxProvider {
+    fn active_window(&self) -> ActiveWindow {
+        match get_active_window() {
+            Ok(window) => ActiveWindow::App {
+                name: window.app_name,
+                bundle_id: None,
+                pid: window.process_id as i32,
+                title: Some(window.title).filter(|title| !title.is_empty()),
+            },
+            Err(_) => ActiveWindow::Unknown,
+        }
+    }
+
+    /// PRIMARY keeps the last highlighted text after the app deselects it,
+    /// until something else is highlighted, so it can be stale.
+    fn get_selected_text(&self) -> Result<SelectedText> {
+        let app_name = self
+            .active_window()
+            .app_name()
+            .unwrap_or_default()
+            .to_owned();
+        let timeout = Duration::from_millis(self.command_timeout);
+        let text = primary_selection(timeout)
+            .filter(|text| !text.is_empty())
+            .or_else(|| atspi_selection(timeout));
+        match text {
+            Some(text) => Ok(SelectedText::from_text(app_name, text)),
+            None if !is_x11() && !is_wayland() => Err(SelectedTextError::other(
+                "no X11 or Wayland display and no AT-SPI2 selection",
+            )),
+            None => Ok(SelectedText::from_text(app_name, String::new())),
+        }
+    }
+}
+
+fn is_x11() -> bool {
+    std::env::var_os("DISPLAY").is_some()
+}
+
+fn is_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+// XWayland's PRIMARY lags behind or misses what native Wayland clients
+// select, so wlr-data-control goes first in a Wayland session. GNOME has no
+// data control, XWayland's PRIMARY is the fallback there.
+fn primary_selection(timeout: Duration) -> Option<String> {
+    if is_wayland() {
+        if let Some(text) = wayland::primary_selection(timeout) {
+            return Some(text);
+        }
+    }
+    if !is_x11() {
+        return None;
+    }
+    run(timeout, "xclip", &["-o", "-selection", "primary"])
+        .or_else(|| run(timeout, "xsel", &["--primary", "--output"]))
+}
+
+/// The selection of the focused accessible in the active window, `None`
+/// when nothing has focus, it has no text or the accessibility bus is down.
+fn atspi_selection(timeout: Duration) -> Option<String> {
+    match focused_selection(timeout) {
+        Ok(text) => text,
+        Err(e) => {
+            diagnostics::debug!("AT-SPI2: {}", e);
+            None
+        }
+    }
+}
+
+fn focused_selection(timeout: Duration) -> zbus::Result<Option<String>> {
+    let deadline = Instant::now() + timeout;
+    // the accessibility bus is a separate bus, the session bus knows where
+    let session = connection::Builder::session()?
+        .method_timeout(timeout)
+        .build()?;
+    let address = BusProxyBlocking::new(&session)?.get_address()?;
+    let bus = connection::Builder::address(address.as_str())?
+        .method_timeout(timeout)
+        .build()?;
+    let desktop = AccessibleProxyBlocking::builder(&bus)
+        .destination("org.a11y.atspi.Registry")?
+        .path("/org/a11y/atspi/accessible/root")?
+        .cache_properties(CacheProperties::No)
+        .build()?;
+    for app in desktop.get_children()? {
+        let app = accessible(&bus, &app)?;
+        for window in app.get_children()? {
+            let window = accessible(&bus, &window)?;
+            if !window.get_state()?.contains(State::Active) {
+                continue;
+            }
+            let Some(node) = focused(&bus, window, 0, deadline) else {
+                return Ok(None);
+            };
+            if !node.get_interfaces()?.contains(Interface::Text) {
+                return Ok(None);
+            }
+            let text = TextProxyBlocking::builder(&bus)
+                .destination(node.inner().destination().to_owned())?
+                .path(node.inner().path().to_owned())?
+                .cache_properties(CacheProperties::No)
+                .build()?;
+            let mut parts = Vec::new();
+            for i in 0..text.get_n_selections()? {
+                let (start, end) = text.get_selection(i)?;
+                parts.push(text.get_text(start, end)?);
+            }
+            return Ok(Some(parts.join("\n")));
+        }
+    }
+    Ok(None)
+}
+
+fn accessible<'a>(
+    bus: &zbus::blocking::Connection,
+    object: &ObjectRefOwned,
+) -> zbus::Result<AccessibleProxyBlocking<'a>> {
+    let name = object
+        .name()
+        .ok_or_else(|| zbus::Error::Failure("accessible without a bus name".into()))?;
+    AccessibleProxyBlocking::builder(bus)
+        .destination(name.to_owned())?
+        .path(object.path().to_owned())?
+        .cache_properties(CacheProperties::No)
+        .build()
+}
+
+// Depth first, capped since some apps expose huge tables and the walk is
+// one round trip per node
+fn focused<'a>(
+    bus: &zbus::blocking::Connection,
+    node: AccessibleProxyBlocking<'a>,
+    depth: usize,
+    deadline: Instant,
+) -> Option<AccessibleProxyBlocking<'a>> {
+    if depth > 40 || Instant::now() > deadline {
+        return None;
+    }
+    if node.get_state().ok()?.contains(State::Focused) {
+        return Some(node);
+    }
+    let children = node.get_children().ok()?;
+    children
+        .iter()
+        .take(500)
+        .filter_map(|child| accessible(bus, child).ok())
+        .find_map(|child| focused(bus, child, depth + 1, deadline))
+}
+
+/// What `program` printed, `None` when it's missing, failed or ran out of
+/// time.
+fn run(timeout: Duration, program: &str, args: &[&str]) -> Option<String> {
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            diagnostics::debug!("{}: {}", program, e);
+            return None;
+        }
+    };
+    let mut stdout = child.stdout.take()?;
+    // read on another thread, a big selection would fill the pipe before exit
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() < timeout => thread::sleep(Duration::from_millis(5)),
+            _ => {
+                diagnostics::debug!("{} didn't answer within {:?}", program, timeout);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    let output = reader.join().ok()?.ok()?;
+    status.success().then(|| decode_lossy(&output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let timeout = Duration::from_secs(5);
+        assert_eq!(run(timeout, "printf", &["%s", "héllo"]).unwrap(), "héllo");
+        assert_eq!(run(timeout, "false", &[]), None);
+        assert_eq!(run(timeout, "no-such-program", &[]), None);
+        assert_eq!(run(Duration::from_millis(50), "sleep", &["5"]), None);
+    }
+}
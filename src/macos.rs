@@ -0,0 +1,1031 @@
+// The capture itself: AX first, then a copy into the user's pasteboard that's
+// put back afterwards, and Finder's selection through AppleScript. All of it
+// talks to AppKit, AX or System Events, other platforms have a provider of
+// their own and share only the types in lib.rs.
+
+use std::sync::Arc;
+
+use core_graphics::event::CGKeyCode;
+use objc2::rc::Retained;
+use objc2_app_kit::{
+    NSPasteboard, NSPasteboardItem, NSPasteboardTypeHTML, NSPasteboardTypePNG,
+    NSPasteboardTypeString, NSPasteboardTypeTIFF, NSWorkspace,
+};
+use objc2_foundation::NSArray;
+
+use crate::diagnostics::{self, debug, warn};
+#[cfg(feature = "rtf")]
+use crate::parse_rtf;
+#[cfg(feature = "pasteboard-patterns")]
+use crate::patterns;
+#[cfg(feature = "file-promises")]
+use crate::promises;
+use crate::{
+    accessibility, active_window, applescript, attribution, autocopy, ax, bounds, breadcrumb,
+    capture_lock, chord, chord_timing_for, copy_chord_for, decode_lossy, delivery, error, files,
+    find, handlers, html_to_markdown, markers, policy, post_key, redact, strategy_cache,
+    strategy_config, ActiveApp, ActiveWindow, Attribution, CaptureStage, CaptureStrategy,
+    ChordTiming, ClipboardToken, CopyChord, CopyMethod, Learned, PasteboardGuard,
+    PasteboardSnapshot, Redacted, Result, SelectedText, SelectedTextError, SelectedTextOptions,
+    Strategy, ThreadConfined,
+};
+
+impl SelectedText {
+    fn touched_clipboard(&mut self, restored: bool, restore_skipped: bool) {
+        self.clipboard_touched = true;
+        self.clipboard_restored = Some(restored);
+        self.clipboard_restore_skipped = restore_skipped;
+    }
+
+    pub(crate) fn with_strategy(mut self, strategy: CaptureStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    pub(crate) fn with_html(mut self, html: Option<String>) -> Self {
+        let html = html.map(|html| {
+            let (html, found) = redact(&html);
+            self.record_redactions(found);
+            html
+        });
+        self.markdown = html.as_deref().map(html_to_markdown);
+        self.html = html;
+        self
+    }
+
+    #[cfg(feature = "rtf")]
+    fn with_rtf(mut self, rtf: Option<&[u8]>) -> Self {
+        let mut styled_runs = rtf.map(parse_rtf);
+        for run in styled_runs.iter_mut().flatten() {
+            let (text, found) = redact(&run.text);
+            run.text = text;
+            self.record_redactions(found);
+        }
+        self.styled_runs = styled_runs;
+        self
+    }
+
+    // the same secret usually shows up in every flavor, so keep the highest count
+    fn record_redactions(&mut self, found: Vec<Redacted>) {
+        for found in found {
+            match self.redactions.iter_mut().find(|r| r.name == found.name) {
+                Some(redacted) => redacted.count = redacted.count.max(found.count),
+                None => self.redactions.push(found),
+            }
+        }
+    }
+}
+
+pub struct PasteboardSavedState {
+    pub saved_change_count: isize,
+    /// `None` when the clipboard was too large to snapshot, it isn't restored.
+    pub saved_contents: Option<PasteboardSnapshot>,
+}
+
+// boxing `SelectedText` would break callers matching on the variant
+#[allow(clippy::large_enum_variant)]
+pub enum GetSelectedTextResult {
+    Text(SelectedText),
+    PasteboardState(PasteboardSavedState),
+}
+
+/// A pasteboard and a snapshot of its items that can be handed to other
+/// threads, see `ThreadConfined`.
+#[derive(Clone)]
+pub struct PasteBoardContainer {
+    pub inner: Arc<ThreadConfined<Retained<NSPasteboard>>>,
+    pub pasteboard: Option<Arc<ThreadConfined<Retained<NSArray<NSPasteboardItem>>>>>,
+}
+
+pub fn simulate(key: CGKeyCode, key_down: bool) -> Result<()> {
+    post_key(key, key_down)?;
+    // Let ths MacOS catchup
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    Ok(())
+}
+
+// KeyPress(Key),
+// KeyRelease(Key),
+// reference - https://github.com/Narsil/rdev/blob/main/src/macos/keycodes.rs
+pub fn sim_ctrl_c() -> Result<()> {
+    sim_chord(&CopyChord::cmd_c(), &chord_timing_for(None))
+}
+
+/// Types `chord` and checks that the events arrived. A failure part way
+/// through releases every key pressed so far, keys whose release wasn't seen
+/// are released again, a chord that never reached the app fails
+/// with `SelectedTextError::InterceptedByEventTap`.
+pub fn sim_chord(chord: &CopyChord, timing: &ChordTiming) -> Result<()> {
+    let key = chord.key_code()?;
+    let watch = delivery::DeliveryWatch::start();
+    let mut held = Vec::new();
+    if let Err(e) = type_chord(chord, key, timing, &mut held) {
+        // anything left down stays logically pressed for every later event
+        for &key in held.iter().rev() {
+            if let Err(release_error) = post_key(key, false) {
+                error!("failed to release key {}: {:?}", key, release_error);
+            }
+        }
+        return Err(e.into());
+    }
+
+    // without a tap there's nothing to check against
+    let Some(watch) = watch else {
+        return Ok(());
+    };
+    let delivery = watch.finish();
+    let pressed = chord.modifiers.iter().map(|modifier| modifier.key_code());
+    let pressed: Vec<CGKeyCode> = pressed.chain(std::iter::once(key)).collect();
+    for &stuck in pressed.iter().rev().filter(|&&k| !delivery.released(k)) {
+        warn!("release of key {} wasn't seen, releasing it again", stuck);
+        post_key(stuck, false)?;
+    }
+    if !delivery.pressed(key) {
+        return Err(SelectedTextError::InterceptedByEventTap);
+    }
+    Ok(())
+}
+
+fn type_chord(
+    chord: &CopyChord,
+    key: CGKeyCode,
+    timing: &ChordTiming,
+    held: &mut Vec<CGKeyCode>,
+) -> anyhow::Result<()> {
+    for modifier in &chord.modifiers {
+        debug!("keydown {:?}", modifier);
+        press(modifier.key_code(), held)?;
+        std::thread::sleep(timing.gap);
+    }
+    debug!("keydown {:?}", chord.key);
+    press(key, held)?;
+    std::thread::sleep(timing.hold);
+    debug!("key up {:?}", chord.key);
+    release(key, held)?;
+    for modifier in chord.modifiers.iter().rev() {
+        std::thread::sleep(timing.gap);
+        debug!("key up {:?}", modifier);
+        release(modifier.key_code(), held)?;
+    }
+    std::thread::sleep(timing.settle);
+    Ok(())
+}
+
+fn press(key: CGKeyCode, held: &mut Vec<CGKeyCode>) -> anyhow::Result<()> {
+    post_key(key, true)?;
+    held.push(key);
+    Ok(())
+}
+
+fn release(key: CGKeyCode, held: &mut Vec<CGKeyCode>) -> anyhow::Result<()> {
+    post_key(key, false)?;
+    held.retain(|&k| k != key);
+    Ok(())
+}
+
+fn quiet_copy_script(chord: &CopyChord) -> String {
+    let modifiers = chord
+        .modifiers
+        .iter()
+        .map(|modifier| modifier.applescript_name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"
+tell application "System Events"
+    set savedAlertVolume to alert volume of (get volume settings)
+    set volume alert volume 0
+    keystroke {} using {{{}}}
+    set volume alert volume savedAlertVolume
+end tell
+"#,
+        applescript::quote(&chord.key.to_string()),
+        modifiers
+    )
+}
+
+fn quiet_cmd_c(chord: &CopyChord) -> anyhow::Result<()> {
+    // debug_println!("get_selected_text_by_clipboard_using_applescript");
+    let output = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(quiet_copy_script(chord))
+        .output()?;
+    // .spawn()?;
+
+    if !output.status.success() {
+        return Err(applescript::error_from_stderr(
+            &output.stderr,
+            "System Events",
+        ));
+    }
+    Ok(())
+}
+
+impl CopyMethod {
+    pub(crate) fn from_applescript_flag(use_applescript: bool) -> Self {
+        if use_applescript {
+            CopyMethod::AppleScript
+        } else {
+            CopyMethod::KeyChord
+        }
+    }
+
+    fn copy(self, timing: Option<ChordTiming>) -> anyhow::Result<()> {
+        let bundle_id = match self {
+            CopyMethod::MenuItem => None,
+            _ => frontmost_bundle_id(),
+        };
+        let chord = copy_chord_for(bundle_id.as_deref());
+        match self {
+            CopyMethod::KeyChord => Ok(sim_chord(
+                &chord,
+                &copy_chord_timing(bundle_id.as_deref(), timing),
+            )?),
+            CopyMethod::AppleScript => quiet_cmd_c(&chord),
+            CopyMethod::MenuItem => ax::press_copy_menu_item(),
+        }
+    }
+}
+
+static COPY_METHODS: std::sync::RwLock<Vec<(String, CopyMethod)>> =
+    std::sync::RwLock::new(Vec::new());
+
+/// Copies with `method` in the app with `bundle_id`, whatever the caller
+/// passes to `get_selected_text_using_ax_then_copy_with`. `None` goes back
+/// to the caller's method.
+pub fn set_copy_method(bundle_id: &str, method: Option<CopyMethod>) {
+    let mut methods = COPY_METHODS.write().unwrap();
+    methods.retain(|(id, _)| !id.eq_ignore_ascii_case(bundle_id));
+    if let Some(method) = method {
+        methods.push((bundle_id.to_owned(), method));
+    }
+}
+
+// set for the app, then its handler's
+pub(crate) fn app_copy_method(bundle_id: &str) -> Option<CopyMethod> {
+    strategy_config::copy_method(bundle_id)
+        .or_else(|| copy_method_for(bundle_id))
+        .or_else(|| handlers::copy_method(bundle_id))
+}
+
+pub(crate) fn copy_method_for(bundle_id: &str) -> Option<CopyMethod> {
+    let method = COPY_METHODS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .map(|(_, method)| *method);
+    #[cfg(feature = "app-config")]
+    let method =
+        method.or_else(|| crate::config::app_setting(bundle_id, |settings| settings.copy_method));
+    method
+}
+
+// a timing set for the app wins over its handler's, both win over `default`
+// and the default set with `set_default_chord_timing`
+pub(crate) fn copy_chord_timing(
+    bundle_id: Option<&str>,
+    default: Option<ChordTiming>,
+) -> ChordTiming {
+    bundle_id
+        .and_then(|id| chord::configured_chord_timing(id).or_else(|| handlers::chord_timing(id)))
+        .or(default)
+        .unwrap_or_else(|| chord_timing_for(None))
+}
+
+pub fn ctrl_c_and_save_pasteboard(
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    use_applescript: bool,
+) -> Result<PasteboardSavedState> {
+    copy_and_save_pasteboard(
+        pasteboard,
+        CopyMethod::from_applescript_flag(use_applescript),
+    )
+}
+
+/// Snapshots the pasteboard and copies the selection. Captures take turns,
+/// this waits for one still running and the turn lasts until the result is
+/// passed to `get_selected_text_from_pasteboard`.
+pub fn copy_and_save_pasteboard(
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    method: CopyMethod,
+) -> Result<PasteboardSavedState> {
+    save_and_copy(pasteboard, method, None, true)
+}
+
+// without `restore` the pasteboard isn't snapshotted, so it isn't put back
+fn save_and_copy(
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    method: CopyMethod,
+    timing: Option<ChordTiming>,
+    restore: bool,
+) -> Result<PasteboardSavedState> {
+    policy::ensure_capture_allowed()?;
+    // ended by `get_selected_text_from_pasteboard`
+    capture_lock::begin();
+    // puts the clipboard back if the copy fails after all
+    let mut guard = if restore {
+        PasteboardGuard::new(pasteboard)
+    } else {
+        PasteboardGuard::from_snapshot(pasteboard, unsafe { pasteboard.changeCount() }, None)
+    };
+    let saved_change_count = guard.change_count();
+    capture_lock::claim(saved_change_count);
+    attribution::record_attempt(pasteboard, saved_change_count, method);
+    breadcrumb::switch_strategy(method.name());
+    breadcrumb::reached(CaptureStage::Copying);
+
+    let copied = std::panic::catch_unwind(|| method.copy(timing))
+        .unwrap_or_else(|panic| Err(SelectedTextError::panicked(panic).into()));
+    if let Err(e) = copied {
+        // a tap that remaps Cmd+C may still have made the app copy
+        let intercepted = e.downcast_ref() == Some(&SelectedTextError::InterceptedByEventTap);
+        if !intercepted || unsafe { pasteboard.changeCount() } == saved_change_count {
+            breadcrumb::failed(e.downcast_ref());
+            capture_lock::end();
+            return Err(e.into());
+        }
+        warn!("copy chord was intercepted but the pasteboard changed anyway");
+    }
+
+    Ok(PasteboardSavedState {
+        saved_change_count,
+        saved_contents: guard.disarm(),
+    })
+}
+
+pub fn get_selected_text_from_pasteboard(
+    app_name: String,
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    saved_change_count: isize,
+    saved_contents: Option<PasteboardSnapshot>,
+    pasteboard_wait_timeout: u64,
+) -> Result<SelectedText> {
+    let turn = capture_lock::Turn::of_copy(saved_change_count);
+    breadcrumb::reached(CaptureStage::WaitingForPasteboard);
+    // the copy may have replaced the user's clipboard, an error or a panic
+    // before the restore puts it back on the way out
+    let mut guard = PasteboardGuard::from_snapshot(pasteboard, saved_change_count, saved_contents);
+    let mut after_restore = AfterRestore::default();
+    let read = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        read_copied_selection(
+            app_name,
+            pasteboard,
+            &mut guard,
+            pasteboard_wait_timeout,
+            &mut after_restore,
+        )
+    }));
+    drop(guard);
+    drop(turn);
+    #[cfg(feature = "file-promises")]
+    let read = read.map(|read| {
+        read.map(|mut selected_text| {
+            if let Some(file_promises) = after_restore.file_promises {
+                selected_text.text = file_promises
+                    .resolve()
+                    .map_err(|e| error!("Failed to resolve file promises: {:?}", e))
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect();
+            }
+            selected_text
+        })
+    });
+    let read = read
+        .unwrap_or_else(|panic| Err(SelectedTextError::panicked(panic).into()))
+        .map(
+            |selected_text| match attribution::copy_method(saved_change_count) {
+                Some(method) => selected_text.with_strategy(CaptureStrategy::Copy(method)),
+                None => selected_text,
+            },
+        );
+    match &read {
+        Ok(_) => breadcrumb::reached(CaptureStage::Finished),
+        Err(e) => breadcrumb::failed(e.downcast_ref()),
+    }
+    Ok(delivered(read?, pasteboard))
+}
+
+// every capture result goes out through here
+pub(crate) fn delivered(
+    mut selected_text: SelectedText,
+    pasteboard: &NSPasteboard,
+) -> SelectedText {
+    selected_text.bounds = bounds::selection_bounds();
+    selected_text.clipboard_token = Some(ClipboardToken::current(pasteboard));
+    find::publish(&selected_text);
+    selected_text
+}
+
+/// How long a copy waits for the system to detect pasteboard patterns.
+#[cfg(feature = "pasteboard-patterns")]
+const PATTERN_WAIT: std::time::Duration = std::time::Duration::from_millis(50);
+
+// what's left of a capture once the user's clipboard is back
+#[derive(Default)]
+struct AfterRestore {
+    #[cfg(feature = "file-promises")]
+    file_promises: Option<promises::FilePromises>,
+}
+
+#[cfg_attr(not(feature = "file-promises"), allow(unused_variables))]
+fn read_copied_selection(
+    app_name: String,
+    pasteboard: &NSPasteboard,
+    guard: &mut PasteboardGuard,
+    pasteboard_wait_timeout: u64,
+    after_restore: &mut AfterRestore,
+) -> anyhow::Result<SelectedText> {
+    use diagnostics::info;
+
+    let saved_change_count = guard.change_count();
+    let start_time = std::time::Instant::now();
+    let bundle_id = frontmost_bundle_id();
+    let mut timeout = std::time::Duration::from_millis(pasteboard_wait_timeout);
+    if let Some(min_wait) = bundle_id.as_deref().and_then(handlers::min_pasteboard_wait) {
+        timeout = timeout.max(min_wait);
+    }
+    let mut new_change_count = saved_change_count;
+    let mut seen_change_count = saved_change_count;
+    // an unchanged pasteboard is only accepted once nothing better showed up
+    let mut unchanged_change_count = None;
+    while start_time.elapsed() <= timeout {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let change_count = unsafe { pasteboard.changeCount() };
+        if change_count == seen_change_count {
+            continue;
+        }
+        seen_change_count = change_count;
+        match attribution::attribute(pasteboard, saved_change_count) {
+            Attribution::Ours => {
+                // before a clipboard manager gets around to recording it, a
+                // copy that isn't restored is the user's to keep
+                let change_count = guard
+                    .snapshot()
+                    .and_then(|_| markers::mark_copy(pasteboard))
+                    .unwrap_or(change_count);
+                new_change_count =
+                    settled_change_count(pasteboard, change_count, start_time + timeout);
+                break;
+            }
+            Attribution::Unchanged => unchanged_change_count = Some(change_count),
+            Attribution::Foreign(reason) => {
+                info!("ignoring pasteboard change {}: {}", change_count, reason);
+                unchanged_change_count = None;
+            }
+        }
+    }
+    if let Some(bundle_id) = &bundle_id {
+        let unchanged = new_change_count == saved_change_count && unchanged_change_count.is_some();
+        autocopy::note_copy(bundle_id, unchanged);
+    }
+    if new_change_count == saved_change_count {
+        new_change_count = unchanged_change_count.unwrap_or(saved_change_count);
+    }
+    if new_change_count == saved_change_count {
+        // nothing of ours to undo, maybe someone else's copy to keep
+        guard.disarm();
+        autocopy::note_change_count(new_change_count);
+        // an empty result, but for stats the copy didn't work
+        breadcrumb::failed(Some(&SelectedTextError::PasteboardTimeout));
+        if error::is_strict() {
+            return Err(SelectedTextError::PasteboardTimeout.into());
+        }
+        info!("User didn't select any text or pasteboard took too long to update");
+        return Ok(SelectedText::from_text(app_name, String::new()));
+    }
+    let copied_text = unsafe { pasteboard.stringForType(NSPasteboardTypeString) };
+    let recovered_text = match copied_text {
+        Some(_) => None,
+        None => recover_pasteboard_text(pasteboard),
+    };
+    let copied_image = match (&copied_text, &recovered_text) {
+        (None, None) => copied_image(pasteboard),
+        _ => None,
+    };
+    #[cfg(feature = "file-promises")]
+    let file_promises = match (&copied_text, &recovered_text, &copied_image) {
+        (None, None, None) => promises::file_promises(pasteboard),
+        _ => None,
+    };
+    let copied_html = unsafe { pasteboard.stringForType(NSPasteboardTypeHTML) };
+    #[cfg(feature = "rtf")]
+    let copied_rtf = unsafe { pasteboard.dataForType(objc2_app_kit::NSPasteboardTypeRTF) };
+    // before the restore replaces what was copied, and briefly since the
+    // user's clipboard is gone until then
+    #[cfg(feature = "pasteboard-patterns")]
+    let pasteboard_patterns = patterns::detect_within(pasteboard, PATTERN_WAIT)
+        .map_err(|e| debug!("{}", e))
+        .ok();
+    #[cfg(not(feature = "pasteboard-patterns"))]
+    let pasteboard_patterns = None;
+    debug!("new_change_count: {:?}", new_change_count);
+    debug!("saved_change_count: {:?}", saved_change_count);
+    breadcrumb::reached(CaptureStage::Restoring);
+    let replaced = unsafe { pasteboard.changeCount() } != new_change_count
+        && unsafe { pasteboard.stringForType(NSPasteboardTypeString) } != copied_text;
+    let restore_skipped = guard.snapshot().is_none();
+    let restored = if replaced || restore_skipped {
+        // restoring would clobber whatever was copied since, not our copy
+        info!("pasteboard was replaced after the copy, not restoring it");
+        guard.disarm();
+        false
+    } else {
+        guard
+            .restore()
+            .map_err(|e| error!("Failed to restore the pasteboard: {:?}", e))
+            .is_ok()
+    };
+    autocopy::note_change_count(unsafe { pasteboard.changeCount() });
+    if !restored && !replaced && !restore_skipped && error::is_strict() {
+        return Err(SelectedTextError::RestoreFailed.into());
+    }
+    if let Some(image) = copied_image {
+        let mut selected_text = SelectedText::from_image(app_name, image);
+        selected_text.touched_clipboard(restored, restore_skipped);
+        selected_text.pasteboard_patterns = pasteboard_patterns;
+        return Ok(selected_text);
+    }
+    // the paths are filled in once the promises are resolved
+    #[cfg(feature = "file-promises")]
+    if let Some(file_promises) = file_promises {
+        let mut selected_text = SelectedText::from_file_paths(app_name, Vec::new());
+        selected_text.touched_clipboard(restored, restore_skipped);
+        selected_text.pasteboard_patterns = pasteboard_patterns;
+        after_restore.file_promises = Some(file_promises);
+        return Ok(selected_text);
+    }
+    let lossy = recovered_text.is_some();
+    let copied_text = copied_text
+        .map(|t| t.to_string())
+        .or(recovered_text)
+        .unwrap_or_default();
+    let copied_text = bundle_id
+        .as_deref()
+        .and_then(|id| handlers::tidy_copied_text(id, &copied_text))
+        .unwrap_or(copied_text);
+    let mut selected_text = SelectedText::from_text(app_name, copied_text)
+        .with_html(copied_html.map(|t| t.to_string()));
+    selected_text.lossy = lossy;
+    selected_text.touched_clipboard(restored, restore_skipped);
+    selected_text.pasteboard_patterns = pasteboard_patterns;
+    #[cfg(feature = "rtf")]
+    let selected_text = selected_text.with_rtf(copied_rtf.as_deref().map(|data| data.bytes()));
+    debug!("copied_text: {:?}", selected_text.text);
+    Ok(selected_text)
+}
+
+/// How long the change count has to stay put before a copy is read.
+pub(crate) const SETTLE_TIME: std::time::Duration = std::time::Duration::from_millis(30);
+
+// Clipboard managers like Paste rewrite the pasteboard as soon as it changes,
+// reading before they're done races them.
+fn settled_change_count(
+    pasteboard: &NSPasteboard,
+    mut change_count: isize,
+    deadline: std::time::Instant,
+) -> isize {
+    let mut unchanged_since = std::time::Instant::now();
+    while unchanged_since.elapsed() < SETTLE_TIME && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let current = unsafe { pasteboard.changeCount() };
+        if current != change_count {
+            debug!("pasteboard rewritten: {} -> {}", change_count, current);
+            change_count = current;
+            unchanged_since = std::time::Instant::now();
+        }
+    }
+    change_count
+}
+
+pub(crate) fn restore_pasteboard(
+    pasteboard: &NSPasteboard,
+    saved_contents: Option<&PasteboardSnapshot>,
+) -> anyhow::Result<()> {
+    if let Some(snapshot) = saved_contents {
+        debug!(
+            "restoring {} items, {} bytes",
+            snapshot.len(),
+            snapshot.size()
+        );
+        snapshot.restore(pasteboard)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn copied_image(pasteboard: &NSPasteboard) -> Option<Vec<u8>> {
+    [unsafe { NSPasteboardTypePNG }, unsafe {
+        NSPasteboardTypeTIFF
+    }]
+    .into_iter()
+    .find_map(|image_type| unsafe { pasteboard.dataForType(image_type) })
+    .map(|data| data.bytes().to_vec())
+}
+
+// some apps only post text as raw data under a legacy or private type
+pub(crate) fn recover_pasteboard_text(pasteboard: &NSPasteboard) -> Option<String> {
+    const TEXT_TYPE_HINTS: &[&str] = &["text", "string", "utf8", "utf16", "TEXT", "ustr"];
+    let types = unsafe { pasteboard.types() }?;
+    for pasteboard_type in types.iter() {
+        let name = pasteboard_type.to_string();
+        if !TEXT_TYPE_HINTS.iter().any(|hint| name.contains(hint)) {
+            continue;
+        }
+        let Some(data) = (unsafe { pasteboard.dataForType(pasteboard_type) }) else {
+            continue;
+        };
+        let text = decode_lossy(data.bytes());
+        if !text.is_empty() {
+            debug!("recovered text from pasteboard type {}", name);
+            return Some(text);
+        }
+    }
+    None
+}
+
+// what the deprecated API calls anything but an app with a window
+const LEGACY_EMPTY_WINDOW: &str = "Empty Window";
+
+#[deprecated(note = "use `active_window`, this reports the desktop and a locked screen alike")]
+pub fn get_window_meta() -> (String, String) {
+    match active_window() {
+        ActiveWindow::App {
+            name,
+            title: Some(title),
+            ..
+        } => (name, title),
+        ActiveWindow::App { name, .. } => (name, String::new()),
+        _ => (LEGACY_EMPTY_WINDOW.into(), LEGACY_EMPTY_WINDOW.into()),
+    }
+}
+
+// the focused element is ours when we're frontmost, or when one of our
+// non-activating panels has focus over another app
+pub(crate) fn focused_in_this_process() -> bool {
+    let pid = std::process::id() as i32;
+    let workspace = unsafe { NSWorkspace::sharedWorkspace() };
+    let frontmost = unsafe { workspace.frontmostApplication() };
+    frontmost.is_some_and(|app| unsafe { app.processIdentifier() } == pid)
+        || ax::focused_pid() == Some(pid)
+}
+
+pub(crate) fn frontmost_bundle_id() -> Option<String> {
+    let workspace = unsafe { NSWorkspace::sharedWorkspace() };
+    let app = unsafe { workspace.frontmostApplication() }?;
+    unsafe { app.bundleIdentifier() }.map(|id| id.to_string())
+}
+
+/// Where a capture should look. A locked screen, where captures fail with
+/// `SelectedTextError::ScreenLocked`, and no frontmost app count as the desktop,
+/// see `active_window` to tell them apart.
+pub fn active_app() -> ActiveApp {
+    active_window().app().unwrap_or(ActiveApp::Desktop)
+}
+
+#[deprecated(note = "use `active_window`, the app name is \"Empty Window\" on the desktop")]
+pub fn in_finder_or_empty_window() -> (bool, String) {
+    match active_window() {
+        ActiveWindow::App { name, .. } => (name == "Finder", name),
+        _ => (true, LEGACY_EMPTY_WINDOW.to_owned()),
+    }
+}
+
+#[deprecated(note = "use `get_selected_files_of`")]
+pub fn get_selected_files(window_name: &str) -> Result<SelectedText> {
+    let app = match window_name {
+        LEGACY_EMPTY_WINDOW => ActiveApp::Desktop,
+        name => ActiveApp::App(name.to_owned()),
+    };
+    get_selected_files_of(&app)
+}
+
+/// The POSIX paths selected in Finder, or on the desktop for
+/// `ActiveApp::Desktop`.
+pub fn get_selected_files_of(app: &ActiveApp) -> Result<SelectedText> {
+    policy::ensure_capture_allowed()?;
+    let on_desktop = *app == ActiveApp::Desktop;
+    breadcrumb::begin(app.app_name(), "finder-script");
+    match get_selected_file_paths_by_clipboard_using_applescript(on_desktop) {
+        Ok(output) => {
+            breadcrumb::reached(CaptureStage::Finished);
+            let (paths, lossy_paths) = files::decode_paths(&output);
+            debug!("file paths: {:?}", paths);
+            let mut selected_text = SelectedText::from_file_paths(app.app_name().to_owned(), paths)
+                .with_strategy(CaptureStrategy::FinderFilePaths);
+            selected_text.lossy = !lossy_paths.is_empty();
+            selected_text.lossy_paths = lossy_paths;
+            let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
+            Ok(delivered(selected_text, &pasteboard))
+        }
+        Err(e) if e.is::<SelectedTextError>() => {
+            breadcrumb::failed(e.downcast_ref());
+            Err(e.into())
+        }
+        Err(e) => {
+            breadcrumb::failed(None);
+            Err(SelectedTextError::other(format_args!(
+                "get_selected_file_paths_by_clipboard_using_applescript failed: {:?}",
+                e
+            )))
+        }
+    }
+}
+
+/// How many items are selected in Finder or on the desktop. Finder only
+/// counts them, which is much cheaper than resolving every path with
+/// `get_selected_files_of`.
+pub fn selected_finder_item_count() -> Result<usize> {
+    policy::ensure_capture_allowed()?;
+    let output = applescript::run(SELECTION_COUNT_APPLE_SCRIPT, "Finder")?;
+    let count = String::from_utf8_lossy(&output);
+    count.trim().parse().map_err(|e| {
+        SelectedTextError::other(format_args!(
+            "Finder returned {:?} as the count: {}",
+            count, e
+        ))
+    })
+}
+
+const SELECTION_COUNT_APPLE_SCRIPT: &str = r#"
+tell application "Finder"
+	return count of (get selection)
+end tell
+"#;
+
+pub fn get_selected_text_using_ax_then_copy(
+    app_name: String,
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    use_apple_script: bool,
+) -> Result<GetSelectedTextResult> {
+    get_selected_text_using_ax_then_copy_with(
+        app_name,
+        pasteboard,
+        CopyMethod::from_applescript_flag(use_apple_script),
+    )
+}
+
+pub fn get_selected_text_using_ax_then_copy_with(
+    app_name: String,
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    copy_method: CopyMethod,
+) -> Result<GetSelectedTextResult> {
+    capture_with_options(
+        app_name,
+        pasteboard,
+        &SelectedTextOptions::new().copy_method(copy_method),
+    )
+}
+
+pub(crate) fn capture_with_options(
+    app_name: String,
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    options: &SelectedTextOptions,
+) -> Result<GetSelectedTextResult> {
+    policy::ensure_capture_allowed()?;
+    if focused_in_this_process() {
+        return Err(SelectedTextError::SelfFocused);
+    }
+    if error::is_strict() && !accessibility::is_trusted() {
+        return Err(SelectedTextError::AccessibilityDenied);
+    }
+    breadcrumb::begin(&app_name, "ax");
+    let bundle_id = frontmost_bundle_id();
+    let mut ax_failed = false;
+    for strategy in &strategies_for(bundle_id.as_deref(), options) {
+        match strategy {
+            Strategy::Handler => {
+                let Some(bundle_id) = &bundle_id else {
+                    continue;
+                };
+                if let Some(selected_text) = handlers::capture_with_handler(&app_name, bundle_id)
+                    .inspect_err(|e| breadcrumb::failed(e.downcast_ref()))?
+                {
+                    breadcrumb::reached(CaptureStage::Finished);
+                    return Ok(GetSelectedTextResult::Text(delivered(
+                        selected_text,
+                        pasteboard,
+                    )));
+                }
+            }
+            Strategy::Ax => {
+                breadcrumb::switch_strategy("ax");
+                breadcrumb::reached(CaptureStage::ReadingAx);
+                let ax_read = match bundle_id.as_deref() {
+                    Some(id) if strategy_cache::skip_ax(id) => Err(anyhow::anyhow!(
+                        "skipped AX, {} needed the clipboard last time",
+                        id
+                    )),
+                    _ => get_selected_text_by_ax(),
+                };
+                match ax_read {
+                    Ok(txt) => {
+                        if let Some(id) = &bundle_id {
+                            strategy_cache::learn(id, Learned::Ax);
+                        }
+                        breadcrumb::reached(CaptureStage::Finished);
+                        return Ok(GetSelectedTextResult::Text(delivered(
+                            SelectedText::from_text(app_name, txt)
+                                .with_strategy(CaptureStrategy::Ax),
+                            pasteboard,
+                        )));
+                    }
+                    Err(e) => {
+                        error!("get_selected_text_by_ax failed: {:?}", e);
+                        // otherwise we'd wait out the whole pasteboard timeout
+                        if ax::selection_is_empty() {
+                            diagnostics::info!("focused element has no selection, not copying");
+                            break;
+                        }
+                        ax_failed = true;
+                    }
+                }
+            }
+            Strategy::AutoCopy => {
+                if let Some(selected_text) = auto_copied_selection(&app_name, pasteboard) {
+                    breadcrumb::switch_strategy("auto-copy");
+                    breadcrumb::reached(CaptureStage::Finished);
+                    return Ok(GetSelectedTextResult::Text(delivered(
+                        selected_text,
+                        pasteboard,
+                    )));
+                }
+            }
+            Strategy::Copy => {
+                if let Some(id) = bundle_id.as_deref().filter(|_| ax_failed) {
+                    strategy_cache::learn(id, Learned::Clipboard);
+                }
+                let copy_method = bundle_id
+                    .as_deref()
+                    .and_then(app_copy_method)
+                    .unwrap_or(options.copy_method);
+                return Ok(GetSelectedTextResult::PasteboardState(save_and_copy(
+                    pasteboard,
+                    copy_method,
+                    options.chord_timing,
+                    options.restore_pasteboard,
+                )?));
+            }
+        }
+    }
+    breadcrumb::reached(CaptureStage::Finished);
+    Ok(GetSelectedTextResult::Text(delivered(
+        SelectedText::from_text(app_name, String::new())
+            .with_strategy(CaptureStrategy::NothingSelected),
+        pasteboard,
+    )))
+}
+
+/// The strategies a capture tries in the app with `bundle_id`, the ones set
+/// for the app with `set_strategies` over those in `options`.
+pub(crate) fn strategies_for(
+    bundle_id: Option<&str>,
+    options: &SelectedTextOptions,
+) -> Vec<Strategy> {
+    bundle_id
+        .and_then(strategy_config::strategies)
+        .unwrap_or_else(|| options.strategies.clone())
+}
+
+// the selection of an app that copies on select is already on the pasteboard
+// if something wrote to it since the last capture
+fn auto_copied_selection(app_name: &str, pasteboard: &NSPasteboard) -> Option<SelectedText> {
+    let bundle_id = frontmost_bundle_id()?;
+    let change_count = unsafe { pasteboard.changeCount() };
+    if !autocopy::auto_copies(&bundle_id) || !autocopy::changed_since_last_capture(change_count) {
+        return None;
+    }
+    let text = unsafe { pasteboard.stringForType(NSPasteboardTypeString) }?;
+    let html = unsafe { pasteboard.stringForType(NSPasteboardTypeHTML) };
+    autocopy::note_change_count(change_count);
+    diagnostics::info!("{} copies on select, reading the pasteboard", bundle_id);
+    Some(
+        SelectedText::from_text(app_name.to_owned(), text.to_string())
+            .with_html(html.map(|html| html.to_string()))
+            .with_strategy(CaptureStrategy::AutoCopy),
+    )
+}
+
+fn get_selected_text_by_ax() -> anyhow::Result<String> {
+    diagnostics::info!("get_selected_text_by_ax");
+    Ok(ax::selected_text()?.to_string())
+}
+
+const FILE_PATH_COPY_APPLE_SCRIPT: &str = r#"
+tell application "Finder"
+	set selectedItems to selection
+	
+	if selectedItems is {} then
+		return "" -- Return an empty string if no items are selected
+	end if
+	
+	set itemPaths to {}
+	repeat with anItem in selectedItems
+		set filePath to POSIX path of (anItem as alias)
+		set end of itemPaths to my quote_text(filePath)
+	end repeat
+	
+	set AppleScript's text item delimiters to linefeed
+	set pathText to itemPaths as text
+	
+	return pathText -- Return the pathText content
+end tell
+"#;
+
+const EMPTY_WINDOW_PATH_COPY_APPLE_SCRIPT: &str = r#"
+tell application "Finder"
+	set desktopPath to (path to desktop folder as text)
+	set selectedItems to (get selection)
+	
+	if selectedItems is {} then
+		return "" -- Return an empty string if no items are selected
+	end if
+	
+	set itemPaths to {}
+	repeat with anItem in selectedItems
+		set filePath to POSIX path of (anItem as alias)
+		set end of itemPaths to my quote_text(filePath)
+	end repeat
+	
+	set AppleScript's text item delimiters to linefeed
+	set pathText to itemPaths as text
+	
+	return pathText -- Return the pathText content
+end tell
+"#;
+
+fn get_selected_file_paths_by_clipboard_using_applescript(
+    for_empty_window: bool,
+) -> anyhow::Result<Vec<u8>> {
+    diagnostics::info!("get_selected_text_by_clipboard_using_applescript");
+    let mut binding = std::process::Command::new("osascript");
+    let cmd = binding.arg("-e");
+
+    let script = if for_empty_window {
+        EMPTY_WINDOW_PATH_COPY_APPLE_SCRIPT
+    } else {
+        FILE_PATH_COPY_APPLE_SCRIPT
+    };
+    cmd.arg(format!("{}{}", script, applescript::QUOTE_HANDLER));
+
+    let output = cmd.output()?;
+
+    if output.status.success() {
+        // decoded per path, see `files::decode_paths`
+        Ok(output.stdout.trim_ascii().to_vec())
+    } else {
+        Err(applescript::error_from_stderr(&output.stderr, "Finder"))
+    }
+}
+
+fn _selected_text(
+    app_name: String,
+    pasteboard: &objc2::rc::Retained<NSPasteboard>,
+    use_apple_script: bool,
+) -> Result<SelectedText> {
+    match get_selected_text_using_ax_then_copy(app_name.clone(), &pasteboard, use_apple_script)? {
+        GetSelectedTextResult::Text(selected_text) => Ok(selected_text),
+        GetSelectedTextResult::PasteboardState(mut pasteboard_saved_state) => {
+            get_selected_text_from_pasteboard(
+                app_name.clone(),
+                &pasteboard,
+                pasteboard_saved_state.saved_change_count,
+                pasteboard_saved_state.saved_contents.take(),
+                90,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_selected_text() {
+        const USE_APPLE_SCRIPT: bool = false;
+        let dummy_app_name = "Dummy App".to_owned();
+        let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
+        println!("--- get_selected_text ---");
+        let mut start = std::time::Instant::now();
+        let text = _selected_text(dummy_app_name.clone(), &pasteboard, USE_APPLE_SCRIPT).unwrap();
+        let elapsed = start.elapsed();
+        println!("Time elapsed: {} ms", elapsed.as_millis());
+        println!("selected text: {:#?}", text);
+        println!("--- get_selected_text ---");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+        start = std::time::Instant::now();
+        let text = _selected_text(dummy_app_name.clone(), &pasteboard, USE_APPLE_SCRIPT).unwrap();
+        let elapsed = start.elapsed();
+        println!("Time elapsed: {} ms", elapsed.as_millis());
+        println!("selected text: {:#?}", text);
+        println!("--- get_selected_text ---");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+        start = std::time::Instant::now();
+        let text = _selected_text(dummy_app_name.clone(), &pasteboard, USE_APPLE_SCRIPT).unwrap();
+        let elapsed = start.elapsed();
+        println!("Time elapsed: {} ms", elapsed.as_millis());
+        println!("selected text: {:#?}", text);
+    }
+}
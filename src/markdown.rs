@@ -0,0 +1,476 @@
+// Small HTML to Markdown converter for the HTML flavor apps put on the
+// pasteboard. It understands the structure browsers and editors emit when
+// copying (headings, paragraphs, links, emphasis, lists, quotes and code) and
+// drops everything else, keeping the text.
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Text(String),
+    Start {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    End {
+        name: String,
+    },
+}
+
+/// Converts an HTML fragment (e.g. the `public.html` pasteboard flavor) to Markdown.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut converter = Converter::default();
+    for token in tokenize(html) {
+        converter.token(token);
+    }
+    converter.finish()
+}
+
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        if lt > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..lt])));
+        }
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        let Some(end) = tag_end(rest) else {
+            // a stray `<` that doesn't open a tag
+            tokens.push(Token::Text("<".to_owned()));
+            rest = &rest[1..];
+            continue;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::End {
+                name: name.trim().to_ascii_lowercase(),
+            });
+            continue;
+        }
+        let (name, attrs) = parse_tag(tag.trim_end_matches('/'));
+        if name.is_empty() {
+            tokens.push(Token::Text(decode_entities(&format!("<{tag}>"))));
+            continue;
+        }
+        // the contents of these elements are never text
+        if name == "script" || name == "style" {
+            let closing = format!("</{name}");
+            rest = find_ascii_case_insensitive(rest, &closing)
+                .and_then(|start| rest[start..].find('>').map(|end| &rest[start + end + 1..]))
+                .unwrap_or("");
+            continue;
+        }
+        tokens.push(Token::Start { name, attrs });
+    }
+    tokens
+}
+
+// index of the `>` closing the tag that starts at `s[0] == '<'`, skipping quoted attribute values
+fn tag_end(s: &str) -> Option<usize> {
+    if !s[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_tag(tag: &str) -> (String, Vec<(String, String)>) {
+    let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+    let name = tag[..name_end].to_ascii_lowercase();
+    let mut attrs = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (raw, remaining) = match after_eq.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let inner = &after_eq[1..];
+                    let close = inner.find(q).unwrap_or(inner.len());
+                    (&inner[..close], inner.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let close = after_eq
+                        .find(|c: char| c.is_whitespace())
+                        .unwrap_or(after_eq.len());
+                    (&after_eq[..close], &after_eq[close..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining.trim_start();
+        }
+        if !key.is_empty() {
+            attrs.push((key, value));
+        }
+    }
+    (name, attrs)
+}
+
+fn find_ascii_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest
+            .find(';')
+            .filter(|&semi| semi <= 10)
+            .and_then(|semi| decode_entity(&rest[1..semi]).map(|c| (c, semi)));
+        match entity {
+            Some((c, semi)) => {
+                decoded.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        _ => return None,
+    })
+}
+
+#[derive(Default)]
+struct Converter {
+    out: String,
+    // `None` for `<ul>`, the next item number for `<ol>`
+    lists: Vec<Option<usize>>,
+    links: Vec<Option<String>>,
+    quote_depth: usize,
+    pre_depth: usize,
+    skip_depth: usize,
+    pending_space: bool,
+}
+
+impl Converter {
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Text(text) if self.skip_depth == 0 => self.text(&text),
+            Token::Text(_) => {}
+            Token::Start { name, attrs } => self.start(&name, &attrs),
+            Token::End { name } => self.end(&name),
+        }
+    }
+
+    fn start(&mut self, name: &str, attrs: &[(String, String)]) {
+        if name == "head" || name == "title" {
+            self.skip_depth += 1;
+        }
+        if self.skip_depth > 0 {
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.blank_line();
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                self.write(&format!("{} ", "#".repeat(level)));
+            }
+            "p" => self.blank_line(),
+            "div" | "tr" | "dt" | "dd" => self.newline(),
+            "br" => self.line_break(),
+            "hr" => {
+                self.blank_line();
+                self.write("---");
+                self.blank_line();
+            }
+            "strong" | "b" => self.inline_marker("**"),
+            "em" | "i" => self.inline_marker("*"),
+            "del" | "s" | "strike" => self.inline_marker("~~"),
+            "code" if self.pre_depth == 0 => self.inline_marker("`"),
+            "a" => {
+                let href = attribute(attrs, "href").filter(|href| !href.is_empty());
+                if href.is_some() {
+                    self.inline_marker("[");
+                }
+                self.links.push(href.map(str::to_owned));
+            }
+            "img" => {
+                if let Some(src) = attribute(attrs, "src") {
+                    let alt = attribute(attrs, "alt").unwrap_or_default();
+                    self.inline_marker(&format!("![{alt}]({src})"));
+                }
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.blank_line();
+                } else {
+                    self.newline();
+                }
+                let start = attribute(attrs, "start").and_then(|s| s.parse().ok());
+                self.lists.push((name == "ol").then(|| start.unwrap_or(1)));
+            }
+            "li" => {
+                self.newline();
+                let indent = "   ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_owned(),
+                };
+                self.write(&format!("{indent}{marker}"));
+            }
+            "pre" => {
+                self.blank_line();
+                self.write("```");
+                self.out.push('\n');
+                self.pre_depth += 1;
+            }
+            "blockquote" => {
+                self.blank_line();
+                self.quote_depth += 1;
+            }
+            "td" | "th" => self.pending_space = true,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if name == "head" || name == "title" {
+            self.skip_depth = self.skip_depth.saturating_sub(1);
+            return;
+        }
+        if self.skip_depth > 0 {
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" => self.blank_line(),
+            "div" | "tr" | "dt" | "dd" => self.newline(),
+            "strong" | "b" => self.write("**"),
+            "em" | "i" => self.write("*"),
+            "del" | "s" | "strike" => self.write("~~"),
+            "code" if self.pre_depth == 0 => self.write("`"),
+            "a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.write(&format!("]({href})"));
+                }
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                } else {
+                    self.newline();
+                }
+            }
+            "pre" => {
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                self.newline();
+                self.write("```");
+                self.blank_line();
+            }
+            "blockquote" => {
+                self.blank_line();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.out.push('\n');
+                }
+                if !line.is_empty() {
+                    self.write(line);
+                }
+            }
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                self.pending_space = true;
+                continue;
+            }
+            if self.pending_space && !self.at_line_start() && !self.out.ends_with(' ') {
+                self.out.push(' ');
+            }
+            self.pending_space = false;
+            let mut buf = [0; 4];
+            self.write(c.encode_utf8(&mut buf));
+        }
+    }
+
+    fn inline_marker(&mut self, marker: &str) {
+        if self.pending_space && !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        self.write(marker);
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn write(&mut self, s: &str) {
+        if self.at_line_start() && self.quote_depth > 0 {
+            self.out.push_str(&"> ".repeat(self.quote_depth));
+        }
+        self.out.push_str(s);
+    }
+
+    fn trim_trailing_spaces(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+    }
+
+    fn line_break(&mut self) {
+        self.trim_trailing_spaces();
+        self.out.push('\n');
+        self.pending_space = false;
+    }
+
+    fn newline(&mut self) {
+        self.trim_trailing_spaces();
+        if !self.at_line_start() {
+            self.out.push('\n');
+        }
+        self.pending_space = false;
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut markdown = String::with_capacity(self.out.len());
+        let mut newlines = 0;
+        for c in self.out.trim().chars() {
+            if c == '\n' {
+                newlines += 1;
+                if newlines > 2 {
+                    continue;
+                }
+            } else {
+                newlines = 0;
+            }
+            markdown.push(c);
+        }
+        markdown
+    }
+}
+
+fn attribute<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_formatting() {
+        assert_eq!(
+            html_to_markdown(
+                r#"<p>Read <a href="https://example.com">the <b>docs</b></a> and <em>enjoy</em> <code>cargo&nbsp;run</code>.</p>"#
+            ),
+            "Read [the **docs**](https://example.com) and *enjoy* `cargo\u{a0}run`."
+        );
+    }
+
+    #[test]
+    fn test_block_structure() {
+        let html = concat!(
+            "<html><head><meta charset=\"utf-8\"><style>p { color: red; }</style></head>",
+            "<body><h2>Title</h2><p>First   paragraph\nwrapped.</p>",
+            "<ul><li>one</li><li>two<ol><li>nested</li></ol></li></ul>",
+            "<blockquote><p>quoted</p></blockquote>",
+            "<pre><code>fn main() {\n    println!(\"&lt;hi&gt;\");\n}</code></pre></body></html>"
+        );
+        assert_eq!(
+            html_to_markdown(html),
+            "## Title\n\nFirst paragraph wrapped.\n\n- one\n- two\n   1. nested\n\n> quoted\n\n```\nfn main() {\n    println!(\"<hi>\");\n}\n```"
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &#60;&#x3e; &bogus; &"),
+            "a & b <> &bogus; &"
+        );
+    }
+
+    #[test]
+    fn test_stray_angle_brackets() {
+        assert_eq!(html_to_markdown("1 < 2 and 3 > 2"), "1 < 2 and 3 > 2");
+        assert_eq!(html_to_markdown("<!-- note -->text<br>more"), "text\nmore");
+    }
+}
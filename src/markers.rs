@@ -0,0 +1,70 @@
+// Clipboard history tools (Alfred, Maccy, Paste) record every change of the
+// pasteboard, the hidden copy of a capture included. The markers of
+// http://nspasteboard.org tell them to leave a change out: apps can't be
+// asked to write them, so they're added to the copy as soon as it lands.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use objc2_app_kit::NSPasteboard;
+use objc2_foundation::{NSArray, NSData, NSString};
+
+use crate::diagnostics;
+
+/// The data is only on the pasteboard for a moment.
+const TRANSIENT_TYPE: &str = "org.nspasteboard.TransientType";
+/// The data is sensitive and mustn't be stored.
+const CONCEALED_TYPE: &str = "org.nspasteboard.ConcealedType";
+
+static MARK_TRANSIENT: AtomicBool = AtomicBool::new(true);
+static MARK_CONCEALED: AtomicBool = AtomicBool::new(true);
+
+/// Which markers a capture adds to its copy, both are on by default.
+/// Clipboard tools differ in which of them they honor. A copy that isn't
+/// restored afterwards is never marked.
+pub fn set_pasteboard_markers(transient: bool, concealed: bool) {
+    MARK_TRANSIENT.store(transient, Ordering::Relaxed);
+    MARK_CONCEALED.store(concealed, Ordering::Relaxed);
+}
+
+fn marker_types(transient: bool, concealed: bool) -> Vec<&'static str> {
+    [(transient, TRANSIENT_TYPE), (concealed, CONCEALED_TYPE)]
+        .into_iter()
+        .filter_map(|(enabled, marker)| enabled.then_some(marker))
+        .collect()
+}
+
+/// Adds the enabled markers to what's on `pasteboard`, returning its new
+/// change count. `None` when no marker is enabled or they couldn't be added.
+pub(crate) fn mark_copy(pasteboard: &NSPasteboard) -> Option<isize> {
+    let markers = marker_types(
+        MARK_TRANSIENT.load(Ordering::Relaxed),
+        MARK_CONCEALED.load(Ordering::Relaxed),
+    );
+    if markers.is_empty() {
+        return None;
+    }
+    let markers: Vec<_> = markers.into_iter().map(NSString::from_str).collect();
+    let change_count =
+        unsafe { pasteboard.addTypes_owner(&NSArray::from_id_slice(&markers), None) };
+    if change_count == 0 {
+        diagnostics::debug!("couldn't add clipboard manager markers to the copy");
+        return None;
+    }
+    let empty = NSData::new();
+    for marker in &markers {
+        unsafe { pasteboard.setData_forType(Some(&empty), marker) };
+    }
+    Some(change_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_types() {
+        assert_eq!(marker_types(true, true), [TRANSIENT_TYPE, CONCEALED_TYPE]);
+        assert_eq!(marker_types(false, true), [CONCEALED_TYPE]);
+        assert!(marker_types(false, false).is_empty());
+    }
+}
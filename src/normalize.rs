@@ -0,0 +1,164 @@
+use std::sync::RwLock;
+
+/// Opt-in clean-ups applied to captured text. Everything is off by default so
+/// the text is returned exactly as the app provided it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Convert CR and CRLF line endings to LF.
+    pub line_endings: bool,
+    /// Remove zero-width spaces, bidi controls, soft hyphens and control
+    /// characters other than tab and newline.
+    pub strip_invisible: bool,
+    /// Replace non-breaking and figure spaces with plain spaces.
+    pub replace_nbsp: bool,
+    /// Fold smart quotes, dashes and ellipses to their ASCII equivalents.
+    pub fold_typography: bool,
+}
+
+impl NormalizeOptions {
+    pub fn all() -> Self {
+        Self {
+            line_endings: true,
+            strip_invisible: true,
+            replace_nbsp: true,
+            fold_typography: true,
+        }
+    }
+}
+
+static DEFAULT_OPTIONS: RwLock<NormalizeOptions> = RwLock::new(NormalizeOptions {
+    line_endings: false,
+    strip_invisible: false,
+    replace_nbsp: false,
+    fold_typography: false,
+});
+
+/// Clean-ups `get_selected_text` applies, and `get_selected_text_with` when
+/// its options set none. Off by default.
+pub fn set_default_normalize(options: NormalizeOptions) {
+    *DEFAULT_OPTIONS.write().unwrap() = options;
+}
+
+pub(crate) fn default_normalize() -> NormalizeOptions {
+    *DEFAULT_OPTIONS.read().unwrap()
+}
+
+pub fn normalize(text: &str, options: &NormalizeOptions) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if options.line_endings => {
+                chars.next_if_eq(&'\n');
+                normalized.push('\n');
+            }
+            '\u{a0}' | '\u{2007}' | '\u{202f}' if options.replace_nbsp => normalized.push(' '),
+            c if options.strip_invisible && is_invisible(c) => {}
+            c => match fold_typography(c) {
+                Some(folded) if options.fold_typography => normalized.push_str(folded),
+                _ => normalized.push(c),
+            },
+        }
+    }
+    normalized
+}
+
+// ZWJ and ZWNJ are left alone, they are load-bearing in emoji sequences and
+// several scripts
+fn is_invisible(c: char) -> bool {
+    match c {
+        '\t' | '\n' | '\r' => false,
+        '\u{200b}' | '\u{2060}' | '\u{feff}' | '\u{00ad}' | '\u{180e}' => true,
+        '\u{061c}'
+        | '\u{200e}'
+        | '\u{200f}'
+        | '\u{202a}'..='\u{202e}'
+        | '\u{2066}'..='\u{2069}' => true,
+        c => c.is_control(),
+    }
+}
+
+/// Joins words PDF text breaks across lines with a hyphen, `exam-\nple`
+/// becomes `example`. Hyphens before an uppercase letter or a digit, like
+/// `Jean-\nPaul`, and hyphens not ending a line are kept.
+#[cfg(target_os = "macos")]
+pub(crate) fn join_hyphenated_lines(text: &str) -> String {
+    let mut joined = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        let is_hyphen = matches!(c, '-' | '\u{00ad}' | '\u{2010}');
+        let after_letter = joined.chars().next_back().is_some_and(char::is_alphabetic);
+        if is_hyphen && after_letter {
+            if let Some(next_line) = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))
+                .map(|line| line.trim_start_matches([' ', '\t']))
+            {
+                if next_line.chars().next().is_some_and(char::is_lowercase) {
+                    rest = next_line;
+                    continue;
+                }
+            }
+        }
+        joined.push(c);
+    }
+    joined
+}
+
+fn fold_typography(c: char) -> Option<&'static str> {
+    let folded = match c {
+        '‘' | '’' | '‚' | '‛' | '′' => "'",
+        '“' | '”' | '„' | '‟' | '″' => "\"",
+        '‐' | '‑' | '‒' | '–' | '−' => "-",
+        '—' | '―' => "--",
+        '…' => "...",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let text = "a\r\nb\rc\n\u{200b}d\u{202e}e\u{7}\u{a0}f\tg 👩\u{200d}💻";
+        assert_eq!(normalize(text, &NormalizeOptions::default()), text);
+        assert_eq!(
+            normalize(text, &NormalizeOptions::all()),
+            "a\nb\nc\nde f\tg 👩\u{200d}💻"
+        );
+        let line_endings_only = NormalizeOptions {
+            line_endings: true,
+            ..Default::default()
+        };
+        assert_eq!(normalize("x\r\n\r\ny", &line_endings_only), "x\n\ny");
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_join_hyphenated_lines() {
+        assert_eq!(
+            join_hyphenated_lines("an exam-\nple of hyphen\u{ad}\r\n  ation"),
+            "an example of hyphenation"
+        );
+        assert_eq!(
+            join_hyphenated_lines("Jean-\nPaul, 1990-\n1999, well-known,\n- item"),
+            "Jean-\nPaul, 1990-\n1999, well-known,\n- item"
+        );
+    }
+
+    #[test]
+    fn test_fold_typography() {
+        let options = NormalizeOptions {
+            fold_typography: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize("“It’s 9–5…” — she said", &options),
+            "\"It's 9-5...\" -- she said"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::anyhow;
+use objc2::{rc::Retained, ClassType};
+use objc2_foundation::{NSArray, NSData, NSDictionary};
+use objc2_vision::{
+    VNImageRequestHandler, VNRecognizeTextRequest, VNRequest, VNRequestTextRecognitionLevel,
+};
+
+use crate::Result;
+
+/// Recognizes text in PNG, TIFF or any other ImageIO-readable image data with
+/// the Vision framework, one line per detected text line.
+pub fn recognize_text(image: &[u8]) -> Result<String> {
+    let data = NSData::with_bytes(image);
+    let handler = unsafe {
+        VNImageRequestHandler::initWithData_options(
+            VNImageRequestHandler::alloc(),
+            &data,
+            &NSDictionary::new(),
+        )
+    };
+    let request = unsafe { VNRecognizeTextRequest::new() };
+    unsafe {
+        request.setRecognitionLevel(VNRequestTextRecognitionLevel::Accurate);
+        request.setUsesLanguageCorrection(true);
+    }
+    let requests: Retained<NSArray<VNRequest>> = NSArray::from_slice(&[&**request]);
+    unsafe { handler.performRequests_error(&requests) }
+        .map_err(|e| anyhow!("Text recognition failed: {:?}", e))?;
+
+    let Some(observations) = (unsafe { request.results() }) else {
+        return Ok(String::new());
+    };
+    let lines = observations
+        .iter()
+        .filter_map(|observation| unsafe { observation.topCandidates(1).firstObject() })
+        .map(|candidate| unsafe { candidate.string() }.to_string())
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
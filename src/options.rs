@@ -0,0 +1,179 @@
+// Every knob of a capture used to be a loose argument (a bool for
+// AppleScript, a timeout in milliseconds) threaded through
+// `get_selected_text_using_ax_then_copy` and
+// `get_selected_text_from_pasteboard`. One value holds them now.
+
+use std::time::Duration;
+
+use crate::{
+    active_window, capture_with_options, get_selected_text_from_pasteboard, normalize,
+    truncate_graphemes, ChordTiming, CopyMethod, GetSelectedTextResult, NormalizeOptions,
+    PasteboardTarget, Result, SelectedText,
+};
+
+/// A way of reading the selection, tried in the order set with
+/// `SelectedTextOptions::strategies` until one gives a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// The handler registered for the frontmost app, see `register_handler`.
+    Handler,
+    /// The focused element's `AXSelectedText`.
+    Ax,
+    /// What an app that copies on select already put on the pasteboard.
+    AutoCopy,
+    /// Copying and reading the pasteboard, then restoring it.
+    Copy,
+}
+
+/// How `get_selected_text_with` captures. The defaults are what
+/// `get_selected_text_using_ax_then_copy` has always done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedTextOptions {
+    pub(crate) copy_method: CopyMethod,
+    pub(crate) pasteboard_timeout: Duration,
+    pub(crate) chord_timing: Option<ChordTiming>,
+    pub(crate) restore_pasteboard: bool,
+    pub(crate) max_len: Option<usize>,
+    pub(crate) normalize: Option<NormalizeOptions>,
+    pub(crate) strategies: Vec<Strategy>,
+    pub(crate) pasteboard: PasteboardTarget,
+}
+
+impl Default for SelectedTextOptions {
+    fn default() -> Self {
+        SelectedTextOptions {
+            copy_method: CopyMethod::default(),
+            pasteboard_timeout: Duration::from_millis(90),
+            chord_timing: None,
+            restore_pasteboard: true,
+            max_len: None,
+            normalize: None,
+            strategies: vec![
+                Strategy::Handler,
+                Strategy::Ax,
+                Strategy::AutoCopy,
+                Strategy::Copy,
+            ],
+            pasteboard: PasteboardTarget::General,
+        }
+    }
+}
+
+impl SelectedTextOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How to copy, unless one is set for the app with `set_copy_method`.
+    pub fn copy_method(mut self, copy_method: CopyMethod) -> Self {
+        self.copy_method = copy_method;
+        self
+    }
+
+    /// Copies through System Events instead of posting the chord.
+    pub fn use_applescript(self, use_applescript: bool) -> Self {
+        self.copy_method(CopyMethod::from_applescript_flag(use_applescript))
+    }
+
+    /// How long to wait for the app to write the pasteboard after a copy.
+    pub fn pasteboard_timeout(mut self, timeout: Duration) -> Self {
+        self.pasteboard_timeout = timeout;
+        self
+    }
+
+    /// Delays of the copy chord, unless a timing is set for the app with
+    /// `set_chord_timing`.
+    pub fn chord_timing(mut self, timing: ChordTiming) -> Self {
+        self.chord_timing = Some(timing);
+        self
+    }
+
+    /// Whether to put the user's clipboard back after copying. Without it
+    /// the clipboard is left holding the copy.
+    pub fn restore_pasteboard(mut self, restore: bool) -> Self {
+        self.restore_pasteboard = restore;
+        self
+    }
+
+    /// Cuts each captured text to `max_len` graphemes.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Clean-ups applied to the captured text, instead of the ones set with
+    /// `set_default_normalize`.
+    pub fn normalize(mut self, options: NormalizeOptions) -> Self {
+        self.normalize = Some(options);
+        self
+    }
+
+    /// Tries only `strategies`, in this order. The selection is empty when
+    /// none of them finds it.
+    pub fn strategies(mut self, strategies: impl IntoIterator<Item = Strategy>) -> Self {
+        self.strategies = strategies.into_iter().collect();
+        self
+    }
+
+    /// Which pasteboard is read and restored, the general one by default.
+    pub fn pasteboard(mut self, target: PasteboardTarget) -> Self {
+        self.pasteboard = target;
+        self
+    }
+}
+
+/// Captures the selection of the frontmost app as `options` say.
+pub fn get_selected_text_with(options: &SelectedTextOptions) -> Result<SelectedText> {
+    let app_name = active_window().app_name().unwrap_or_default().to_owned();
+    let pasteboard = options.pasteboard.pasteboard();
+    let selected_text = match capture_with_options(app_name.clone(), &pasteboard, options)? {
+        GetSelectedTextResult::Text(selected_text) => selected_text,
+        GetSelectedTextResult::PasteboardState(mut saved_state) => {
+            get_selected_text_from_pasteboard(
+                app_name,
+                &pasteboard,
+                saved_state.saved_change_count,
+                saved_state.saved_contents.take(),
+                options.pasteboard_timeout.as_millis() as u64,
+            )?
+        }
+    };
+    // normalizing first so `max_len` counts the graphemes that are returned
+    let normalize_options = options
+        .normalize
+        .unwrap_or_else(normalize::default_normalize);
+    let mut selected_text = selected_text.normalized(&normalize_options);
+    if let Some(max_len) = options.max_len {
+        for text in &mut selected_text.text {
+            let end = truncate_graphemes(text, max_len).len();
+            text.truncate(end);
+        }
+    }
+    Ok(selected_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_builder() {
+        let options = SelectedTextOptions::new()
+            .use_applescript(true)
+            .pasteboard_timeout(Duration::from_millis(250))
+            .restore_pasteboard(false)
+            .normalize(NormalizeOptions::all())
+            .strategies([Strategy::Ax]);
+        assert_eq!(options.copy_method, CopyMethod::AppleScript);
+        assert_eq!(options.pasteboard_timeout, Duration::from_millis(250));
+        assert!(!options.restore_pasteboard);
+        assert_eq!(options.normalize, Some(NormalizeOptions::all()));
+        assert_eq!(SelectedTextOptions::new().normalize, None);
+        assert_eq!(options.strategies, [Strategy::Ax]);
+        assert_eq!(SelectedTextOptions::new().max_len, None);
+        assert_eq!(
+            SelectedTextOptions::new().pasteboard,
+            PasteboardTarget::General
+        );
+    }
+}
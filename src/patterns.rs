@@ -0,0 +1,115 @@
+// Since macOS 13 the system can say what the pasteboard holds (a link, a
+// number, a search) without the contents being read, so without the paste
+// privacy prompt. Enough for a caller that only wants to classify a copy.
+
+/// What the system detected on the pasteboard, see
+/// `detect_pasteboard_patterns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum PasteboardPattern {
+    ProbableWebUrl,
+    ProbableWebSearch,
+    Number,
+}
+
+#[cfg(all(target_os = "macos", feature = "pasteboard-patterns"))]
+pub use detect::detect_pasteboard_patterns;
+#[cfg(all(target_os = "macos", feature = "pasteboard-patterns"))]
+pub(crate) use detect::detect_within;
+
+#[cfg(all(target_os = "macos", feature = "pasteboard-patterns"))]
+mod detect {
+    use std::ffi::{c_char, c_void, CStr};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use block2::RcBlock;
+    use objc2::{msg_send, sel};
+    use objc2_app_kit::NSPasteboard;
+    use objc2_foundation::{NSCopying, NSError, NSSet, NSString};
+
+    use super::PasteboardPattern;
+    use crate::{Result, SelectedTextError};
+
+    /// How long the pasteboard server gets to answer.
+    const WAIT: Duration = Duration::from_secs(2);
+
+    // `RTLD_DEFAULT`, every image loaded in the process
+    const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+
+    extern "C" {
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+
+    // AppKit only has the pattern names since macOS 13, linking them would
+    // keep the process from launching on older systems
+    fn pattern_name(symbol: &CStr) -> Option<&'static NSString> {
+        let name = unsafe { dlsym(RTLD_DEFAULT, symbol.as_ptr()) } as *const *const NSString;
+        unsafe { name.as_ref()?.as_ref() }
+    }
+
+    fn patterns() -> Option<[(PasteboardPattern, &'static NSString); 3]> {
+        Some([
+            (
+                PasteboardPattern::ProbableWebUrl,
+                pattern_name(c"NSPasteboardDetectionPatternProbableWebURL")?,
+            ),
+            (
+                PasteboardPattern::ProbableWebSearch,
+                pattern_name(c"NSPasteboardDetectionPatternProbableWebSearch")?,
+            ),
+            (
+                PasteboardPattern::Number,
+                pattern_name(c"NSPasteboardDetectionPatternNumber")?,
+            ),
+        ])
+    }
+
+    /// Patterns the system detects in what `pasteboard` holds, without
+    /// reading its contents. Fails before macOS 13.
+    pub fn detect_pasteboard_patterns(pasteboard: &NSPasteboard) -> Result<Vec<PasteboardPattern>> {
+        detect_within(pasteboard, WAIT)
+    }
+
+    /// Like `detect_pasteboard_patterns`, giving the pasteboard server `wait`
+    /// to answer.
+    pub(crate) fn detect_within(
+        pasteboard: &NSPasteboard,
+        wait: Duration,
+    ) -> Result<Vec<PasteboardPattern>> {
+        let selector = sel!(detectPatternsForPatterns:completionHandler:);
+        let supported: bool = unsafe { msg_send![pasteboard, respondsToSelector: selector] };
+        let patterns = patterns().filter(|_| supported).ok_or_else(|| {
+            SelectedTextError::other("pasteboard pattern detection needs macOS 13")
+        })?;
+        let requested = NSSet::from_id_slice(&patterns.map(|(_, name)| name.copy()));
+        let (done, answer) = mpsc::channel();
+        let handler = RcBlock::new(move |detected: *mut NSSet<NSString>, error: *mut NSError| {
+            let result = match unsafe { (detected.as_ref(), error.as_ref()) } {
+                (Some(detected), _) => Ok(patterns
+                    .iter()
+                    .filter(|(_, name)| unsafe { detected.containsObject(name) })
+                    .map(|(pattern, _)| *pattern)
+                    .collect()),
+                (None, Some(error)) => Err(error.localizedDescription().to_string()),
+                (None, None) => Ok(Vec::new()),
+            };
+            let _ = done.send(result);
+        });
+        unsafe {
+            let _: () = msg_send![
+                pasteboard,
+                detectPatternsForPatterns: &*requested,
+                completionHandler: &*handler
+            ];
+        }
+        answer
+            .recv_timeout(wait)
+            .map_err(|_| {
+                SelectedTextError::other(format_args!(
+                    "no pattern detection result after {:?}",
+                    wait
+                ))
+            })?
+            .map_err(|e| SelectedTextError::other(format_args!("pattern detection failed: {}", e)))
+    }
+}
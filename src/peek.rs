@@ -0,0 +1,145 @@
+// Hosts decide whether to show a "capture" button before the user asks for
+// a capture, and a real capture can type Cmd+C and borrow the clipboard.
+// `peek` walks the same decisions while only reading AX and the pasteboard's
+// change count.
+
+use std::time::Duration;
+
+use objc2_app_kit::NSPasteboard;
+
+use crate::{
+    accessibility, app_copy_method, autocopy, ax, copy_chord_for, copy_chord_timing, error,
+    focused_in_this_process, frontmost_bundle_id, handlers, policy, strategies_for, strategy_cache,
+    CaptureStrategy, CopyMethod, SelectedTextError, SelectedTextOptions, Strategy, SETTLE_TIME,
+};
+
+/// What `get_selected_text_using_ax_then_copy` would do right now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturePeek {
+    pub bundle_id: Option<String>,
+    /// Why the capture would fail before doing anything.
+    pub blocked: Option<SelectedTextError>,
+    /// Whether this process may use Accessibility.
+    pub trusted: bool,
+    /// Handler tried before `strategy`, it may still fall through.
+    pub handler: Option<&'static str>,
+    /// `None` when `blocked`, nothing is read from the app then.
+    pub strategy: Option<CaptureStrategy>,
+    /// Least time the capture spends typing and waiting for the pasteboard,
+    /// zero unless it copies.
+    pub min_duration: Duration,
+}
+
+impl CapturePeek {
+    pub fn touches_clipboard(&self) -> bool {
+        matches!(self.strategy, Some(CaptureStrategy::Copy(_)))
+    }
+
+    /// Whether key events would be posted into the app.
+    pub fn sends_keys(&self) -> bool {
+        matches!(
+            self.strategy,
+            Some(CaptureStrategy::Copy(
+                CopyMethod::KeyChord | CopyMethod::AppleScript
+            ))
+        )
+    }
+}
+
+/// Checks what a capture with the default `CopyMethod` would do and cost,
+/// without posting keys, touching the pasteboard or enabling accessibility
+/// in the app.
+pub fn peek() -> CapturePeek {
+    let bundle_id = frontmost_bundle_id();
+    let trusted = accessibility::is_trusted();
+    let blocked = match policy::ensure_capture_allowed() {
+        Err(e) => Some(e),
+        Ok(()) if focused_in_this_process() => Some(SelectedTextError::SelfFocused),
+        Ok(()) if error::is_strict() && !trusted => Some(SelectedTextError::AccessibilityDenied),
+        Ok(()) => None,
+    };
+    let handler = bundle_id.as_deref().and_then(handlers::handler_name);
+    let strategy = if blocked.is_some() {
+        None
+    } else {
+        Some(strategy(bundle_id.as_deref()))
+    };
+    let min_duration = match strategy {
+        Some(CaptureStrategy::Copy(CopyMethod::KeyChord)) => {
+            let chord = copy_chord_for(bundle_id.as_deref());
+            let timing = copy_chord_timing(bundle_id.as_deref(), None);
+            chord_duration(chord.modifiers.len(), &timing) + SETTLE_TIME
+        }
+        Some(CaptureStrategy::Copy(_)) => SETTLE_TIME,
+        _ => Duration::ZERO,
+    };
+    CapturePeek {
+        bundle_id,
+        blocked,
+        trusted,
+        handler,
+        strategy,
+        min_duration,
+    }
+}
+
+// walks the strategies the way `capture_with_options` does, a handler is
+// reported on its own since it may fall through
+fn strategy(bundle_id: Option<&str>) -> CaptureStrategy {
+    let options = SelectedTextOptions::new();
+    for strategy in strategies_for(bundle_id, &options) {
+        match strategy {
+            Strategy::Handler => {}
+            Strategy::Ax => {
+                let skipped = bundle_id.is_some_and(strategy_cache::would_skip_ax);
+                if !skipped && ax::selected_text().is_ok() {
+                    return CaptureStrategy::Ax;
+                }
+                if ax::selection_is_empty() {
+                    break;
+                }
+            }
+            Strategy::AutoCopy if auto_copied(bundle_id) => return CaptureStrategy::AutoCopy,
+            Strategy::AutoCopy => {}
+            Strategy::Copy => {
+                let method = bundle_id
+                    .and_then(app_copy_method)
+                    .unwrap_or(options.copy_method);
+                return CaptureStrategy::Copy(method);
+            }
+        }
+    }
+    CaptureStrategy::NothingSelected
+}
+
+fn auto_copied(bundle_id: Option<&str>) -> bool {
+    let Some(bundle_id) = bundle_id else {
+        return false;
+    };
+    let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
+    let change_count = unsafe { pasteboard.changeCount() };
+    autocopy::auto_copies(bundle_id) && autocopy::changed_since_last_capture(change_count)
+}
+
+// every modifier is pressed and released with a gap, see `sim_chord`
+fn chord_duration(modifiers: usize, timing: &crate::ChordTiming) -> Duration {
+    timing.gap * (2 * modifiers as u32) + timing.hold + timing.settle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChordTiming;
+
+    #[test]
+    fn test_chord_duration() {
+        assert_eq!(
+            chord_duration(1, &ChordTiming::DEFAULT),
+            Duration::from_millis(80)
+        );
+        assert_eq!(
+            chord_duration(0, &ChordTiming::FAST),
+            Duration::from_millis(15)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+// Some apps hold secrets that must never leave them, whatever a caller asks
+// for, and some deployments only vet a few apps. Every capture entry point
+// checks the frontmost app first and refuses before touching AX or the
+// pasteboard.
+
+use std::sync::RwLock;
+
+use crate::{frontmost_bundle_id, SelectedTextError};
+
+const DEFAULT_DENIED_APPS: &[&str] = &[
+    "com.1password.1password",
+    "com.agilebits.onepassword7",
+    "com.bitwarden.desktop",
+    "com.lastpass.LastPass",
+    "com.dashlane.dashlanephonefinal",
+    "com.apple.keychainaccess",
+    "com.apple.Passwords",
+    "com.moneymoney-app.retail",
+    "com.quicken.Quicken",
+];
+
+static DENIED_APPS: RwLock<Vec<(String, bool)>> = RwLock::new(Vec::new());
+static ALLOWED_APPS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// Refuses every capture in the app with `bundle_id` with
+/// `SelectedTextError::AppDenied`, or lifts that, also for the apps denied by
+/// default (password managers, Keychain Access, banking apps).
+pub fn set_app_denied(bundle_id: &str, denied: bool) {
+    let mut apps = DENIED_APPS.write().unwrap();
+    apps.retain(|(id, _)| !id.eq_ignore_ascii_case(bundle_id));
+    apps.push((bundle_id.to_owned(), denied));
+}
+
+/// Captures only from the apps with these bundle ids, others fail with
+/// `SelectedTextError::AppNotAllowed`. `None`, the default, allows every app that
+/// isn't denied. The deny list still applies to allowed apps.
+pub fn set_allowed_apps(bundle_ids: Option<Vec<String>>) {
+    *ALLOWED_APPS.write().unwrap() = bundle_ids;
+}
+
+pub(crate) fn is_allowed(bundle_id: Option<&str>) -> bool {
+    let allowed = ALLOWED_APPS.read().unwrap().clone();
+    #[cfg(feature = "app-config")]
+    let allowed = allowed.or_else(crate::config::allowed_apps);
+    let Some(allowed) = allowed else {
+        return true;
+    };
+    // an app without a bundle id can't have been vetted
+    bundle_id.is_some_and(|bundle_id| allowed.iter().any(|id| id.eq_ignore_ascii_case(bundle_id)))
+}
+
+pub(crate) fn is_denied(bundle_id: &str) -> bool {
+    let configured = DENIED_APPS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .map(|(_, denied)| *denied);
+    #[cfg(feature = "app-config")]
+    let configured =
+        configured.or_else(|| crate::config::app_setting(bundle_id, |settings| settings.denied));
+    configured.unwrap_or_else(|| {
+        DEFAULT_DENIED_APPS
+            .iter()
+            .any(|id| id.eq_ignore_ascii_case(bundle_id))
+    })
+}
+
+/// Fails when the frontmost app may not be captured from.
+pub(crate) fn ensure_capture_allowed() -> Result<(), SelectedTextError> {
+    // the config file can change both lists
+    #[cfg(feature = "app-config")]
+    crate::config::reload_if_changed();
+    if crate::window::screen_is_locked() {
+        return Err(SelectedTextError::ScreenLocked);
+    }
+    let bundle_id = frontmost_bundle_id();
+    if let Some(bundle_id) = bundle_id.as_ref().filter(|id| is_denied(id)) {
+        return Err(SelectedTextError::AppDenied {
+            bundle_id: bundle_id.clone(),
+        });
+    }
+    if !is_allowed(bundle_id.as_deref()) {
+        return Err(SelectedTextError::AppNotAllowed { bundle_id });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denied_apps() {
+        assert!(is_denied("com.1password.1Password"));
+        assert!(!is_denied("com.apple.Notes"));
+        set_app_denied("com.example.Vault", true);
+        assert!(is_denied("com.example.vault"));
+        set_app_denied("com.bitwarden.desktop", false);
+        assert!(!is_denied("com.bitwarden.desktop"));
+    }
+
+    #[test]
+    fn test_allowed_apps() {
+        assert!(is_allowed(Some("com.apple.Notes")));
+        assert!(is_allowed(None));
+        set_allowed_apps(Some(vec!["com.apple.Notes".to_owned()]));
+        assert!(is_allowed(Some("com.apple.notes")));
+        assert!(!is_allowed(Some("com.apple.Safari")));
+        assert!(!is_allowed(None));
+        set_allowed_apps(None);
+        assert!(is_allowed(Some("com.apple.Safari")));
+    }
+}
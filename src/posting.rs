@@ -0,0 +1,78 @@
+// Managed Macs sometimes filter synthetic input by where it enters the event
+// stream, so both knobs CGEvent posting has are configurable.
+
+use std::sync::RwLock;
+
+use core_graphics::{
+    event::{CGEvent, CGEventTapLocation, CGKeyCode, EventField},
+    event_source::{CGEventSource, CGEventSourceStateID},
+};
+
+use crate::{delivery, SelectedTextError};
+
+/// Where synthetic key events are inserted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PostTap {
+    /// Where hardware events enter, before every other tap.
+    #[default]
+    Hid,
+    /// Where events enter the login session, after HID-level taps.
+    Session,
+}
+
+/// Which key state the events are combined with, `HidSystem` makes the
+/// modifiers we press visible to every later event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceState {
+    #[default]
+    HidSystem,
+    CombinedSession,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventPosting {
+    pub tap: PostTap,
+    pub source_state: SourceState,
+}
+
+static EVENT_POSTING: RwLock<EventPosting> = RwLock::new(EventPosting {
+    tap: PostTap::Hid,
+    source_state: SourceState::HidSystem,
+});
+
+/// Changes how every synthetic key event is posted.
+pub fn set_event_posting(posting: EventPosting) {
+    *EVENT_POSTING.write().unwrap() = posting;
+}
+
+pub fn event_posting() -> EventPosting {
+    *EVENT_POSTING.read().unwrap()
+}
+
+pub(crate) fn post_key(key: CGKeyCode, key_down: bool) -> anyhow::Result<()> {
+    let posting = event_posting();
+    let state = match posting.source_state {
+        SourceState::HidSystem => CGEventSourceStateID::HIDSystemState,
+        SourceState::CombinedSession => CGEventSourceStateID::CombinedSessionState,
+    };
+    let source = CGEventSource::new(state).map_err(|_| {
+        SelectedTextError::KeySimulationFailed("couldn't create a CGEventSource".to_owned())
+    })?;
+    if let Ok(cg_event) = CGEvent::new_keyboard_event(source, key, key_down) {
+        cg_event.set_integer_value_field(
+            EventField::EVENT_SOURCE_USER_DATA,
+            delivery::SYNTHETIC_EVENT_TAG,
+        );
+        cg_event.post(match posting.tap {
+            PostTap::Hid => CGEventTapLocation::HID,
+            PostTap::Session => CGEventTapLocation::Session,
+        });
+        Ok(())
+    } else {
+        Err(SelectedTextError::KeySimulationFailed(format!(
+            "couldn't create the event for key {}",
+            key
+        ))
+        .into())
+    }
+}
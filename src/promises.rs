@@ -0,0 +1,205 @@
+// Browsers and Photos can copy promised files instead of files: the data
+// only exists once we ask the source app to write it somewhere. Without
+// resolving the promises such a copy reads as an empty selection.
+
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::diagnostics::warn;
+use anyhow::anyhow;
+use block2::RcBlock;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2::{msg_send_id, ClassType};
+use objc2_app_kit::{NSFilePromiseReceiver, NSPasteboard};
+use objc2_foundation::{NSArray, NSDictionary, NSError, NSOperationQueue, NSString, NSURL};
+
+const DEFAULT_SIZE_LIMIT: u64 = 100 * 1024 * 1024;
+
+/// How long the source apps get to write all promised files.
+const WAIT: Duration = Duration::from_secs(10);
+
+static SIZE_LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_SIZE_LIMIT);
+
+/// Largest total size in bytes of the promised files a capture keeps. Sizes
+/// are only known once a file is written, files past the limit are deleted.
+pub fn set_file_promise_limit(max_bytes: u64) {
+    SIZE_LIMIT.store(max_bytes, Ordering::Relaxed);
+}
+
+/// The promised files of a copy. They're resolved with `resolve` once the
+/// user's clipboard is back and the capture lock released, the source apps
+/// can take seconds to write them.
+pub(crate) struct FilePromises(Vec<Retained<NSFilePromiseReceiver>>);
+
+/// The file promises on `pasteboard`, `None` when it holds none.
+pub(crate) fn file_promises(pasteboard: &NSPasteboard) -> Option<FilePromises> {
+    if !has_file_promises(pasteboard) {
+        return None;
+    }
+    let receivers = promise_receivers(pasteboard);
+    (!receivers.is_empty()).then_some(FilePromises(receivers))
+}
+
+fn has_file_promises(pasteboard: &NSPasteboard) -> bool {
+    let Some(types) = (unsafe { pasteboard.types() }) else {
+        return false;
+    };
+    let promise_types = unsafe { NSFilePromiseReceiver::readableDraggedTypes() };
+    types
+        .iter()
+        .any(|t| unsafe { promise_types.containsObject(t) })
+}
+
+impl FilePromises {
+    /// Has the source apps write the promised files into a new temporary
+    /// directory, and returns their paths. The files are the caller's to
+    /// delete.
+    pub(crate) fn resolve(self) -> anyhow::Result<Vec<PathBuf>> {
+        resolve_file_promises(&self.0)
+    }
+}
+
+fn resolve_file_promises(
+    receivers: &[Retained<NSFilePromiseReceiver>],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = destination_dir()?;
+    let dir_url = unsafe {
+        NSURL::fileURLWithPath_isDirectory(
+            &NSString::from_str(
+                dir.to_str()
+                    .ok_or_else(|| anyhow!("temp dir isn't UTF-8"))?,
+            ),
+            true,
+        )
+    };
+    let expected: usize = receivers
+        .iter()
+        .map(|receiver| unsafe { receiver.fileNames() }.count())
+        .sum();
+    let (written, received) = mpsc::channel();
+    let reader = RcBlock::new(move |url: NonNull<NSURL>, error: *mut NSError| {
+        let path = match unsafe { error.as_ref() } {
+            Some(error) => Err(error.localizedDescription().to_string()),
+            None => unsafe { url.as_ref().path() }
+                .map(|path| PathBuf::from(path.to_string()))
+                .ok_or_else(|| "no file path".to_owned()),
+        };
+        let _ = written.send(path);
+    });
+    // a queue of its own, the main run loop may not be running
+    let queue = unsafe { NSOperationQueue::new() };
+    for receiver in receivers {
+        unsafe {
+            receiver.receivePromisedFilesAtDestination_options_operationQueue_reader(
+                &dir_url,
+                &NSDictionary::new(),
+                &queue,
+                &reader,
+            )
+        };
+    }
+    let deadline = Instant::now() + WAIT;
+    let mut paths = Vec::new();
+    for _ in 0..expected {
+        match received.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Ok(path)) => paths.push(path),
+            Ok(Err(e)) => warn!("promised file wasn't written: {}", e),
+            Err(_) => {
+                warn!("gave up waiting for promised files after {:?}", WAIT);
+                break;
+            }
+        }
+    }
+    let paths = keep_within(paths, SIZE_LIMIT.load(Ordering::Relaxed));
+    if paths.is_empty() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    Ok(paths)
+}
+
+fn promise_receivers(pasteboard: &NSPasteboard) -> Vec<Retained<NSFilePromiseReceiver>> {
+    // classes are objects too, but `readObjectsForClasses` isn't bound yet
+    let class = NSFilePromiseReceiver::class() as *const AnyClass as *const AnyObject;
+    let classes: Retained<NSArray> =
+        unsafe { msg_send_id![NSArray::<AnyObject>::class(), arrayWithObject: class] };
+    let objects: Option<Retained<NSArray<NSFilePromiseReceiver>>> = unsafe {
+        msg_send_id![
+            pasteboard,
+            readObjectsForClasses: &*classes,
+            options: std::ptr::null::<NSDictionary>()
+        ]
+    };
+    objects
+        .map(|objects| objects.to_vec_retained())
+        .unwrap_or_default()
+}
+
+fn destination_dir() -> anyhow::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos());
+    let dir = std::env::temp_dir().join(format!(
+        "selected-text-promises-{}-{}",
+        std::process::id(),
+        nanos
+    ));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+// deletes the files that don't fit, in the order they were written
+fn keep_within(paths: Vec<PathBuf>, max_bytes: u64) -> Vec<PathBuf> {
+    let mut total = 0u64;
+    let mut kept = Vec::new();
+    for path in paths {
+        let size = size_of(&path);
+        if total.saturating_add(size) > max_bytes {
+            warn!(
+                "dropping promised file {}, over the size limit",
+                path.display()
+            );
+            let _ = std::fs::remove_dir_all(&path).or_else(|_| std::fs::remove_file(&path));
+            continue;
+        }
+        total += size;
+        kept.push(path);
+    }
+    kept
+}
+
+// promised "files" are sometimes packages or folders
+fn size_of(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| size_of(&entry.path()))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_within() {
+        let dir = destination_dir().unwrap();
+        let small = dir.join("small.txt");
+        let large = dir.join("large.bin");
+        std::fs::write(&small, [0; 10]).unwrap();
+        std::fs::write(&large, [0; 100]).unwrap();
+        assert_eq!(keep_within(vec![small.clone(), large.clone()], 50), [small]);
+        assert!(!large.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,132 @@
+// Capturing used to mean calling free functions wired to AppKit and AX. A
+// host app holding a `SelectedTextProvider` instead can be handed another
+// platform's backend, or a mock in its tests.
+
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crate::{
+    active_window, get_selected_files_of, get_selected_text_with, ActiveApp, CopyMethod,
+    SelectedTextOptions,
+};
+use crate::{ActiveWindow, Result, SelectedText, SelectedTextError};
+
+/// A way to read the user's selection. Only `get_selected_text` is
+/// required.
+pub trait SelectedTextProvider: Send + Sync {
+    /// What the user is looking at, `Unknown` when the backend can't tell.
+    fn active_window(&self) -> ActiveWindow {
+        ActiveWindow::Unknown
+    }
+
+    /// The selected text of the focused element.
+    fn get_selected_text(&self) -> Result<SelectedText>;
+
+    /// The files selected in the file manager or on the desktop.
+    fn get_selected_files(&self) -> Result<SelectedText> {
+        Err(SelectedTextError::other(
+            "this provider can't read selected files",
+        ))
+    }
+
+    /// Files when the file manager or the desktop is in front, text
+    /// otherwise.
+    fn get_selection(&self) -> Result<SelectedText> {
+        match self.active_window() {
+            ActiveWindow::ScreenLocked => Err(SelectedTextError::ScreenLocked),
+            window if window.app().is_some_and(|app| app.selects_files()) => {
+                self.get_selected_files()
+            }
+            _ => self.get_selected_text(),
+        }
+    }
+}
+
+/// AX first, then copying with `copy_method` and waiting up to
+/// `pasteboard_wait_timeout` milliseconds for the pasteboard.
+#[cfg(target_os = "macos")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacOsProvider {
+    pub copy_method: CopyMethod,
+    pub pasteboard_wait_timeout: u64,
+}
+
+#[cfg(target_os = "macos")]
+impl Default for MacOsProvider {
+    fn default() -> Self {
+        MacOsProvider {
+            copy_method: CopyMethod::default(),
+            pasteboard_wait_timeout: 90,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl SelectedTextProvider for MacOsProvider {
+    fn active_window(&self) -> ActiveWindow {
+        active_window()
+    }
+
+    fn get_selected_text(&self) -> Result<SelectedText> {
+        get_selected_text_with(
+            &SelectedTextOptions::new()
+                .copy_method(self.copy_method)
+                .pasteboard_timeout(Duration::from_millis(self.pasteboard_wait_timeout)),
+        )
+    }
+
+    fn get_selected_files(&self) -> Result<SelectedText> {
+        match self.active_window() {
+            ActiveWindow::ScreenLocked => Err(SelectedTextError::ScreenLocked),
+            window => get_selected_files_of(&window.app().unwrap_or(ActiveApp::Desktop)),
+        }
+    }
+}
+
+/// The provider for the platform this was built for.
+pub fn default_provider() -> Box<dyn SelectedTextProvider> {
+    #[cfg(target_os = "windows")]
+    return Box::new(crate::WindowsProvider::default());
+    #[cfg(target_os = "linux")]
+    return Box::new(crate::LinuxProvider::default());
+    #[cfg(target_os = "macos")]
+    Box::new(MacOsProvider::default())
+}
+
+/// The selection of the frontmost app in one call: the selected files when
+/// the file manager or the desktop is in front, the selected text otherwise.
+/// Goes through `default_provider` and applies the clean-ups set with
+/// `set_default_normalize`, see `get_selected_text_with` to configure the
+/// capture.
+pub fn get_selected_text() -> Result<SelectedText> {
+    let selected_text = default_provider().get_selection()?;
+    // on macOS the provider captures through `get_selected_text_with`, which
+    // already applied them
+    #[cfg(not(target_os = "macos"))]
+    let selected_text = selected_text.normalized(&crate::normalize::default_normalize());
+    Ok(selected_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fake;
+
+    impl SelectedTextProvider for Fake {
+        fn get_selected_text(&self) -> Result<SelectedText> {
+            Ok(SelectedText::from_text(
+                "Fake".to_owned(),
+                "hello".to_owned(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_provider_defaults() {
+        let provider: Box<dyn SelectedTextProvider> = Box::new(Fake);
+        assert_eq!(provider.get_selection().unwrap().text, ["hello"]);
+        assert!(provider.get_selected_files().is_err());
+    }
+}
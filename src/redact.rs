@@ -0,0 +1,177 @@
+use std::sync::RwLock;
+
+use regex::Regex;
+
+use crate::{Result, SelectedTextError};
+
+struct Redaction {
+    name: String,
+    regex: Regex,
+    // rejects matches that only look like a secret, e.g. digit runs failing the Luhn check
+    validate: Option<fn(&str) -> bool>,
+}
+
+static REDACTIONS: RwLock<Vec<Redaction>> = RwLock::new(Vec::new());
+
+/// Reports that matches of the redaction pattern `name` were replaced.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Redacted {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Registers a pattern whose matches are replaced with `[REDACTED:<name>]` in
+/// every capture, before it is returned or logged.
+pub fn add_redaction(name: &str, pattern: &str) -> Result<()> {
+    let redaction = Redaction {
+        name: name.to_owned(),
+        regex: Regex::new(pattern).map_err(SelectedTextError::other)?,
+        validate: None,
+    };
+    REDACTIONS.write().unwrap().push(redaction);
+    Ok(())
+}
+
+/// Registers the built-in `credit-card`, `api-key` and `email` patterns.
+pub fn add_default_redactions() {
+    let mut redactions = REDACTIONS.write().unwrap();
+    redactions.extend(default_redactions());
+}
+
+pub fn clear_redactions() {
+    REDACTIONS.write().unwrap().clear();
+}
+
+/// Applies the registered redactions to `text`.
+pub fn redact(text: &str) -> (String, Vec<Redacted>) {
+    let redactions = REDACTIONS.read().unwrap();
+    redact_with(text, &redactions)
+}
+
+fn default_redactions() -> Vec<Redaction> {
+    let redaction = |name: &str, pattern: &str, validate| Redaction {
+        name: name.to_owned(),
+        regex: Regex::new(pattern).expect("built-in redaction pattern"),
+        validate,
+    };
+    vec![
+        redaction(
+            "credit-card",
+            r"\b\d(?:[ -]?\d){12,18}\b",
+            Some(passes_luhn as fn(&str) -> bool),
+        ),
+        redaction(
+            "api-key",
+            r"\b(?:sk-[A-Za-z0-9_-]{20,}|AKIA[0-9A-Z]{16}|gh[pousr]_[A-Za-z0-9]{36,}|xox[abprs]-[A-Za-z0-9-]{10,}|AIza[0-9A-Za-z_-]{35})\b",
+            None,
+        ),
+        redaction(
+            "email",
+            r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+            None,
+        ),
+    ]
+}
+
+fn redact_with(text: &str, redactions: &[Redaction]) -> (String, Vec<Redacted>) {
+    let mut text = text.to_owned();
+    let mut found = Vec::new();
+    for redaction in redactions {
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        let mut count = 0;
+        for m in redaction.regex.find_iter(&text) {
+            if redaction
+                .validate
+                .is_some_and(|validate| !validate(m.as_str()))
+            {
+                continue;
+            }
+            redacted.push_str(&text[last..m.start()]);
+            redacted.push_str(&format!("[REDACTED:{}]", redaction.name));
+            last = m.end();
+            count += 1;
+        }
+        if count > 0 {
+            redacted.push_str(&text[last..]);
+            text = redacted;
+            found.push(Redacted {
+                name: redaction.name.clone(),
+                count,
+            });
+        }
+    }
+    (text, found)
+}
+
+// matches in different text (the selection and the text around it, or a
+// second pass over the normalized text) add up, unlike the flavors of one copy
+pub(crate) fn add_redactions(redactions: &mut Vec<Redacted>, found: Vec<Redacted>) {
+    for found in found {
+        match redactions.iter_mut().find(|r| r.name == found.name) {
+            Some(redacted) => redacted.count += found.count,
+            None => redactions.push(found),
+        }
+    }
+}
+
+fn passes_luhn(candidate: &str) -> bool {
+    let digits = candidate
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect::<Vec<_>>();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            0 => digit,
+            _ if digit * 2 > 9 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_redactions() {
+        let text = "card 4111 1111 1111 1111, order 1234 5678 9012 3456, \
+                    key sk-abcdefghijklmnopqrstuvwx, mail me@example.com";
+        let (redacted, found) = redact_with(text, &default_redactions());
+        assert_eq!(
+            redacted,
+            "card [REDACTED:credit-card], order 1234 5678 9012 3456, \
+             key [REDACTED:api-key], mail [REDACTED:email]"
+        );
+        assert_eq!(
+            found.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+            ["credit-card", "api-key", "email"]
+        );
+    }
+
+    #[test]
+    fn test_redact_without_patterns() {
+        assert_eq!(
+            redact_with("nothing here", &[]),
+            ("nothing here".into(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_add_redactions() {
+        let redacted = |name: &str, count| Redacted {
+            name: name.to_owned(),
+            count,
+        };
+        let mut redactions = vec![redacted("email", 1)];
+        add_redactions(
+            &mut redactions,
+            vec![redacted("email", 2), redacted("api-key", 1)],
+        );
+        assert_eq!(redactions, [redacted("email", 3), redacted("api-key", 1)]);
+    }
+}
@@ -0,0 +1,365 @@
+// Minimal RTF reader for the `public.rtf` pasteboard flavor. It only tracks
+// the character formatting consumers care about (bold, italic, hyperlinks)
+// and skips font tables, stylesheets, pictures and other destinations.
+
+use crate::{decode::cp1252_to_char, StyledRun};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Destination {
+    Text,
+    FieldInstruction,
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+struct GroupState {
+    bold: bool,
+    italic: bool,
+    link: Option<String>,
+    // `\fsN` is in half-points
+    font_size: Option<f64>,
+    destination: Destination,
+    // number of fallback characters following a `\uN` escape
+    unicode_skip: usize,
+}
+
+impl Default for GroupState {
+    fn default() -> Self {
+        Self {
+            bold: false,
+            italic: false,
+            link: None,
+            font_size: None,
+            destination: Destination::Text,
+            unicode_skip: 1,
+        }
+    }
+}
+
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "expandedcolortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "header",
+    "headerl",
+    "headerr",
+    "footer",
+    "footerl",
+    "footerr",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "xmlnstbl",
+    "themedata",
+    "colorschememapping",
+    "latentstyles",
+    "datastore",
+];
+
+struct Parser {
+    state: GroupState,
+    stack: Vec<GroupState>,
+    runs: Vec<StyledRun>,
+    field_instruction: String,
+    field_link: Option<String>,
+    chars_to_skip: usize,
+    pending_high_surrogate: Option<u16>,
+}
+
+/// Parses RTF data (e.g. the `public.rtf` pasteboard flavor) into runs of
+/// uniformly formatted text.
+pub fn parse_rtf(rtf: &[u8]) -> Vec<StyledRun> {
+    let mut parser = Parser {
+        state: GroupState::default(),
+        stack: Vec::new(),
+        runs: Vec::new(),
+        field_instruction: String::new(),
+        field_link: None,
+        chars_to_skip: 0,
+        pending_high_surrogate: None,
+    };
+    parser.parse(rtf);
+    parser.runs.retain(|run| !run.text.is_empty());
+    parser.runs
+}
+
+impl Parser {
+    fn parse(&mut self, rtf: &[u8]) {
+        let mut i = 0;
+        while i < rtf.len() {
+            match rtf[i] {
+                b'{' => {
+                    self.stack.push(self.state.clone());
+                    i += 1;
+                }
+                b'}' => {
+                    self.end_group();
+                    i += 1;
+                }
+                b'\\' => i = self.control(rtf, i + 1),
+                b'\r' | b'\n' => i += 1,
+                byte => {
+                    self.push_char(char::from(byte));
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    fn end_group(&mut self) {
+        let closed = self.state.destination;
+        if let Some(state) = self.stack.pop() {
+            self.state = state;
+        }
+        // the instruction may itself be split into nested groups
+        if closed == Destination::FieldInstruction
+            && self.state.destination != Destination::FieldInstruction
+        {
+            self.field_link = hyperlink_target(&self.field_instruction);
+            self.field_instruction.clear();
+        }
+    }
+
+    // parses the control sequence following a backslash at `rtf[start - 1]`, returns the new position
+    fn control(&mut self, rtf: &[u8], start: usize) -> usize {
+        let Some(&first) = rtf.get(start) else {
+            return start;
+        };
+        if !first.is_ascii_alphabetic() {
+            return self.control_symbol(rtf, start);
+        }
+
+        let mut end = start;
+        while end < rtf.len() && rtf[end].is_ascii_alphabetic() {
+            end += 1;
+        }
+        let word = std::str::from_utf8(&rtf[start..end]).unwrap_or_default();
+        let param_start = end;
+        if end < rtf.len() && rtf[end] == b'-' {
+            end += 1;
+        }
+        while end < rtf.len() && rtf[end].is_ascii_digit() {
+            end += 1;
+        }
+        let param = std::str::from_utf8(&rtf[param_start..end])
+            .ok()
+            .and_then(|p| p.parse::<i32>().ok());
+        // a single space delimits the control word and is not part of the text
+        if end < rtf.len() && rtf[end] == b' ' {
+            end += 1;
+        }
+        self.control_word(word, param);
+        end
+    }
+
+    fn control_symbol(&mut self, rtf: &[u8], start: usize) -> usize {
+        match rtf[start] {
+            b'\'' => {
+                let byte = rtf
+                    .get(start + 1..start + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = byte {
+                    self.push_char(cp1252_to_char(byte));
+                    return start + 3;
+                }
+                start + 1
+            }
+            b'*' => {
+                // `{\*\dest ...}` groups are optional and skipped unless we understand them
+                let is_field_instruction = rtf[start + 1..].starts_with(b"\\fldinst");
+                self.state.destination = if is_field_instruction {
+                    Destination::FieldInstruction
+                } else {
+                    Destination::Skip
+                };
+                start + 1
+            }
+            b'~' => {
+                self.push_char('\u{a0}');
+                start + 1
+            }
+            b'_' => {
+                self.push_char('\u{2011}');
+                start + 1
+            }
+            // optional hyphen
+            b'-' => start + 1,
+            b'\r' | b'\n' => {
+                self.push_char('\n');
+                start + 1
+            }
+            symbol => {
+                self.push_char(char::from(symbol));
+                start + 1
+            }
+        }
+    }
+
+    fn control_word(&mut self, word: &str, param: Option<i32>) {
+        let enabled = param != Some(0);
+        match word {
+            "b" => self.state.bold = enabled,
+            "fs" => self.state.font_size = param.map(|half_points| f64::from(half_points) / 2.0),
+            "i" => self.state.italic = enabled,
+            "plain" => {
+                self.state.bold = false;
+                self.state.italic = false;
+            }
+            "par" | "line" | "sect" | "page" | "row" => self.push_char('\n'),
+            "tab" | "cell" => self.push_char('\t'),
+            "emdash" => self.push_char('—'),
+            "endash" => self.push_char('–'),
+            "bullet" => self.push_char('•'),
+            "lquote" => self.push_char('‘'),
+            "rquote" => self.push_char('’'),
+            "ldblquote" => self.push_char('“'),
+            "rdblquote" => self.push_char('”'),
+            "uc" => self.state.unicode_skip = param.unwrap_or(1).max(0) as usize,
+            "u" => {
+                if let Some(code) = param {
+                    // parameters are signed 16-bit values
+                    self.push_utf16(code.rem_euclid(0x10000) as u16);
+                    self.chars_to_skip = self.state.unicode_skip;
+                }
+            }
+            "fldinst" => self.state.destination = Destination::FieldInstruction,
+            "fldrslt" => self.state.link = self.field_link.clone(),
+            "field" => self.field_link = None,
+            _ if SKIPPED_DESTINATIONS.contains(&word) => {
+                self.state.destination = Destination::Skip;
+            }
+            _ => {}
+        }
+    }
+
+    fn push_utf16(&mut self, unit: u16) {
+        match unit {
+            0xD800..=0xDBFF => self.pending_high_surrogate = Some(unit),
+            0xDC00..=0xDFFF => {
+                if let Some(high) = self.pending_high_surrogate.take() {
+                    if let Some(Ok(c)) = char::decode_utf16([high, unit]).next() {
+                        self.push_text_char(c);
+                    }
+                }
+            }
+            _ => {
+                if let Some(c) = char::from_u32(u32::from(unit)) {
+                    self.push_text_char(c);
+                }
+            }
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        if self.chars_to_skip > 0 {
+            self.chars_to_skip -= 1;
+            return;
+        }
+        self.push_text_char(c);
+    }
+
+    fn push_text_char(&mut self, c: char) {
+        match self.state.destination {
+            Destination::Skip => {}
+            Destination::FieldInstruction => self.field_instruction.push(c),
+            Destination::Text => {
+                let state = &self.state;
+                match self.runs.last_mut() {
+                    Some(run)
+                        if run.bold == state.bold
+                            && run.italic == state.italic
+                            && run.link == state.link
+                            && run.font_size == state.font_size =>
+                    {
+                        run.text.push(c)
+                    }
+                    _ => self.runs.push(StyledRun {
+                        text: c.to_string(),
+                        bold: state.bold,
+                        italic: state.italic,
+                        link: state.link.clone(),
+                        font: None,
+                        font_size: state.font_size,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+// `HYPERLINK "https://example.com"` or `HYPERLINK https://example.com`
+fn hyperlink_target(instruction: &str) -> Option<String> {
+    let rest = instruction.trim().strip_prefix("HYPERLINK")?.trim();
+    let target = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    (!target.is_empty()).then(|| target.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str, bold: bool, italic: bool, link: Option<&str>) -> StyledRun {
+        StyledRun {
+            text: text.to_owned(),
+            bold,
+            italic,
+            link: link.map(str::to_owned),
+            font: None,
+            font_size: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_rtf_styles() {
+        let rtf = br"{\rtf1\ansi\ansicpg1252\cocoartf2761
+{\fonttbl\f0\fswiss\fcharset0 Helvetica;}
+{\colortbl;\red255\green255\blue255;}
+\f0\fs24 \cf0 Plain \b bold\b0  and \i italic\i0 .\
+Next}";
+        let expected = vec![
+            run("Plain ", false, false, None),
+            run("bold", true, false, None),
+            run(" and ", false, false, None),
+            run("italic", false, true, None),
+            run(".\nNext", false, false, None),
+        ];
+        let expected = expected
+            .into_iter()
+            .map(|run| StyledRun {
+                font_size: Some(12.0),
+                ..run
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(parse_rtf(rtf), expected);
+    }
+
+    #[test]
+    fn test_parse_rtf_hyperlink() {
+        let rtf = br#"{\rtf1 See {\field{\*\fldinst{HYPERLINK "https://example.com"}}{\fldrslt the site}} now}"#;
+        assert_eq!(
+            parse_rtf(rtf),
+            vec![
+                run("See ", false, false, None),
+                run("the site", false, false, Some("https://example.com")),
+                run(" now", false, false, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rtf_escapes() {
+        let rtf = br"{\rtf1 caf\'e9 \'93q\'94 \uc0\u8364  \uc1\u-10179?\u-8704? \{x\}}";
+        assert_eq!(
+            parse_rtf(rtf),
+            vec![run("café “q” € 😀 {x}", false, false, None)]
+        );
+    }
+}
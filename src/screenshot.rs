@@ -0,0 +1,96 @@
+use std::ffi::c_void;
+
+use anyhow::bail;
+use core_foundation::{
+    base::{CFRelease, CFTypeRef, TCFType},
+    data::{CFData, CFDataCreateMutable, CFDataRef, CFMutableDataRef},
+    dictionary::CFDictionaryRef,
+    string::{CFString, CFStringRef},
+};
+use core_graphics::{
+    geometry::CGRect,
+    window::{
+        kCGNullWindowID, kCGWindowImageBestResolution, kCGWindowListOptionOnScreenOnly, CGWindowID,
+        CGWindowImageOption, CGWindowListOption,
+    },
+};
+
+use crate::{get_selection_bounds, Result, SelectedTextError};
+
+type CGImageRef = *const c_void;
+type CGImageDestinationRef = *mut c_void;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGWindowListCreateImage(
+        screen_bounds: CGRect,
+        list_option: CGWindowListOption,
+        window_id: CGWindowID,
+        image_option: CGWindowImageOption,
+    ) -> CGImageRef;
+}
+
+#[link(name = "ImageIO", kind = "framework")]
+extern "C" {
+    fn CGImageDestinationCreateWithData(
+        data: CFMutableDataRef,
+        type_identifier: CFStringRef,
+        count: usize,
+        options: CFDictionaryRef,
+    ) -> CGImageDestinationRef;
+    fn CGImageDestinationAddImage(
+        destination: CGImageDestinationRef,
+        image: CGImageRef,
+        properties: CFDictionaryRef,
+    );
+    fn CGImageDestinationFinalize(destination: CGImageDestinationRef) -> bool;
+}
+
+/// Screenshot of exactly the selected region as PNG data, at the display's
+/// native resolution. Needs the Screen Recording permission, without it macOS
+/// returns an image of the desktop wallpaper only.
+pub fn capture_selection_image() -> Result<Vec<u8>> {
+    let bounds = get_selection_bounds()?;
+    if bounds.size.width <= 0.0 || bounds.size.height <= 0.0 {
+        return Err(SelectedTextError::NoSelection);
+    }
+    let image = unsafe {
+        CGWindowListCreateImage(
+            bounds,
+            kCGWindowListOptionOnScreenOnly,
+            kCGNullWindowID,
+            kCGWindowImageBestResolution,
+        )
+    };
+    if image.is_null() {
+        return Err(SelectedTextError::other("Failed to capture the screen"));
+    }
+    let png = encode_png(image);
+    unsafe { CFRelease(image as CFTypeRef) };
+    Ok(png?)
+}
+
+fn encode_png(image: CGImageRef) -> anyhow::Result<Vec<u8>> {
+    let data = unsafe {
+        CFData::wrap_under_create_rule(CFDataCreateMutable(std::ptr::null(), 0) as CFDataRef)
+    };
+    let png_type = CFString::from_static_string("public.png");
+    unsafe {
+        let destination = CGImageDestinationCreateWithData(
+            data.as_concrete_TypeRef() as CFMutableDataRef,
+            png_type.as_concrete_TypeRef(),
+            1,
+            std::ptr::null(),
+        );
+        if destination.is_null() {
+            bail!("Failed to create a PNG destination");
+        }
+        CGImageDestinationAddImage(destination, image, std::ptr::null());
+        let finalized = CGImageDestinationFinalize(destination);
+        CFRelease(destination as CFTypeRef);
+        if !finalized {
+            bail!("Failed to encode the screenshot as PNG");
+        }
+    }
+    Ok(data.bytes().to_vec())
+}
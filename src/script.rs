@@ -0,0 +1,158 @@
+// Block-range approximations of the Unicode Script property, good enough to
+// pick a font or mirror a popup without pulling in the full UCD tables.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum TextDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Syriac,
+    Thaana,
+    Devanagari,
+    Bengali,
+    Gurmukhi,
+    Gujarati,
+    Tamil,
+    Telugu,
+    Kannada,
+    Malayalam,
+    Thai,
+    Lao,
+    Tibetan,
+    Georgian,
+    Ethiopic,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Han,
+    Other,
+}
+
+impl Script {
+    pub fn direction(self) -> TextDirection {
+        match self {
+            Script::Hebrew | Script::Arabic | Script::Syriac | Script::Thaana => {
+                TextDirection::RightToLeft
+            }
+            _ => TextDirection::LeftToRight,
+        }
+    }
+}
+
+/// Script of a letter, `None` for digits, punctuation, symbols and whitespace.
+pub fn script_of(c: char) -> Option<Script> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    let script = match c {
+        'A'..='Z' | 'a'..='z' | '\u{00c0}'..='\u{024f}' | '\u{1e00}'..='\u{1eff}' => Script::Latin,
+        '\u{0370}'..='\u{03ff}' | '\u{1f00}'..='\u{1fff}' => Script::Greek,
+        '\u{0400}'..='\u{052f}' => Script::Cyrillic,
+        '\u{0530}'..='\u{058f}' => Script::Armenian,
+        '\u{0590}'..='\u{05ff}' | '\u{fb1d}'..='\u{fb4f}' => Script::Hebrew,
+        '\u{0600}'..='\u{06ff}'
+        | '\u{0750}'..='\u{077f}'
+        | '\u{08a0}'..='\u{08ff}'
+        | '\u{fb50}'..='\u{fdff}'
+        | '\u{fe70}'..='\u{feff}' => Script::Arabic,
+        '\u{0700}'..='\u{074f}' => Script::Syriac,
+        '\u{0780}'..='\u{07bf}' => Script::Thaana,
+        '\u{0900}'..='\u{097f}' => Script::Devanagari,
+        '\u{0980}'..='\u{09ff}' => Script::Bengali,
+        '\u{0a00}'..='\u{0a7f}' => Script::Gurmukhi,
+        '\u{0a80}'..='\u{0aff}' => Script::Gujarati,
+        '\u{0b80}'..='\u{0bff}' => Script::Tamil,
+        '\u{0c00}'..='\u{0c7f}' => Script::Telugu,
+        '\u{0c80}'..='\u{0cff}' => Script::Kannada,
+        '\u{0d00}'..='\u{0d7f}' => Script::Malayalam,
+        '\u{0e00}'..='\u{0e7f}' => Script::Thai,
+        '\u{0e80}'..='\u{0eff}' => Script::Lao,
+        '\u{0f00}'..='\u{0fff}' => Script::Tibetan,
+        '\u{10a0}'..='\u{10ff}' | '\u{1c90}'..='\u{1cbf}' => Script::Georgian,
+        '\u{1200}'..='\u{139f}' => Script::Ethiopic,
+        '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' | '\u{ac00}'..='\u{d7af}' => {
+            Script::Hangul
+        }
+        '\u{3040}'..='\u{309f}' => Script::Hiragana,
+        '\u{30a0}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' | '\u{ff66}'..='\u{ff9f}' => {
+            Script::Katakana
+        }
+        '\u{2e80}'..='\u{2fdf}'
+        | '\u{3005}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{3134f}' => Script::Han,
+        _ => Script::Other,
+    };
+    Some(script)
+}
+
+/// The script most letters of `text` are written in.
+pub fn dominant_script(text: &str) -> Option<Script> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for script in text.chars().filter_map(script_of) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    // ties go to the script that appeared first
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(script, _)| *script)
+}
+
+/// Direction of the majority of letters, `None` when there are no letters.
+pub fn text_direction(text: &str) -> Option<TextDirection> {
+    let (mut left_to_right, mut right_to_left) = (0, 0);
+    for script in text.chars().filter_map(script_of) {
+        match script.direction() {
+            TextDirection::LeftToRight => left_to_right += 1,
+            TextDirection::RightToLeft => right_to_left += 1,
+        }
+    }
+    match (left_to_right, right_to_left) {
+        (0, 0) => None,
+        (ltr, rtl) if rtl > ltr => Some(TextDirection::RightToLeft),
+        _ => Some(TextDirection::LeftToRight),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_and_direction() {
+        let cases = [
+            ("Hello, world", Script::Latin, TextDirection::LeftToRight),
+            ("שלום עולם", Script::Hebrew, TextDirection::RightToLeft),
+            (
+                "مرحبا بالعالم (hello)",
+                Script::Arabic,
+                TextDirection::RightToLeft,
+            ),
+            ("Привет", Script::Cyrillic, TextDirection::LeftToRight),
+            ("日本語の文章", Script::Han, TextDirection::LeftToRight),
+            ("안녕하세요", Script::Hangul, TextDirection::LeftToRight),
+        ];
+        for (text, script, direction) in cases {
+            assert_eq!(dominant_script(text), Some(script), "{}", text);
+            assert_eq!(text_direction(text), Some(direction), "{}", text);
+        }
+        assert_eq!(dominant_script("123 !?"), None);
+        assert_eq!(text_direction("123 !?"), None);
+    }
+}
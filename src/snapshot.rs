@@ -0,0 +1,280 @@
+// Snapshotting the user's clipboard before a copy reads every flavor of it.
+// For hundreds of megabytes of video frames or images that doubles memory
+// and adds seconds, so past a limit the clipboard is left alone instead.
+//
+// Restoring used to write back the `NSPasteboardItem`s the pasteboard had
+// handed out. Those only read through to the pasteboard, so after the copy
+// their data was gone or the copy's, and the last item was dropped outright.
+// The snapshot owns the bytes of every type of every item instead.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use objc2::runtime::ProtocolObject;
+use objc2_app_kit::{NSPasteboard, NSPasteboardItem};
+use objc2_foundation::{NSArray, NSData, NSString};
+
+use crate::{diagnostics, Result, SelectedTextError};
+
+static SIZE_LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Skips snapshotting and restoring a clipboard holding more than
+/// `max_bytes`, across all its items and types. The copy then replaces it,
+/// which results report as `clipboard_restore_skipped`. `None`, the default,
+/// always restores.
+pub fn set_clipboard_size_limit(max_bytes: Option<usize>) {
+    SIZE_LIMIT.store(max_bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+pub(crate) fn exceeds_size_limit(pasteboard: &NSPasteboard) -> bool {
+    let limit = SIZE_LIMIT.load(Ordering::Relaxed);
+    if limit == usize::MAX {
+        return false;
+    }
+    let Some(items) = (unsafe { pasteboard.pasteboardItems() }) else {
+        return false;
+    };
+    let mut total = 0usize;
+    for item in items.iter() {
+        for pasteboard_type in unsafe { item.types() }.iter() {
+            // one flavor in memory at a time, stopping once over the limit
+            let size = unsafe { item.dataForType(pasteboard_type) }.map_or(0, |data| data.len());
+            total = total.saturating_add(size);
+            if total > limit {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// The data of every type of every item on a pasteboard, copied out of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PasteboardSnapshot {
+    items: Vec<Vec<(String, Vec<u8>)>>,
+}
+
+impl PasteboardSnapshot {
+    /// Reads every item of `pasteboard` in every type it offers. Types whose
+    /// data the owner won't provide are left out.
+    pub fn take(pasteboard: &NSPasteboard) -> Self {
+        Self::take_within(pasteboard, usize::MAX).unwrap_or_default()
+    }
+
+    // `None` once the data adds up to more than `limit` bytes
+    fn take_within(pasteboard: &NSPasteboard, limit: usize) -> Option<Self> {
+        let mut snapshot = PasteboardSnapshot::default();
+        let Some(items) = (unsafe { pasteboard.pasteboardItems() }) else {
+            return Some(snapshot);
+        };
+        let mut total = 0usize;
+        for item in items.iter() {
+            let mut flavors = Vec::new();
+            for pasteboard_type in unsafe { item.types() }.iter() {
+                let Some(data) = (unsafe { item.dataForType(pasteboard_type) }) else {
+                    continue;
+                };
+                total = total.saturating_add(data.len());
+                if total > limit {
+                    return None;
+                }
+                flavors.push((pasteboard_type.to_string(), data.bytes().to_vec()));
+            }
+            snapshot.items.push(flavors);
+        }
+        Some(snapshot)
+    }
+
+    /// Replaces the contents of `pasteboard` with the snapshot, item for
+    /// item and byte for byte.
+    pub fn restore(&self, pasteboard: &NSPasteboard) -> Result<()> {
+        let items: Vec<_> = self
+            .items
+            .iter()
+            .map(|flavors| {
+                let item = unsafe { NSPasteboardItem::new() };
+                for (pasteboard_type, data) in flavors {
+                    unsafe {
+                        item.setData_forType(
+                            &NSData::with_bytes(data),
+                            &NSString::from_str(pasteboard_type),
+                        )
+                    };
+                }
+                ProtocolObject::from_retained(item)
+            })
+            .collect();
+        unsafe { pasteboard.clearContents() };
+        if items.is_empty() {
+            return Ok(());
+        }
+        if !unsafe { pasteboard.writeObjects(&NSArray::from_vec(items)) } {
+            return Err(SelectedTextError::other(
+                "Failed to write objects to pasteboard",
+            ));
+        }
+        Ok(())
+    }
+
+    /// How many items the pasteboard held.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The types item `index` was saved in, in the owner's order of
+    /// preference.
+    pub fn types(&self, index: usize) -> impl Iterator<Item = &str> {
+        self.items
+            .get(index)
+            .into_iter()
+            .flatten()
+            .map(|(pasteboard_type, _)| pasteboard_type.as_str())
+    }
+
+    /// The saved bytes of item `index` in `pasteboard_type`.
+    pub fn data(&self, index: usize, pasteboard_type: &str) -> Option<&[u8]> {
+        self.items
+            .get(index)?
+            .iter()
+            .find(|(t, _)| t == pasteboard_type)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Total size in bytes of the saved data.
+    pub fn size(&self) -> usize {
+        self.items
+            .iter()
+            .flatten()
+            .map(|(_, data)| data.len())
+            .sum()
+    }
+}
+
+/// A snapshot of `pasteboard`, `None` when it's over the size limit.
+fn snapshot_within_limit(pasteboard: &NSPasteboard) -> Option<PasteboardSnapshot> {
+    PasteboardSnapshot::take_within(pasteboard, SIZE_LIMIT.load(Ordering::Relaxed))
+}
+
+/// Snapshots a pasteboard and puts the snapshot back when dropped, if
+/// anything wrote to the pasteboard in the meantime. An error or a panic
+/// between a copy and the restore then can't leave the user's clipboard
+/// holding the copy.
+pub struct PasteboardGuard<'a> {
+    pasteboard: &'a NSPasteboard,
+    change_count: isize,
+    snapshot: Option<PasteboardSnapshot>,
+}
+
+impl<'a> PasteboardGuard<'a> {
+    /// Snapshots `pasteboard`. A clipboard over the size limit (see
+    /// `set_clipboard_size_limit`) isn't snapshotted and isn't restored.
+    pub fn new(pasteboard: &'a NSPasteboard) -> Self {
+        let snapshot = snapshot_within_limit(pasteboard);
+        if snapshot.is_none() {
+            diagnostics::warn!("clipboard is over the size limit, it won't be restored");
+        }
+        Self::from_snapshot(pasteboard, unsafe { pasteboard.changeCount() }, snapshot)
+    }
+
+    /// Guards a snapshot taken earlier, when the pasteboard's change count
+    /// was `change_count`.
+    pub fn from_snapshot(
+        pasteboard: &'a NSPasteboard,
+        change_count: isize,
+        snapshot: Option<PasteboardSnapshot>,
+    ) -> Self {
+        PasteboardGuard {
+            pasteboard,
+            change_count,
+            snapshot,
+        }
+    }
+
+    /// The change count the snapshot was taken at.
+    pub fn change_count(&self) -> isize {
+        self.change_count
+    }
+
+    /// `None` when there's nothing to restore.
+    pub fn snapshot(&self) -> Option<&PasteboardSnapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Restores the snapshot now rather than on drop.
+    pub fn restore(&mut self) -> Result<()> {
+        match self.snapshot.take() {
+            Some(snapshot) => snapshot.restore(self.pasteboard),
+            None => Ok(()),
+        }
+    }
+
+    /// Leaves the pasteboard as it is from now on, returning the snapshot.
+    pub fn disarm(&mut self) -> Option<PasteboardSnapshot> {
+        self.snapshot.take()
+    }
+}
+
+impl Drop for PasteboardGuard<'_> {
+    fn drop(&mut self) {
+        if self.snapshot.is_none() || unsafe { self.pasteboard.changeCount() } == self.change_count
+        {
+            return;
+        }
+        if let Err(e) = self.restore() {
+            diagnostics::error!("Failed to restore the pasteboard: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let pasteboard = unsafe { NSPasteboard::pasteboardWithUniqueName() };
+        let png = [0x89, b'P', b'N', b'G', 0, 1, 2, 255];
+        let first = unsafe { NSPasteboardItem::new() };
+        let second = unsafe { NSPasteboardItem::new() };
+        unsafe {
+            first.setString_forType(
+                &NSString::from_str("one"),
+                &NSString::from_str("public.utf8-plain-text"),
+            );
+            first.setData_forType(&NSData::with_bytes(&png), &NSString::from_str("public.png"));
+            second.setString_forType(
+                &NSString::from_str("two"),
+                &NSString::from_str("public.utf8-plain-text"),
+            );
+            pasteboard.clearContents();
+            pasteboard.writeObjects(&NSArray::from_vec(vec![
+                ProtocolObject::from_retained(first),
+                ProtocolObject::from_retained(second),
+            ]));
+        }
+        let snapshot = PasteboardSnapshot::take(&pasteboard);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.data(0, "public.png"), Some(&png[..]));
+
+        unsafe {
+            pasteboard.clearContents();
+            pasteboard.setString_forType(
+                &NSString::from_str("copied"),
+                &NSString::from_str("public.utf8-plain-text"),
+            );
+        }
+        snapshot.restore(&pasteboard).unwrap();
+        assert_eq!(PasteboardSnapshot::take(&pasteboard), snapshot);
+        assert!(PasteboardSnapshot::take_within(&pasteboard, png.len()).is_none());
+
+        let guard = PasteboardGuard::new(&pasteboard);
+        unsafe {
+            pasteboard.clearContents();
+        }
+        drop(guard);
+        assert_eq!(PasteboardSnapshot::take(&pasteboard), snapshot);
+    }
+}
@@ -0,0 +1,123 @@
+// Some apps only work with some strategies, and a host app can't tell its
+// users "capturing is unreliable in this app" from a single failed capture.
+// When enabled, captures are counted per app and strategy as they finish.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How captures in one app went with one strategy.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CaptureStats {
+    pub app: String,
+    /// The strategy the capture finished with, named as in
+    /// `CaptureBreadcrumb::strategy`.
+    pub strategy: String,
+    pub successes: u64,
+    /// Errors, and copies the pasteboard never changed for.
+    pub failures: u64,
+    /// Mean time from the start of a capture to its result.
+    pub mean_latency: Duration,
+    #[serde(skip)]
+    total_latency: Duration,
+}
+
+impl CaptureStats {
+    pub fn attempts(&self) -> u64 {
+        self.successes + self.failures
+    }
+
+    /// Failures as a share of the attempts, 0 before any.
+    pub fn failure_rate(&self) -> f64 {
+        match self.attempts() {
+            0 => 0.0,
+            attempts => self.failures as f64 / attempts as f64,
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STATS: Mutex<Vec<CaptureStats>> = Mutex::new(Vec::new());
+
+/// Counts captures for `stats`. Off by default, turning it off keeps what was
+/// counted so far.
+pub fn set_stats_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// What was counted since stats were enabled or last reset, by app and then
+/// strategy.
+pub fn stats() -> Vec<CaptureStats> {
+    let mut stats = STATS.lock().unwrap().clone();
+    stats.sort_by(|a, b| (&a.app, &a.strategy).cmp(&(&b.app, &b.strategy)));
+    stats
+}
+
+pub fn reset_stats() {
+    STATS.lock().unwrap().clear();
+}
+
+pub(crate) fn finished(app: &str, strategy: &str, succeeded: bool, latency: Duration) {
+    if ENABLED.load(Ordering::Relaxed) {
+        record(
+            &mut STATS.lock().unwrap(),
+            app,
+            strategy,
+            succeeded,
+            latency,
+        );
+    }
+}
+
+fn record(
+    stats: &mut Vec<CaptureStats>,
+    app: &str,
+    strategy: &str,
+    succeeded: bool,
+    latency: Duration,
+) {
+    let index = match stats
+        .iter()
+        .position(|s| s.app == app && s.strategy == strategy)
+    {
+        Some(index) => index,
+        None => {
+            stats.push(CaptureStats {
+                app: app.to_owned(),
+                strategy: strategy.to_owned(),
+                successes: 0,
+                failures: 0,
+                mean_latency: Duration::ZERO,
+                total_latency: Duration::ZERO,
+            });
+            stats.len() - 1
+        }
+    };
+    let entry = &mut stats[index];
+    if succeeded {
+        entry.successes += 1;
+    } else {
+        entry.failures += 1;
+    }
+    entry.total_latency += latency;
+    entry.mean_latency = entry.total_latency / entry.attempts() as u32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut stats = Vec::new();
+        let ms = Duration::from_millis;
+        record(&mut stats, "Slack", "key-chord", true, ms(300));
+        record(&mut stats, "Slack", "key-chord", false, ms(500));
+        record(&mut stats, "Slack", "ax", true, ms(10));
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].attempts(), 2);
+        assert_eq!(stats[0].mean_latency, ms(400));
+        assert_eq!(stats[0].failure_rate(), 0.5);
+        assert_eq!(stats[1].failure_rate(), 0.0);
+    }
+}
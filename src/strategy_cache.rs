@@ -0,0 +1,221 @@
+// Apps whose selection only comes through the clipboard fail the AX read on
+// every capture first, and for Electron apps that failure is slow. What each
+// app needed is remembered, and optionally kept in a file so a restart
+// doesn't have to learn it again.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::diagnostics;
+use crate::{breadcrumb::write_atomically, Result};
+
+/// Apps remembered, the least recently captured one is forgotten first.
+const CAPACITY: usize = 256;
+
+/// Captures in an app that needed the clipboard before AX is tried again,
+/// in case it was one text field that didn't support it.
+const RETRY_AX_AFTER: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Learned {
+    Ax,
+    Clipboard,
+}
+
+impl Learned {
+    fn name(self) -> &'static str {
+        match self {
+            Learned::Ax => "ax",
+            Learned::Clipboard => "clipboard",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    bundle_id: String,
+    learned: Learned,
+    /// AX reads skipped since it was last tried.
+    skipped: u32,
+}
+
+// most recently used last
+static CACHE: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static CACHE_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Keeps what was learned about each app's capture strategy in `path`, and
+/// reads back what an earlier run left there. `None`, the default, keeps it
+/// in memory only.
+pub fn set_strategy_cache_file(path: Option<PathBuf>) -> Result<()> {
+    if let Some(path) = &path {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => merge(&mut CACHE.lock().unwrap(), from_file_contents(&contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    *CACHE_FILE.lock().unwrap() = path;
+    Ok(())
+}
+
+/// Whether the AX read can be skipped for the app with `bundle_id`, it needed
+/// the clipboard last time.
+pub(crate) fn skip_ax(bundle_id: &str) -> bool {
+    let mut cache = CACHE.lock().unwrap();
+    let Some(entry) = touch(&mut cache, bundle_id) else {
+        return false;
+    };
+    if entry.learned != Learned::Clipboard || entry.skipped >= RETRY_AX_AFTER {
+        entry.skipped = 0;
+        return false;
+    }
+    entry.skipped += 1;
+    true
+}
+
+/// What `skip_ax` would answer, without counting a skip.
+pub(crate) fn would_skip_ax(bundle_id: &str) -> bool {
+    CACHE.lock().unwrap().iter().any(|entry| {
+        entry.bundle_id.eq_ignore_ascii_case(bundle_id)
+            && entry.learned == Learned::Clipboard
+            && entry.skipped < RETRY_AX_AFTER
+    })
+}
+
+pub(crate) fn learn(bundle_id: &str, learned: Learned) {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(entry) = touch(&mut cache, bundle_id) {
+        if entry.learned == learned {
+            return;
+        }
+        entry.learned = learned;
+        entry.skipped = 0;
+    } else {
+        insert(
+            &mut cache,
+            Entry {
+                bundle_id: bundle_id.to_owned(),
+                learned,
+                skipped: 0,
+            },
+        );
+    }
+    if let Some(path) = CACHE_FILE.lock().unwrap().as_deref() {
+        save(path, &cache);
+    }
+}
+
+// moves the entry to the most recently used end
+fn touch<'a>(cache: &'a mut Vec<Entry>, bundle_id: &str) -> Option<&'a mut Entry> {
+    let index = cache
+        .iter()
+        .position(|entry| entry.bundle_id.eq_ignore_ascii_case(bundle_id))?;
+    let entry = cache.remove(index);
+    cache.push(entry);
+    cache.last_mut()
+}
+
+fn insert(cache: &mut Vec<Entry>, entry: Entry) {
+    if cache.len() >= CAPACITY {
+        cache.remove(0);
+    }
+    cache.push(entry);
+}
+
+// what was learned in this run wins over the file
+fn merge(cache: &mut Vec<Entry>, stored: Vec<Entry>) {
+    for entry in stored.into_iter().rev() {
+        if cache
+            .iter()
+            .any(|known| known.bundle_id.eq_ignore_ascii_case(&entry.bundle_id))
+        {
+            continue;
+        }
+        if cache.len() >= CAPACITY {
+            break;
+        }
+        cache.insert(0, entry);
+    }
+}
+
+fn save(path: &Path, cache: &[Entry]) {
+    if let Err(e) = write_atomically(path, &to_file_contents(cache)) {
+        diagnostics::warn!(
+            "Failed to write strategy cache to {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+// one `bundle-id strategy` per line, oldest first
+fn to_file_contents(cache: &[Entry]) -> String {
+    cache
+        .iter()
+        .map(|entry| format!("{} {}\n", entry.bundle_id, entry.learned.name()))
+        .collect()
+}
+
+fn from_file_contents(contents: &str) -> Vec<Entry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (bundle_id, name) = line.trim().split_once(' ')?;
+            let learned = [Learned::Ax, Learned::Clipboard]
+                .into_iter()
+                .find(|learned| learned.name() == name)?;
+            Some(Entry {
+                bundle_id: bundle_id.to_owned(),
+                learned,
+                skipped: 0,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_ax_until_retry() {
+        learn("com.example.Chat", Learned::Clipboard);
+        for _ in 0..RETRY_AX_AFTER {
+            assert!(skip_ax("com.example.chat"));
+        }
+        assert!(!would_skip_ax("com.example.Chat"));
+        assert!(!skip_ax("com.example.Chat"));
+        assert!(would_skip_ax("com.example.Chat"));
+        assert!(skip_ax("com.example.Chat"));
+        learn("com.example.Chat", Learned::Ax);
+        assert!(!skip_ax("com.example.Chat"));
+        assert!(!skip_ax("com.example.Unknown"));
+    }
+
+    #[test]
+    fn test_strategy_cache_file() {
+        let entry = |bundle_id: &str, learned| Entry {
+            bundle_id: bundle_id.to_owned(),
+            learned,
+            skipped: 0,
+        };
+        let stored = vec![
+            entry("com.example.A", Learned::Ax),
+            entry("com.example.B", Learned::Clipboard),
+        ];
+        let contents = to_file_contents(&stored);
+        assert_eq!(contents, "com.example.A ax\ncom.example.B clipboard\n");
+        assert_eq!(from_file_contents(&contents), stored);
+        assert_eq!(from_file_contents("com.example.C sometimes\n"), []);
+
+        let mut cache = vec![entry("com.example.B", Learned::Ax)];
+        merge(&mut cache, stored);
+        assert_eq!(
+            cache,
+            [
+                entry("com.example.A", Learned::Ax),
+                entry("com.example.B", Learned::Ax)
+            ]
+        );
+    }
+}
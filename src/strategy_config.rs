@@ -0,0 +1,91 @@
+// Some apps are known to need one path, a terminal where only copying
+// through System Events works, an editor whose AX tree costs more than a
+// copy. Callers who know their apps can say so up front instead of every
+// capture trying AX first.
+
+use std::sync::RwLock;
+
+use crate::{CopyMethod, Strategy};
+
+/// How captures go in one app, see `set_strategy_config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyConfig {
+    strategies: Vec<Strategy>,
+    copy_method: Option<CopyMethod>,
+}
+
+impl StrategyConfig {
+    /// Tries only `strategies`, in this order.
+    pub fn new(strategies: impl IntoIterator<Item = Strategy>) -> Self {
+        StrategyConfig {
+            strategies: strategies.into_iter().collect(),
+            copy_method: None,
+        }
+    }
+
+    /// Copies with `method` for `Strategy::Copy`, over `set_copy_method`
+    /// and the caller's method.
+    pub fn copy_method(mut self, method: CopyMethod) -> Self {
+        self.copy_method = Some(method);
+        self
+    }
+}
+
+static CONFIGS: RwLock<Vec<(String, StrategyConfig)>> = RwLock::new(Vec::new());
+
+/// Captures in the app with `bundle_id` as `config` says, whatever
+/// strategies the caller passes in `SelectedTextOptions`. `None` goes back
+/// to the caller's.
+pub fn set_strategy_config(bundle_id: &str, config: Option<StrategyConfig>) {
+    let mut configs = CONFIGS.write().unwrap();
+    configs.retain(|(id, _)| !id.eq_ignore_ascii_case(bundle_id));
+    if let Some(config) = config {
+        configs.push((bundle_id.to_owned(), config));
+    }
+}
+
+fn with_config<R>(bundle_id: &str, f: impl FnOnce(&StrategyConfig) -> R) -> Option<R> {
+    CONFIGS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(bundle_id))
+        .map(|(_, config)| f(config))
+}
+
+pub(crate) fn strategies(bundle_id: &str) -> Option<Vec<Strategy>> {
+    with_config(bundle_id, |config| config.strategies.clone())
+}
+
+pub(crate) fn copy_method(bundle_id: &str) -> Option<CopyMethod> {
+    with_config(bundle_id, |config| config.copy_method).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategy_config() {
+        set_strategy_config(
+            "com.googlecode.iterm2",
+            Some(StrategyConfig::new([Strategy::Copy]).copy_method(CopyMethod::AppleScript)),
+        );
+        set_strategy_config(
+            "com.microsoft.VSCode",
+            Some(StrategyConfig::new([Strategy::Copy])),
+        );
+        assert_eq!(
+            strategies("com.googlecode.iTerm2").as_deref(),
+            Some(&[Strategy::Copy][..])
+        );
+        assert_eq!(
+            copy_method("com.googlecode.iterm2"),
+            Some(CopyMethod::AppleScript)
+        );
+        assert_eq!(copy_method("com.microsoft.VSCode"), None);
+
+        set_strategy_config("com.microsoft.VSCode", None);
+        assert_eq!(strategies("com.microsoft.VSCode"), None);
+    }
+}
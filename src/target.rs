@@ -0,0 +1,35 @@
+// Apps copy to the general pasteboard: neither the copy chord nor the
+// Edit ▸ Copy menu item can be pointed at another one, and AX has no copy
+// action that takes a pasteboard. What a capture can choose is the
+// pasteboard it watches, snapshots and restores.
+
+use objc2::rc::Retained;
+use objc2_app_kit::NSPasteboard;
+use objc2_foundation::NSString;
+
+/// The pasteboard a capture reads the copy from and restores afterwards.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PasteboardTarget {
+    /// The clipboard, where apps copy to.
+    #[default]
+    General,
+    /// A private pasteboard, for handlers and copy paths that write to it
+    /// themselves. A copy sent to the app still lands on the general
+    /// pasteboard and isn't seen.
+    Named(String),
+}
+
+impl PasteboardTarget {
+    pub fn named(name: impl Into<String>) -> Self {
+        PasteboardTarget::Named(name.into())
+    }
+
+    pub fn pasteboard(&self) -> Retained<NSPasteboard> {
+        match self {
+            PasteboardTarget::General => unsafe { NSPasteboard::generalPasteboard() },
+            PasteboardTarget::Named(name) => unsafe {
+                NSPasteboard::pasteboardWithName(&NSString::from_str(name))
+            },
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// A host app building its own clipboard workflow on top of a capture (copy,
+// transform, put something back) mustn't clobber what the user copied in
+// between. The change count seen at capture time is handed out as a token,
+// and writes go through only while it's still current.
+
+#[cfg(target_os = "macos")]
+use objc2::{rc::Retained, runtime::ProtocolObject};
+#[cfg(target_os = "macos")]
+use objc2_app_kit::{NSPasteboard, NSPasteboardItem};
+#[cfg(target_os = "macos")]
+use objc2_foundation::NSArray;
+
+#[cfg(target_os = "macos")]
+use crate::{restore_pasteboard, PasteboardSavedState, Result, SelectedTextError};
+
+/// The state of a pasteboard at one point, still current until anything
+/// writes to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(transparent)]
+pub struct ClipboardToken(isize);
+
+#[cfg(target_os = "macos")]
+impl ClipboardToken {
+    pub fn current(pasteboard: &NSPasteboard) -> Self {
+        ClipboardToken(unsafe { pasteboard.changeCount() })
+    }
+
+    /// Nothing wrote to `pasteboard` since the token was taken.
+    pub fn is_current(&self, pasteboard: &NSPasteboard) -> bool {
+        *self == ClipboardToken::current(pasteboard)
+    }
+}
+
+/// Replaces the contents of `pasteboard` with `items` unless it changed
+/// since `token`, returning the token of the new contents. `None` means it
+/// changed and was left alone. The check and the write aren't atomic, a copy
+/// landing in between them is still lost.
+#[cfg(target_os = "macos")]
+pub fn write_if_unchanged(
+    pasteboard: &NSPasteboard,
+    token: ClipboardToken,
+    items: Vec<Retained<NSPasteboardItem>>,
+) -> Result<Option<ClipboardToken>> {
+    if !token.is_current(pasteboard) {
+        return Ok(None);
+    }
+    let items: Vec<_> = items
+        .into_iter()
+        .map(ProtocolObject::from_retained)
+        .collect();
+    unsafe { pasteboard.clearContents() };
+    if !unsafe { pasteboard.writeObjects(&NSArray::from_vec(items)) } {
+        return Err(SelectedTextError::other(
+            "Failed to write objects to pasteboard",
+        ));
+    }
+    Ok(Some(ClipboardToken::current(pasteboard)))
+}
+
+/// Puts back the clipboard `saved` snapshotted, like a capture does, unless
+/// the pasteboard changed since `token`. `None` means it changed.
+#[cfg(target_os = "macos")]
+pub fn restore_if_unchanged(
+    pasteboard: &NSPasteboard,
+    token: ClipboardToken,
+    saved: PasteboardSavedState,
+) -> Result<Option<ClipboardToken>> {
+    if !token.is_current(pasteboard) {
+        return Ok(None);
+    }
+    restore_pasteboard(pasteboard, saved.saved_contents.as_ref())?;
+    Ok(Some(ClipboardToken::current(pasteboard)))
+}
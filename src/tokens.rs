@@ -0,0 +1,69 @@
+use std::sync::RwLock;
+
+/// Counts tokens for `approx_tokens`. Implement this to plug in the tokenizer
+/// of the model the selection is sent to.
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Character-class estimate that lands within ~20% of BPE tokenizers: about
+/// four characters per token for Latin text, one per CJK character.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let mut quarters: usize = 0;
+        for c in text.chars() {
+            quarters += match c {
+                c if c.is_whitespace() => 0,
+                c if c.is_ascii_alphanumeric() => 1,
+                c if c.is_ascii() => 2,
+                c if is_cjk(c) => 4,
+                c if c.is_alphanumeric() => 2,
+                _ => 4,
+            };
+        }
+        quarters.div_ceil(4)
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}' // Hangul syllables
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2ffff}')
+}
+
+static TOKENIZER: RwLock<Option<Box<dyn Tokenizer>>> = RwLock::new(None);
+
+/// Replaces `HeuristicTokenizer` for every following capture.
+pub fn set_tokenizer(tokenizer: impl Tokenizer + 'static) {
+    *TOKENIZER.write().unwrap() = Some(Box::new(tokenizer));
+}
+
+pub fn approx_tokens(text: &str) -> usize {
+    match TOKENIZER.read().unwrap().as_deref() {
+        Some(tokenizer) => tokenizer.count_tokens(text),
+        None => HeuristicTokenizer.count_tokens(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_tokenizer() {
+        let tokenizer = HeuristicTokenizer;
+        assert_eq!(tokenizer.count_tokens(""), 0);
+        assert_eq!(tokenizer.count_tokens("hello world"), 3);
+        assert_eq!(tokenizer.count_tokens("你好世界"), 4);
+        let prose = "The quick brown fox jumps over the lazy dog.";
+        // cl100k_base counts 10 tokens
+        assert!((9..=13).contains(&tokenizer.count_tokens(prose)));
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(target_os = "macos")]
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Largest grapheme cluster boundary in `text` that is `<= index`, so cutting
+/// there never splits an emoji sequence or a letter from its combining marks.
+pub fn floor_grapheme_boundary(text: &str, index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    text.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|&start| start <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Longest prefix of `text` of at most `max_bytes` bytes ending on a grapheme
+/// cluster boundary.
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    &text[..floor_grapheme_boundary(text, max_bytes)]
+}
+
+/// Longest prefix of `text` of at most `max_units` UTF-16 code units ending on
+/// a grapheme cluster boundary, for lengths reported by AppKit.
+pub fn truncate_utf16(text: &str, max_units: usize) -> &str {
+    let mut units = 0;
+    for (start, grapheme) in text.grapheme_indices(true) {
+        units += grapheme.encode_utf16().count();
+        if units > max_units {
+            return &text[..start];
+        }
+    }
+    text
+}
+
+/// The first `max_graphemes` user-perceived characters of `text`.
+pub fn truncate_graphemes(text: &str, max_graphemes: usize) -> &str {
+    match text.grapheme_indices(true).nth(max_graphemes) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+// NSRange offsets count UTF-16 code units, Rust slices count bytes
+#[cfg(target_os = "macos")]
+pub(crate) fn utf16_range_to_byte_range(
+    text: &str,
+    location: usize,
+    length: usize,
+) -> Option<Range<usize>> {
+    let end = location.checked_add(length)?;
+    let mut utf16_offset = 0;
+    let mut start_byte = None;
+    let mut end_byte = None;
+    for (byte_offset, ch) in text.char_indices() {
+        if utf16_offset == location {
+            start_byte = Some(byte_offset);
+        }
+        if utf16_offset == end {
+            end_byte = Some(byte_offset);
+            break;
+        }
+        utf16_offset += ch.len_utf16();
+    }
+    if utf16_offset == location && start_byte.is_none() {
+        start_byte = Some(text.len());
+    }
+    if utf16_offset == end && end_byte.is_none() {
+        end_byte = Some(text.len());
+    }
+    Some(start_byte?..end_byte?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation_keeps_graphemes() {
+        // "e" + combining acute, a family emoji joined with ZWJs, a flag
+        let text = "e\u{301}👨\u{200d}👩\u{200d}👧🇫🇷!";
+        assert_eq!(truncate_bytes(text, 2), "");
+        assert_eq!(truncate_bytes(text, 3), "e\u{301}");
+        assert_eq!(truncate_bytes(text, 10), "e\u{301}");
+        assert_eq!(truncate_bytes(text, 1000), text);
+        assert_eq!(truncate_utf16(text, 9), "e\u{301}");
+        assert_eq!(truncate_utf16(text, 10), "e\u{301}👨\u{200d}👩\u{200d}👧");
+        assert_eq!(
+            truncate_graphemes(text, 3),
+            "e\u{301}👨\u{200d}👩\u{200d}👧🇫🇷"
+        );
+        assert_eq!(truncate_graphemes(text, 0), "");
+        assert_eq!(floor_grapheme_boundary(text, 0), 0);
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_utf16_range_to_byte_range() {
+        assert_eq!(utf16_range_to_byte_range("hello", 1, 3), Some(1..4));
+        assert_eq!(utf16_range_to_byte_range("hello", 5, 0), Some(5..5));
+        // "é" is one UTF-16 unit but two bytes, "😀" is two UTF-16 units and four bytes
+        assert_eq!(utf16_range_to_byte_range("é😀x", 1, 2), Some(2..6));
+        assert_eq!(utf16_range_to_byte_range("é😀x", 3, 1), Some(6..7));
+        // ranges that split a surrogate pair or run past the end are rejected
+        assert_eq!(utf16_range_to_byte_range("😀", 1, 1), None);
+        assert_eq!(utf16_range_to_byte_range("abc", 2, 5), None);
+    }
+}
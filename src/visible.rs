@@ -0,0 +1,82 @@
+// A selection can run past the edges of a scroll view. Screenshots and OCR
+// of it then miss text and a popup placed at its bounds points offscreen,
+// so callers need to know how much of it is actually on screen.
+
+use std::ops::Range;
+
+use accessibility_ng::{AXAttribute, AXValue};
+use accessibility_sys_ng::kAXVisibleCharacterRangeAttribute;
+use core_foundation::{base::CFRange, string::CFString};
+
+use crate::{ax, policy, Result};
+
+/// The selected range and the part of it the element currently shows, both
+/// UTF-16 ranges like `get_selected_range`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SelectionVisibility {
+    pub selected: Range<usize>,
+    /// `None` when none of the selection is visible, or the element doesn't
+    /// report `AXVisibleCharacterRange`, see `visible_range_known`.
+    pub visible: Option<Range<usize>>,
+    pub visible_range_known: bool,
+}
+
+impl SelectionVisibility {
+    /// The whole selection is on screen. `false` when that's unknown.
+    pub fn is_fully_visible(&self) -> bool {
+        self.visible.as_ref() == Some(&self.selected)
+    }
+}
+
+/// How much of the focused element's selection is scrolled into view.
+pub fn get_selection_visibility() -> Result<SelectionVisibility> {
+    policy::ensure_capture_allowed()?;
+    let element = ax::focused_element()?;
+    let selected = to_range(ax::selected_range(&element)?);
+    let visible_range = element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXVisibleCharacterRangeAttribute,
+        )))
+        .ok()
+        .and_then(|value| value.downcast_into::<AXValue>())
+        .and_then(|value| value.get_value::<CFRange>().ok())
+        .map(to_range);
+    Ok(SelectionVisibility {
+        visible: visible_range
+            .as_ref()
+            .and_then(|visible| intersection(&selected, visible)),
+        visible_range_known: visible_range.is_some(),
+        selected,
+    })
+}
+
+fn to_range(range: CFRange) -> Range<usize> {
+    let start = range.location.max(0) as usize;
+    start..start + range.length.max(0) as usize
+}
+
+// an empty selection counts as visible when its insertion point is
+fn intersection(selected: &Range<usize>, visible: &Range<usize>) -> Option<Range<usize>> {
+    if selected.is_empty() {
+        return (visible.start..=visible.end)
+            .contains(&selected.start)
+            .then(|| selected.clone());
+    }
+    let start = selected.start.max(visible.start);
+    let end = selected.end.min(visible.end);
+    (start < end).then_some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(intersection(&(10..20), &(0..100)), Some(10..20));
+        assert_eq!(intersection(&(10..200), &(0..100)), Some(10..100));
+        assert_eq!(intersection(&(150..200), &(0..100)), None);
+        assert_eq!(intersection(&(100..100), &(0..100)), Some(100..100));
+        assert_eq!(intersection(&(5..5), &(10..100)), None);
+    }
+}
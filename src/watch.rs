@@ -0,0 +1,282 @@
+// Popup dictionaries and translators want the selection as soon as it
+// changes. Polling `get_selected_text` for that posts Cmd+C over and over;
+// AX already tells observers when the selected text of an app changes.
+
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use accessibility_ng::{AXObserver, AXUIElement};
+use accessibility_sys_ng::{
+    kAXErrorSuccess, kAXFocusedUIElementChangedNotification, kAXSelectedTextChangedNotification,
+    AXObserverAddNotification, AXObserverRef, AXObserverRemoveNotification, AXUIElementRef,
+};
+use core_foundation::{
+    base::TCFType,
+    runloop::{kCFRunLoopDefaultMode, CFRunLoop},
+    string::{CFString, CFStringRef},
+};
+
+use crate::{
+    accessibility, active_window, ax, bounds, diagnostics, get_selected_range, policy,
+    CaptureStrategy, Result, SelectedText, SelectedTextError, SelectionDedup,
+};
+
+// focus moving into a field that already has a selection changes the
+// selection just the same
+const NOTIFICATIONS: [&str; 2] = [
+    kAXSelectedTextChangedNotification,
+    kAXFocusedUIElementChangedNotification,
+];
+
+// how long the watch thread's run loop runs before checking for a stop and
+// for another app taking focus
+const SLICE: Duration = Duration::from_millis(50);
+
+/// A running `watch_selection`. The watch stops when this is dropped.
+pub struct SelectionWatch {
+    receiver: Receiver<SelectedText>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SelectionWatch {
+    /// Waits for the next selection. `None` once the watch has stopped.
+    pub fn recv(&self) -> Option<SelectedText> {
+        self.receiver.recv().ok()
+    }
+
+    /// Like `recv`, giving up after `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<SelectedText> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// The next selection if one is waiting.
+    pub fn try_recv(&self) -> Option<SelectedText> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// The channel the selections arrive on, e.g. to `select` over.
+    pub fn receiver(&self) -> &Receiver<SelectedText> {
+        &self.receiver
+    }
+}
+
+impl Drop for SelectionWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Sends the selection of the focused app each time it changes, once it
+/// has stayed the same for `debounce`, so dragging over text gives one
+/// value rather than one per character. Reads AX only, nothing is copied:
+/// apps without an AX selection, and clearing the selection, send nothing.
+/// A selection equal to the last one sent is dropped, and so is any
+/// selection in an app `set_app_denied` or `set_allowed_apps` rule out, or
+/// while the screen is locked.
+pub fn watch_selection(debounce: Duration) -> Result<SelectionWatch> {
+    watch_selection_with(debounce, SelectionDedup::new())
+}
+
+/// Like `watch_selection`, dropping repeats as `dedup` says, e.g. a
+/// `SelectionDedup` with `set_enabled(false)` sends them all.
+pub fn watch_selection_with(debounce: Duration, dedup: SelectionDedup) -> Result<SelectionWatch> {
+    if !accessibility::is_trusted() {
+        return Err(SelectedTextError::AccessibilityDenied);
+    }
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = std::thread::Builder::new()
+        .name("watch_selection".to_owned())
+        .spawn({
+            let stop = stop.clone();
+            move || run(debounce, dedup, sender, &stop)
+        })?;
+    Ok(SelectionWatch {
+        receiver,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+fn run(
+    debounce: Duration,
+    mut dedup: SelectionDedup,
+    sender: Sender<SelectedText>,
+    stop: &AtomicBool,
+) {
+    // set by `on_notification`, which the run loop below calls on this
+    // thread; outlives every observer
+    let notified = Cell::new(false);
+    let mut observed: Option<Observed> = None;
+    let mut debouncer = Debouncer::new(debounce);
+    while !stop.load(Ordering::Relaxed) {
+        let pid = ax::focused_app().ok().and_then(|app| app.pid().ok());
+        if observed.as_ref().map(|observed| observed.pid) != pid {
+            observed = None;
+            if let Some(pid) = pid {
+                observed = Observed::new(pid, &notified);
+                // the new app's selection is news even if it equals the
+                // last one sent
+                dedup.reset();
+                debouncer.notify(Instant::now());
+            }
+        }
+        if observed.is_some() {
+            CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, SLICE, true);
+        } else {
+            // without an observer the mode has no sources and the run loop
+            // returns at once
+            std::thread::sleep(SLICE);
+        }
+        if notified.replace(false) {
+            debouncer.notify(Instant::now());
+        }
+        if !debouncer.is_due(Instant::now()) {
+            continue;
+        }
+        let Some(selected) = current_selection() else {
+            continue;
+        };
+        if dedup.is_repeat(&selected, get_selected_range().ok()) {
+            continue;
+        }
+        if sender.send(selected).is_err() {
+            break;
+        }
+    }
+}
+
+fn current_selection() -> Option<SelectedText> {
+    // the app may have been denied, or the screen locked, since the watch
+    // started
+    if let Err(e) = policy::ensure_capture_allowed() {
+        diagnostics::debug!("not sending the selection: {}", e);
+        return None;
+    }
+    let text = ax::selected_text().ok()?.to_string();
+    if text.is_empty() {
+        return None;
+    }
+    let app_name = active_window().app_name().unwrap_or_default().to_owned();
+    let mut selected = SelectedText::from_text(app_name, text).with_strategy(CaptureStrategy::Ax);
+    selected.bounds = bounds::selection_bounds();
+    Some(selected)
+}
+
+/// An observer of one app, added to the current run loop.
+struct Observed {
+    pid: i32,
+    app: AXUIElement,
+    observer: AXObserver,
+}
+
+impl Observed {
+    fn new(pid: i32, notified: &Cell<bool>) -> Option<Self> {
+        let observer = AXObserver::new(pid, on_notification)
+            .map_err(|e| diagnostics::debug!("no AX observer for pid {}: {:?}", pid, e))
+            .ok()?;
+        let app = AXUIElement::application(pid);
+        let refcon = notified as *const Cell<bool> as *mut c_void;
+        for notification in NOTIFICATIONS {
+            let notification = CFString::from_static_string(notification);
+            let error = unsafe {
+                AXObserverAddNotification(
+                    observer.as_concrete_TypeRef(),
+                    app.as_concrete_TypeRef() as AXUIElementRef,
+                    notification.as_concrete_TypeRef(),
+                    refcon,
+                )
+            };
+            if error != kAXErrorSuccess {
+                diagnostics::debug!(
+                    "pid {} doesn't post {}: AX error {}",
+                    pid,
+                    notification,
+                    error
+                );
+            }
+        }
+        observer.start();
+        Some(Observed { pid, app, observer })
+    }
+}
+
+impl Drop for Observed {
+    fn drop(&mut self) {
+        for notification in NOTIFICATIONS {
+            let notification = CFString::from_static_string(notification);
+            unsafe {
+                AXObserverRemoveNotification(
+                    self.observer.as_concrete_TypeRef(),
+                    self.app.as_concrete_TypeRef() as AXUIElementRef,
+                    notification.as_concrete_TypeRef(),
+                );
+            }
+        }
+        self.observer.stop();
+    }
+}
+
+unsafe extern "C" fn on_notification(
+    _observer: AXObserverRef,
+    _element: AXUIElementRef,
+    _notification: CFStringRef,
+    refcon: *mut c_void,
+) {
+    let notified = &*(refcon as *const Cell<bool>);
+    notified.set(true);
+}
+
+/// Tells when a burst of notifications has been quiet for `delay`.
+struct Debouncer {
+    delay: Duration,
+    last: Option<Instant>,
+}
+
+impl Debouncer {
+    fn new(delay: Duration) -> Self {
+        Debouncer { delay, last: None }
+    }
+
+    fn notify(&mut self, now: Instant) {
+        self.last = Some(now);
+    }
+
+    /// Whether the last notification is `delay` old. Only once per burst.
+    fn is_due(&mut self, now: Instant) -> bool {
+        match self.last {
+            Some(last) if now.duration_since(last) >= self.delay => {
+                self.last = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debouncer() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut debouncer = Debouncer::new(ms(100));
+        assert!(!debouncer.is_due(start));
+        debouncer.notify(start);
+        debouncer.notify(start + ms(60));
+        assert!(!debouncer.is_due(start + ms(120)));
+        assert!(debouncer.is_due(start + ms(160)));
+        assert!(!debouncer.is_due(start + ms(300)));
+    }
+}
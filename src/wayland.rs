@@ -0,0 +1,237 @@
+// Wayland only hands the primary selection to the focused client, a tool
+// reading it from outside needs the compositor's wlr-data-control, version
+// 2 or later for the primary selection. KDE and wlroots compositors have it,
+// GNOME doesn't.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::fd::AsFd;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use wayland_client::{
+    backend::ObjectId,
+    event_created_child,
+    protocol::{wl_registry, wl_seat::WlSeat},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+};
+
+use crate::{decode_lossy, diagnostics};
+
+// in order of preference, what GTK, Qt and XWayland offer text as
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+/// The primary selection as text, `None` when the compositor doesn't
+/// implement wlr-data-control, nothing is selected, or the selection's
+/// owner didn't send it within `timeout`.
+pub(crate) fn primary_selection(timeout: Duration) -> Option<String> {
+    match read_primary_selection(timeout) {
+        Ok(text) => text,
+        Err(e) => {
+            diagnostics::debug!("wlr-data-control: {:#}", e);
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    seat: Option<WlSeat>,
+    manager: Option<ZwlrDataControlManagerV1>,
+    mime_types: HashMap<ObjectId, Vec<String>>,
+    primary: Option<ZwlrDataControlOfferV1>,
+}
+
+fn read_primary_selection(timeout: Duration) -> anyhow::Result<Option<String>> {
+    let connection = Connection::connect_to_env()?;
+    let mut queue = connection.new_event_queue();
+    let handle = queue.handle();
+    connection.display().get_registry(&handle, ());
+    let mut state = State::default();
+    queue.roundtrip(&mut state)?;
+    let (Some(seat), Some(manager)) = (&state.seat, &state.manager) else {
+        return Err(anyhow!(
+            "the compositor doesn't offer data control for the primary selection"
+        ));
+    };
+    // the device announces the current offers right after it's created
+    let device = manager.get_data_device(seat, &handle, ());
+    queue.roundtrip(&mut state)?;
+    let text = match state.primary.take() {
+        Some(offer) => {
+            let text = receive_text(&connection, &offer, &state, timeout);
+            offer.destroy();
+            text
+        }
+        None => Ok(None),
+    };
+    device.destroy();
+    connection.flush()?;
+    text
+}
+
+fn receive_text(
+    connection: &Connection,
+    offer: &ZwlrDataControlOfferV1,
+    state: &State,
+    timeout: Duration,
+) -> anyhow::Result<Option<String>> {
+    let offered = state
+        .mime_types
+        .get(&offer.id())
+        .map_or(&[][..], Vec::as_slice);
+    let Some(mime_type) = text_mime_type(offered) else {
+        return Ok(None);
+    };
+    let (mut reader, writer) = std::io::pipe()?;
+    offer.receive(mime_type.to_owned(), writer.as_fd());
+    connection.flush()?;
+    // the owner writes into its copy of the pipe, ours has to close for the
+    // read to see the end
+    drop(writer);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = sender.send(reader.read_to_end(&mut output).map(|_| output));
+    });
+    let output = receiver
+        .recv_timeout(timeout)
+        .with_context(|| format!("the selection's owner didn't answer within {:?}", timeout))??;
+    Ok(Some(decode_lossy(&output)))
+}
+
+fn text_mime_type(offered: &[String]) -> Option<&'static str> {
+    TEXT_MIME_TYPES
+        .iter()
+        .find(|mime_type| offered.iter().any(|offered| offered == *mime_type))
+        .copied()
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        handle: &QueueHandle<Self>,
+    ) {
+        let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        else {
+            return;
+        };
+        if interface == WlSeat::interface().name && state.seat.is_none() {
+            state.seat = Some(registry.bind(name, 1, handle, ()));
+        } else if interface == ZwlrDataControlManagerV1::interface().name && version >= 2 {
+            state.manager = Some(registry.bind(name, 2, handle, ()));
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlSeat,
+        _: <WlSeat as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlManagerV1,
+        _: <ZwlrDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::DataOffer { id } => {
+                state.mime_types.insert(id.id(), Vec::new());
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id } => {
+                if let Some(previous) = std::mem::replace(&mut state.primary, id) {
+                    previous.destroy();
+                }
+            }
+            // the clipboard's offer, not ours to keep
+            zwlr_data_control_device_v1::Event::Selection { id: Some(offer) } => offer.destroy(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state
+                .mime_types
+                .entry(offer.id())
+                .or_default()
+                .push(mime_type);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_mime_type() {
+        let offered = |types: &[&str]| types.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            text_mime_type(&offered(&["TEXT", "text/plain;charset=utf-8"])),
+            Some("text/plain;charset=utf-8")
+        );
+        assert_eq!(
+            text_mime_type(&offered(&["UTF8_STRING", "text/html"])),
+            Some("UTF8_STRING")
+        );
+        assert_eq!(text_mime_type(&offered(&["image/png"])), None);
+    }
+}
@@ -0,0 +1,143 @@
+// "No active window" used to mean the desktop, reported as an app named
+// "Empty Window". It's also what a locked screen, a screensaver or an app
+// without windows looks like, and a capture sent Cmd+C to the login window
+// or asked Finder for a desktop selection nobody made.
+
+use accessibility_ng::{AXAttribute, AXUIElement, AXUIElementAttributes};
+use accessibility_sys_ng::kAXSelectedTextAttribute;
+use active_win_pos_rs::get_active_window;
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::string::CFString;
+use objc2_app_kit::{NSRunningApplication, NSWorkspace};
+
+use crate::{ax, policy, ActiveWindow, CaptureStrategy, Result, SelectedText};
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+}
+
+const FINDER: &str = "com.apple.finder";
+
+/// Elements looked at per window for a selection.
+const WINDOW_SEARCH_BUDGET: usize = 800;
+
+// frontmost while the screen is locked or the screensaver runs
+const LOCK_SCREEN_APPS: &[&str] = &["com.apple.loginwindow", "com.apple.ScreenSaver.Engine"];
+
+pub fn active_window() -> ActiveWindow {
+    if screen_is_locked() {
+        return ActiveWindow::ScreenLocked;
+    }
+    if let Ok(window) = get_active_window() {
+        let pid = window.process_id as i32;
+        let app = unsafe { NSRunningApplication::runningApplicationWithProcessIdentifier(pid) };
+        return ActiveWindow::App {
+            name: window.app_name,
+            bundle_id: app
+                .and_then(|app| unsafe { app.bundleIdentifier() }.map(|id| id.to_string())),
+            pid,
+            title: Some(window.title).filter(|title| !title.is_empty()),
+        };
+    }
+    let workspace = unsafe { NSWorkspace::sharedWorkspace() };
+    let Some(app) = (unsafe { workspace.frontmostApplication() }) else {
+        return ActiveWindow::Unknown;
+    };
+    let bundle_id = unsafe { app.bundleIdentifier() }.map(|id| id.to_string());
+    if bundle_id.as_deref() == Some(FINDER) {
+        return ActiveWindow::Desktop;
+    }
+    ActiveWindow::App {
+        name: unsafe { app.localizedName() }.map_or_else(String::new, |name| name.to_string()),
+        bundle_id,
+        pid: unsafe { app.processIdentifier() },
+        title: None,
+    }
+}
+
+/// Whether the screen is locked or the screensaver is frontmost.
+pub(crate) fn screen_is_locked() -> bool {
+    if crate::frontmost_bundle_id().is_some_and(|id| LOCK_SCREEN_APPS.contains(&id.as_str())) {
+        return true;
+    }
+    // NULL outside a GUI session, e.g. over SSH
+    let session = unsafe { CGSessionCopyCurrentDictionary() };
+    if session.is_null() {
+        return false;
+    }
+    let session: CFDictionary<CFString, CFType> =
+        unsafe { CFDictionary::wrap_under_create_rule(session) };
+    session
+        .find(CFString::from_static_string("CGSSessionScreenIsLocked"))
+        .and_then(|locked| locked.downcast::<CFBoolean>())
+        .is_some_and(bool::from)
+}
+
+/// A selection found in one of the frontmost app's windows.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WindowSelection {
+    pub window_title: Option<String>,
+    /// Redacted and sequenced like any other capture.
+    pub selected_text: SelectedText,
+    /// The window has keyboard focus, `get_selected_text_using_ax_then_copy`
+    /// would have read this selection.
+    pub focused: bool,
+}
+
+/// Every window of the frontmost app that reports a selection, front to
+/// back. Browsers keep the selection of a window the user clicked away
+/// from, which a capture of the focused element misses.
+pub fn get_selections_in_app_windows() -> Result<Vec<WindowSelection>> {
+    policy::ensure_capture_allowed()?;
+    let app = ax::focused_app()?;
+    let focused_window = app.focused_window().ok();
+    let app_name = active_window().app_name().unwrap_or_default().to_owned();
+    let windows = app
+        .windows()
+        .map_err(|e| anyhow::anyhow!("No windows: {:?}", e))?;
+    Ok(windows
+        .iter()
+        .filter_map(|window| {
+            let text = window_selection(&window)?;
+            Some(WindowSelection {
+                window_title: window
+                    .title()
+                    .ok()
+                    .map(|title| title.to_string())
+                    .filter(|title| !title.is_empty()),
+                selected_text: SelectedText::from_text(app_name.clone(), text)
+                    .with_strategy(CaptureStrategy::Ax),
+                focused: focused_window.as_ref() == Some(&*window),
+            })
+        })
+        .collect())
+}
+
+// the first non-empty selection in the window, of a text element or a web
+// area spanning several
+fn window_selection(window: &AXUIElement) -> Option<String> {
+    let read = |element: &AXUIElement| {
+        match ax::role(element).as_deref() {
+            Some("AXWebArea") => ax::text_marker_selection(element),
+            _ => selected_text_of(element),
+        }
+        .filter(|text| !text.is_empty())
+    };
+    let element = ax::find_descendant(window, WINDOW_SEARCH_BUDGET, |element| {
+        read(element).is_some()
+    })?;
+    read(&element)
+}
+
+fn selected_text_of(element: &AXUIElement) -> Option<String> {
+    let text = element
+        .attribute(&AXAttribute::new(&CFString::from_static_string(
+            kAXSelectedTextAttribute,
+        )))
+        .ok()?
+        .downcast_into::<CFString>()?;
+    Some(text.to_string())
+}
@@ -0,0 +1,232 @@
+// UI Automation exposes the selection of native and most browser text
+// controls through TextPattern, the Windows counterpart of AXSelectedText.
+// Controls without it get Ctrl+C, with the clipboard put back afterwards
+// like on macOS.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ::windows::Win32::Foundation::{HANDLE, HGLOBAL, HWND};
+use ::windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+};
+use ::windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData,
+    GetClipboardSequenceNumber, OpenClipboard, SetClipboardData,
+};
+use ::windows::Win32::System::Memory::{
+    GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE,
+};
+use ::windows::Win32::System::Ole::CF_UNICODETEXT;
+use ::windows::Win32::UI::Accessibility::{
+    CUIAutomation, IUIAutomation, IUIAutomationTextPattern, UIA_TextPatternId,
+};
+use ::windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP,
+    VIRTUAL_KEY, VK_CONTROL,
+};
+use active_win_pos_rs::get_active_window;
+use anyhow::bail;
+
+use crate::{
+    diagnostics, ActiveWindow, Result, SelectedText, SelectedTextError, SelectedTextProvider,
+};
+
+const VK_C: VIRTUAL_KEY = VIRTUAL_KEY(0x43);
+
+// CF_BITMAP, CF_METAFILEPICT, CF_PALETTE and CF_ENHMETAFILE hold GDI
+// handles, not global memory, and can't be copied byte for byte
+const GDI_FORMATS: [u32; 4] = [2, 3, 9, 14];
+
+/// UIA first, then Ctrl+C, waiting up to `clipboard_wait_timeout`
+/// milliseconds for the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowsProvider {
+    pub clipboard_wait_timeout: u64,
+}
+
+impl Default for WindowsProvider {
+    fn default() -> Self {
+        WindowsProvider {
+            clipboard_wait_timeout: 90,
+        }
+    }
+}
+
+impl SelectedTextProvider for WindowsProvider {
+    fn active_window(&self) -> ActiveWindow {
+        match get_active_window() {
+            Ok(window) => ActiveWindow::App {
+                name: window.app_name,
+                bundle_id: None,
+                pid: window.process_id as i32,
+                title: Some(window.title).filter(|title| !title.is_empty()),
+            },
+            Err(_) => ActiveWindow::Unknown,
+        }
+    }
+
+    fn get_selected_text(&self) -> Result<SelectedText> {
+        let app_name = self
+            .active_window()
+            .app_name()
+            .unwrap_or_default()
+            .to_owned();
+        match get_selected_text_by_uia() {
+            Ok(Some(text)) => return Ok(SelectedText::from_text(app_name, text)),
+            Ok(None) => {}
+            Err(e) => diagnostics::debug!("UIA: {}", e),
+        }
+        let text = copy_selection(Duration::from_millis(self.clipboard_wait_timeout))?;
+        Ok(SelectedText::from_text(app_name, text.unwrap_or_default()))
+    }
+}
+
+/// The selection of the focused element, `None` when it doesn't support
+/// TextPattern or nothing is selected.
+fn get_selected_text_by_uia() -> anyhow::Result<Option<String>> {
+    unsafe {
+        // already initialized on this thread is fine, any mode works for UIA
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let automation: IUIAutomation =
+            CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER)?;
+        let element = automation.GetFocusedElement()?;
+        let Ok(pattern) =
+            element.GetCurrentPatternAs::<IUIAutomationTextPattern>(UIA_TextPatternId)
+        else {
+            return Ok(None);
+        };
+        let ranges = pattern.GetSelection()?;
+        let mut text = String::new();
+        for i in 0..ranges.Length()? {
+            text.push_str(&ranges.GetElement(i)?.GetText(-1)?.to_string());
+        }
+        Ok(Some(text).filter(|text| !text.is_empty()))
+    }
+}
+
+/// Posts Ctrl+C and reads the copied text, then puts back what the clipboard
+/// held before. `None` when nothing was copied within `timeout`.
+fn copy_selection(timeout: Duration) -> anyhow::Result<Option<String>> {
+    let saved = with_clipboard(save_clipboard)?;
+    let sequence = unsafe { GetClipboardSequenceNumber() };
+    post_ctrl_c()?;
+    let start = Instant::now();
+    while unsafe { GetClipboardSequenceNumber() } == sequence {
+        if start.elapsed() > timeout {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let text = with_clipboard(|| Ok(clipboard_text()));
+    if let Err(e) = with_clipboard(|| restore_clipboard(&saved)) {
+        diagnostics::warn!("Failed to restore the clipboard: {}", e);
+    }
+    text
+}
+
+fn post_ctrl_c() -> anyhow::Result<()> {
+    let key = |key, flags| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: key,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    let inputs = [
+        key(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+        key(VK_C, KEYBD_EVENT_FLAGS(0)),
+        key(VK_C, KEYEVENTF_KEYUP),
+        key(VK_CONTROL, KEYEVENTF_KEYUP),
+    ];
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        return Err(SelectedTextError::KeySimulationFailed(format!(
+            "SendInput posted {} of {} key events",
+            sent,
+            inputs.len()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+// the clipboard can only be open in one process at a time, the app that just
+// copied may still hold it for a moment
+fn with_clipboard<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let start = Instant::now();
+    while !unsafe { OpenClipboard(HWND(0)) }.as_bool() {
+        if start.elapsed() > Duration::from_millis(100) {
+            bail!("Failed to open the clipboard");
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let result = f();
+    unsafe { CloseClipboard() };
+    result
+}
+
+fn clipboard_text() -> Option<String> {
+    let data = global_bytes(CF_UNICODETEXT.0 as u32)?;
+    let utf16: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_ne_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&utf16))
+}
+
+fn save_clipboard() -> anyhow::Result<Vec<(u32, Vec<u8>)>> {
+    let mut saved = Vec::new();
+    let mut format = 0;
+    loop {
+        format = unsafe { EnumClipboardFormats(format) };
+        if format == 0 {
+            return Ok(saved);
+        }
+        if GDI_FORMATS.contains(&format) {
+            continue;
+        }
+        if let Some(data) = global_bytes(format) {
+            saved.push((format, data));
+        }
+    }
+}
+
+fn restore_clipboard(saved: &[(u32, Vec<u8>)]) -> anyhow::Result<()> {
+    unsafe {
+        if !EmptyClipboard().as_bool() {
+            bail!("Failed to empty the clipboard");
+        }
+        for (format, data) in saved {
+            let memory = GlobalAlloc(GMEM_MOVEABLE, data.len())?;
+            let pointer = GlobalLock(memory) as *mut u8;
+            if pointer.is_null() {
+                bail!("Failed to lock clipboard memory");
+            }
+            std::ptr::copy_nonoverlapping(data.as_ptr(), pointer, data.len());
+            GlobalUnlock(memory);
+            // the clipboard owns the memory once this succeeds
+            SetClipboardData(*format, HANDLE(memory.0))?;
+        }
+    }
+    Ok(())
+}
+
+fn global_bytes(format: u32) -> Option<Vec<u8>> {
+    unsafe {
+        let memory = HGLOBAL(GetClipboardData(format).ok()?.0);
+        let pointer = GlobalLock(memory) as *const u8;
+        if pointer.is_null() {
+            return None;
+        }
+        let data = std::slice::from_raw_parts(pointer, GlobalSize(memory)).to_vec();
+        GlobalUnlock(memory);
+        Some(data)
+    }
+}